@@ -1,149 +1,2331 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
 use std::fs;
-use regex::Regex;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+// Add the following dependencies to your Cargo.toml:
+// serde = { version = "1.0", features = ["derive"] }
+// serde_json = "1.0"
+// toml = "0.8"
+// glob = "0.3"
+
+const USAGE: &str = "Usage: cpp-complexity [--format text|json|sarif] [--config FILE] [--max-complexity N] \
+[--fail-on error|warning|note] PATH...
+Each PATH is a file, a directory searched for .cpp, .cc, .h and .hpp files, or a glob such as 'src/**/*.cpp'.
+Exits with 1 when a diagnostic is at least as severe as --fail-on, and with 2 when the input cannot be read.";
+
+// Extensions of the files found in directories
+const SOURCE_EXTENSIONS: &[&str] = &["cpp", "cc", "h", "hpp"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Sarif,
+}
 
 fn main() {
-    // Read the C++ code from a file
-    let code = match fs::read_to_string("your_file.cpp") { 
-        Ok(content) => content,
+    let mut format = Format::Text;
+    let mut config_path = None;
+    let mut max_complexity = None;
+    let mut fail_on = None;
+    let mut inputs = Vec::new();
+
+    let fail = |message: String| -> ! {
+        eprintln!("Error: {}", message);
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| args.next().unwrap_or_else(|| fail(format!("{} needs {}", arg, what)));
+        match arg.as_str() {
+            "--format" => {
+                format = match value("a format").as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "sarif" => Format::Sarif,
+                    other => fail(format!("unknown format `{}`, expected text, json or sarif", other)),
+                }
+            }
+            "--config" => config_path = Some(PathBuf::from(value("a file"))),
+            "--max-complexity" => {
+                let limit = value("a number");
+                max_complexity = Some(limit.parse().unwrap_or_else(|_| fail(format!("invalid --max-complexity `{}`", limit))));
+            }
+            "--fail-on" => {
+                fail_on = Some(match value("a severity").as_str() {
+                    "error" => Severity::Error,
+                    "warning" => Severity::Warning,
+                    "note" => Severity::Note,
+                    other => fail(format!("unknown severity `{}`, expected error, warning or note", other)),
+                })
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            option if option.starts_with("--") => fail(format!("unknown option {}", option)),
+            _ => inputs.push(arg),
+        }
+    }
+    if inputs.is_empty() {
+        fail("no files given".to_owned());
+    }
+
+    // A config given with --config must exist, the default one may not
+    let loaded = match &config_path {
+        Some(path) => load_config(path, false),
+        None => load_config(Path::new(CONFIG_FILE), true),
+    };
+    let mut config = loaded.unwrap_or_else(|err| fail(err));
+    config.max_complexity = max_complexity.unwrap_or(config.max_complexity);
+    config.fail_on = fail_on.unwrap_or(config.fail_on);
+
+    let (paths, mut unreadable) = collect_files(&inputs);
+    let mut reports = Vec::new();
+    for path in paths {
+        match fs::read_to_string(&path) {
+            Ok(code) => reports.push(FileReport::new(&path, &code, &config)),
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path.display(), e);
+                unreadable += 1;
+            }
+        }
+    }
+
+    match format {
+        Format::Text => print_text(&reports),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&json_report(&reports, &config)).unwrap_or_default()),
+        Format::Sarif => println!("{}", serde_json::to_string_pretty(&sarif_report(&reports, &config)).unwrap_or_default()),
+    }
+
+    if unreadable > 0 {
+        process::exit(2);
+    }
+    if failed(&reports, &config) {
+        process::exit(1);
+    }
+}
+
+// The files named by files, directories and globs, sorted without duplicates, and how many
+// inputs matched nothing readable
+fn collect_files(inputs: &[String]) -> (Vec<PathBuf>, usize) {
+    let mut files = Vec::new();
+    let mut missing = 0;
+    for input in inputs {
+        let mut matched = Vec::new();
+        if input.contains(['*', '?', '[']) {
+            match glob::glob(input) {
+                Ok(paths) => matched.extend(paths.filter_map(Result::ok)),
+                Err(e) => {
+                    eprintln!("Error: invalid pattern {}: {}", input, e);
+                    missing += 1;
+                    continue;
+                }
+            }
+        } else {
+            matched.push(PathBuf::from(input));
+        }
+
+        let before = files.len();
+        for path in matched {
+            if path.is_dir() {
+                find_sources(&path, &mut files);
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+        if files.len() == before {
+            eprintln!("Error: {} matches no C++ files", input);
+            missing += 1;
+        }
+    }
+    files.sort();
+    files.dedup();
+    (files, missing)
+}
+
+// Adds the files with a SOURCE_EXTENSIONS extension below `directory`
+fn find_sources(directory: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
         Err(e) => {
-            eprintln!("Error reading file: {}", e);
+            eprintln!("Error reading {}: {}", directory.display(), e);
             return;
         }
     };
-    
-    // Validate the code
-    if !is_valid_cpp(&code) {
-        println!("Validation failed. Aborting analysis.");
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        if path.is_dir() {
+            find_sources(&path, files);
+        } else if path.extension().is_some_and(|extension| SOURCE_EXTENSIONS.iter().any(|known| extension == *known)) {
+            files.push(path);
+        }
+    }
+}
+
+// Everything found in one file
+struct FileReport {
+    path: String,
+    functions: Vec<FunctionReport>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl FileReport {
+    fn new(path: &Path, code: &str, config: &Config) -> Self {
+        // Rule violations are reported, and the analysis goes on regardless
+        let source = Source::new(code);
+        let diagnostics = lint(&source, config);
+        let mut functions: Vec<FunctionReport> = source.declarations.functions.iter().map(FunctionReport::new).collect();
+        functions.sort_by(|a, b| b.complexity.cmp(&a.complexity).then(a.line.cmp(&b.line)));
+        FileReport { path: path.display().to_string(), functions, diagnostics }
+    }
+}
+
+// True when a diagnostic reaches the severity that fails the run
+fn failed(reports: &[FileReport], config: &Config) -> bool {
+    reports.iter().flat_map(|report| &report.diagnostics).any(|diagnostic| diagnostic.severity >= config.fail_on)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    Identifier,
+    Number,
+    // String and character literals, whose content is never looked at
+    Literal,
+    Punctuation,
+    // Only in the comments returned next to the tokens
+    Comment,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn is(&self, text: &str) -> bool {
+        self.kind != TokenKind::Literal && self.text == text
+    }
+}
+
+// Longest first, so that `<<=` is not read as `<<` and `=`
+const PUNCTUATORS: &[&str] = &[
+    "<<=", ">>=", "<=>", "->*", "...", "::", "->", "++", "--", "&&", "||", "<<", ">>", "<=", ">=", "==", "!=", "+=",
+    "-=", "*=", "/=", "%=", "&=", "|=", "^=", ".*",
+];
+
+// Prefixes of string and character literals such as `L"wide"` and `u8R"(raw)"`
+const LITERAL_PREFIXES: &[&str] = &["L", "u", "U", "u8", "R", "LR", "uR", "UR", "u8R"];
+
+// Splits C++ source into tokens with 1-based positions, and returns the comments apart.
+// Preprocessor lines are dropped, and every string or character literal becomes a single
+// Literal token.
+fn tokenize(code: &str) -> (Vec<Token>, Vec<Token>) {
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens = Vec::new();
+    let mut comments = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+    let mut column = 1;
+    // Only whitespace so far on this line, where `#` starts a directive
+    let mut line_start = true;
+
+    // Moves past `count` characters, keeping track of lines
+    let advance = |i: &mut usize, line: &mut usize, column: &mut usize, count: usize| {
+        for _ in 0..count {
+            if *i >= chars.len() {
+                return;
+            }
+            if chars[*i] == '\n' {
+                *line += 1;
+                *column = 1;
+            } else {
+                *column += 1;
+            }
+            *i += 1;
+        }
+    };
+    let starts_with = |i: usize, text: &str| text.chars().enumerate().all(|(offset, c)| chars.get(i + offset) == Some(&c));
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            if c == '\n' {
+                line_start = true;
+            }
+            advance(&mut i, &mut line, &mut column, 1);
+            continue;
+        }
+        if c == '#' && line_start {
+            // A directive runs to the end of the line, including lines continued with `\`
+            while i < chars.len() && chars[i] != '\n' {
+                let step = if chars[i] == '\\' && chars.get(i + 1) == Some(&'\n') { 2 } else { 1 };
+                advance(&mut i, &mut line, &mut column, step);
+            }
+            continue;
+        }
+        if starts_with(i, "//") || starts_with(i, "/*") {
+            let (start, start_line, start_column) = (i, line, column);
+            if starts_with(i, "//") {
+                while i < chars.len() && chars[i] != '\n' {
+                    advance(&mut i, &mut line, &mut column, 1);
+                }
+            } else {
+                advance(&mut i, &mut line, &mut column, 2);
+                while i < chars.len() && !starts_with(i, "*/") {
+                    advance(&mut i, &mut line, &mut column, 1);
+                }
+                advance(&mut i, &mut line, &mut column, 2);
+            }
+            comments.push(Token {
+                kind: TokenKind::Comment,
+                text: chars[start..i].iter().collect(),
+                line: start_line,
+                column: start_column,
+            });
+            continue;
+        }
+
+        line_start = false;
+        let (start, start_line, start_column) = (i, line, column);
+        let kind = if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|next| next.is_ascii_digit())) {
+            // Digits, digit separators, suffixes and signed exponents such as `1'000`, `0x1Fu` and `1e-9`
+            while i < chars.len() {
+                let current = chars[i];
+                let exponent = matches!(current, 'e' | 'E' | 'p' | 'P') && matches!(chars.get(i + 1), Some('+' | '-'));
+                if exponent {
+                    advance(&mut i, &mut line, &mut column, 2);
+                } else if current.is_alphanumeric() || current == '.' || current == '_' || current == '\'' {
+                    advance(&mut i, &mut line, &mut column, 1);
+                } else {
+                    break;
+                }
+            }
+            TokenKind::Number
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                advance(&mut i, &mut line, &mut column, 1);
+            }
+            let word: String = chars[start..i].iter().collect();
+            match chars.get(i) {
+                Some('"') if word.ends_with('R') && LITERAL_PREFIXES.contains(&word.as_str()) => {
+                    skip_raw_string(&chars, &mut i, &mut line, &mut column, &advance);
+                    TokenKind::Literal
+                }
+                Some(&quote @ ('"' | '\'')) if LITERAL_PREFIXES.contains(&word.as_str()) => {
+                    skip_quoted(&chars, quote, &mut i, &mut line, &mut column, &advance);
+                    TokenKind::Literal
+                }
+                _ => TokenKind::Identifier,
+            }
+        } else if c == '"' || c == '\'' {
+            skip_quoted(&chars, c, &mut i, &mut line, &mut column, &advance);
+            TokenKind::Literal
+        } else {
+            let length = PUNCTUATORS.iter().find(|punctuator| starts_with(i, punctuator)).map_or(1, |punctuator| punctuator.len());
+            advance(&mut i, &mut line, &mut column, length);
+            TokenKind::Punctuation
+        };
+        tokens.push(Token {
+            kind,
+            text: chars[start..i].iter().collect(),
+            line: start_line,
+            column: start_column,
+        });
+    }
+    (tokens, comments)
+}
+
+type Advance<'a> = dyn Fn(&mut usize, &mut usize, &mut usize, usize) + 'a;
+
+// Moves past a literal opened by `quote` at `i`, up to its closing quote or the end of the line
+fn skip_quoted(chars: &[char], quote: char, i: &mut usize, line: &mut usize, column: &mut usize, advance: &Advance) {
+    advance(i, line, column, 1);
+    while *i < chars.len() && chars[*i] != quote && chars[*i] != '\n' {
+        let step = if chars[*i] == '\\' { 2 } else { 1 };
+        advance(i, line, column, step);
+    }
+    if chars.get(*i) == Some(&quote) {
+        advance(i, line, column, 1);
+    }
+}
+
+// Moves past a raw string `"delimiter( ... )delimiter"` starting at the quote at `i`
+fn skip_raw_string(chars: &[char], i: &mut usize, line: &mut usize, column: &mut usize, advance: &Advance) {
+    let open = chars[*i..].iter().position(|&c| c == '(' || c == '\n').map_or(chars.len(), |offset| *i + offset);
+    let closing: Vec<char> = std::iter::once(')').chain(chars[*i + 1..open].iter().copied()).chain(std::iter::once('"')).collect();
+    advance(i, line, column, open - *i);
+    while *i < chars.len() && !chars[*i..].starts_with(&closing) {
+        advance(i, line, column, 1);
+    }
+    advance(i, line, column, closing.len());
+}
+
+#[derive(Debug, Clone)]
+struct Function {
+    // Qualified with the enclosing namespaces and classes, e.g. `geometry::Polygon::area`
+    name: String,
+    line: usize,
+    column: usize,
+    end_line: usize,
+    // The tokens between the braces of the body
+    body: Vec<Token>,
+}
+
+// A brace that opens a namespace or class body; functions are skipped as a whole
+enum Scope {
+    Namespace(String),
+    Class(String),
+}
+
+// What the declaration before a `{` opens
+enum Block {
+    Scope(Scope),
+    Function(String),
+    // Anything else, such as an enum or a brace initializer, which is skipped
+    Other,
+}
+
+// Words that can precede `(` at declaration level without naming a function
+const NOT_FUNCTION_NAMES: &[&str] = &[
+    "alignas", "alignof", "catch", "decltype", "noexcept", "requires", "sizeof", "static_assert", "__attribute__", "__declspec",
+];
+
+// What may follow the parameter list of a function definition
+const FUNCTION_QUALIFIERS: &[&str] = &["const", "volatile", "noexcept", "override", "final", "mutable", "throw", "try", "&", "&&"];
+
+// What a translation unit declares outside function bodies
+struct Declarations {
+    // Function definitions, including methods defined inside classes
+    functions: Vec<Function>,
+    // Token ranges of the declarations ending in `;` at namespace scope, such as globals
+    // and prototypes
+    globals: Vec<Range<usize>>,
+}
+
+fn find_declarations(tokens: &[Token]) -> Declarations {
+    let mut functions = Vec::new();
+    let mut globals = Vec::new();
+    let mut scopes: Vec<Scope> = Vec::new();
+    // First token of the declaration being read
+    let mut start = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if token.is(";") {
+            if start < i && scopes.iter().all(|scope| matches!(scope, Scope::Namespace(_))) {
+                globals.push(start..i);
+            }
+            start = i + 1;
+        } else if token.is("}") {
+            scopes.pop();
+            start = i + 1;
+        } else if token.is(":") && i == start + 1 && ["public", "protected", "private"].iter().any(|label| tokens[start].is(label)) {
+            start = i + 1;
+        } else if token.is("{") {
+            let mut end = matching(tokens, i);
+            match classify(&tokens[start..i]) {
+                Block::Scope(scope) => {
+                    scopes.push(scope);
+                    start = i + 1;
+                }
+                Block::Function(name) => {
+                    // The handlers of a function-try-block are part of the function
+                    while tokens.get(end + 1).is_some_and(|next| next.is("catch")) {
+                        match tokens[end + 1..].iter().position(|token| token.is("{")) {
+                            Some(offset) => end = matching(tokens, end + 1 + offset),
+                            None => break,
+                        }
+                    }
+                    let qualifiers: Vec<&str> = scopes
+                        .iter()
+                        .map(|scope| match scope {
+                            Scope::Namespace(name) | Scope::Class(name) => name.as_str(),
+                        })
+                        .filter(|name| !name.is_empty())
+                        .collect();
+                    let mut qualified = qualifiers.join("::");
+                    if !qualified.is_empty() {
+                        qualified.push_str("::");
+                    }
+                    qualified.push_str(&name);
+                    functions.push(Function {
+                        name: qualified,
+                        line: tokens[start].line,
+                        column: tokens[start].column,
+                        end_line: tokens.get(end).map_or(token.line, |close| close.line),
+                        body: tokens[i + 1..end.min(tokens.len())].to_vec(),
+                    });
+                    start = end + 1;
+                    i = end;
+                }
+                // The declaration goes on after the braces, as in `int values[] = {1, 2};`
+                Block::Other => i = end,
+            }
+        }
+        i += 1;
+    }
+    Declarations { functions, globals }
+}
+
+// The index of the bracket closing the one at `open`, or the end of the tokens
+fn matching(tokens: &[Token], open: usize) -> usize {
+    let (opening, closing) = match tokens[open].text.as_str() {
+        "(" => ("(", ")"),
+        "[" => ("[", "]"),
+        _ => ("{", "}"),
+    };
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        if token.is(opening) {
+            depth += 1;
+        } else if token.is(closing) {
+            depth -= 1;
+            if depth == 0 {
+                return index;
+            }
+        }
+    }
+    tokens.len()
+}
+
+fn classify(declaration: &[Token]) -> Block {
+    let Some(first) = declaration_start(declaration) else {
+        return Block::Other;
+    };
+    let declaration = &declaration[first..];
+    let texts: Vec<&str> = declaration.iter().map(|token| token.text.as_str()).collect();
+
+    match texts.as_slice() {
+        ["namespace", name @ ..] | ["inline", "namespace", name @ ..] => return Block::Scope(Scope::Namespace(name.concat())),
+        ["extern", _] if declaration[1].kind == TokenKind::Literal => return Block::Scope(Scope::Namespace(String::new())),
+        _ => {}
+    }
+    if let Some(name) = function_name(declaration) {
+        return name;
+    }
+    if declaration.iter().any(|token| token.is("enum") || token.is("=")) {
+        return Block::Other;
+    }
+    match declaration.iter().position(|token| token.is("class") || token.is("struct") || token.is("union")) {
+        Some(key) => {
+            let name = declaration[key + 1..]
+                .iter()
+                .take_while(|token| token.kind == TokenKind::Identifier || token.is("::"))
+                .filter(|token| !token.is("final"))
+                .map(|token| token.text.as_str())
+                .collect::<String>();
+            Block::Scope(Scope::Class(name))
+        }
+        None => Block::Other,
+    }
+}
+
+// The index after `template <...>` and attributes such as `[[nodiscard]]` at the start of a
+// declaration
+fn declaration_start(declaration: &[Token]) -> Option<usize> {
+    let mut first = 0;
+    loop {
+        match declaration.get(first) {
+            Some(token) if token.is("template") && declaration.get(first + 1).is_some_and(|next| next.is("<")) => {
+                let mut depth = 0;
+                for (index, token) in declaration.iter().enumerate().skip(first + 1) {
+                    if token.is("<") {
+                        depth += 1;
+                    } else if token.is(">") || token.is(">>") {
+                        depth -= token.text.len() as i32;
+                        if depth <= 0 {
+                            first = index + 1;
+                            break;
+                        }
+                    }
+                }
+                if depth > 0 {
+                    return None;
+                }
+            }
+            Some(token) if token.is("[") && declaration.get(first + 1).is_some_and(|next| next.is("[")) => {
+                first = matching(declaration, first) + 1;
+            }
+            _ => break,
+        }
+    }
+    Some(first.min(declaration.len()))
+}
+
+// A function definition is a name, its parameters, qualifiers and perhaps a trailing return
+// type or a constructor's initializer list before the body
+fn function_name(declaration: &[Token]) -> Option<Block> {
+    let mut open = declaration.iter().position(|token| token.is("("))?;
+    if open > 0 && declaration[open - 1].is("operator") && declaration.get(open + 1).is_some_and(|next| next.is(")")) {
+        // `operator()(...)`
+        open += 2;
+        if !declaration.get(open).is_some_and(|next| next.is("(")) {
+            return None;
+        }
+    }
+
+    let mut name_start = open.checked_sub(1)?;
+    let base = &declaration[name_start];
+    if name_start >= 1 && declaration[name_start - 1].is("operator") {
+        name_start -= 1;
+    } else if declaration[name_start].is(")") && name_start >= 2 && declaration[name_start - 2].is("operator") {
+        name_start -= 2;
+    } else if base.kind != TokenKind::Identifier || NOT_FUNCTION_NAMES.contains(&base.text.as_str()) {
+        return None;
+    }
+    if name_start >= 1 && declaration[name_start - 1].is("~") {
+        name_start -= 1;
+    }
+    while name_start >= 2 && declaration[name_start - 1].is("::") && declaration[name_start - 2].kind == TokenKind::Identifier {
+        name_start -= 2;
+    }
+
+    let mut next = matching(declaration, open) + 1;
+    while let Some(token) = declaration.get(next) {
+        if token.is("->") || token.is("requires") {
+            next = declaration.len();
+        } else if FUNCTION_QUALIFIERS.contains(&token.text.as_str()) {
+            next += 1;
+            if declaration.get(next).is_some_and(|token| token.is("(")) {
+                next = matching(declaration, next) + 1;
+            }
+        } else if token.is("[") {
+            next = matching(declaration, next) + 1;
+        } else {
+            break;
+        }
+    }
+    match declaration.get(next) {
+        None => {}
+        // Braces in an initializer list such as `: values{}` belong to a member, not the body
+        Some(token) if token.is(":") && !declaration.last().is_some_and(|last| last.is(")") || last.is("}")) => {
+            return Some(Block::Other);
+        }
+        Some(token) if token.is(":") => {}
+        Some(_) => return None,
+    }
+
+    let mut name = String::new();
+    for (index, token) in declaration[name_start..open].iter().enumerate() {
+        // `operator bool` keeps its space
+        if index > 0 && token.kind == TokenKind::Identifier && declaration[name_start + index - 1].kind == TokenKind::Identifier {
+            name.push(' ');
+        }
+        name.push_str(&token.text);
+    }
+    Some(Block::Function(name))
+}
+
+// Tokens that add a path through a function: branches, loops, handlers, cases and the
+// short-circuit operators, including their alternative spellings
+const DECISION_POINTS: &[&str] = &["if", "for", "while", "case", "catch", "&&", "||", "?", "and", "or"];
+
+#[derive(Debug, Clone)]
+struct FunctionReport {
+    name: String,
+    line: usize,
+    column: usize,
+    lines: usize,
+    complexity: usize,
+    estimate: Estimate,
+}
+
+impl FunctionReport {
+    fn new(function: &Function) -> Self {
+        FunctionReport {
+            name: function.name.clone(),
+            line: function.line,
+            column: function.column,
+            lines: function.end_line - function.line + 1,
+            complexity: cyclomatic_complexity(&function.body),
+            estimate: estimate(function),
+        }
+    }
+}
+
+// McCabe's complexity: one more than the decision points of the body. Lambdas count
+// towards the function they are written in.
+fn cyclomatic_complexity(body: &[Token]) -> usize {
+    1 + body
+        .iter()
+        .filter(|token| token.kind != TokenKind::Literal && DECISION_POINTS.contains(&token.text.as_str()))
+        .count()
+}
+
+// An upper bound in one input size n: n^degree * log^log n, base^n, or none at all
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Order {
+    Polynomial { degree: u32, log: u32 },
+    Exponential(usize),
+    Unknown,
+}
+
+const CONSTANT: Order = Order::Polynomial { degree: 0, log: 0 };
+const LINEAR: Order = Order::Polynomial { degree: 1, log: 0 };
+const LOGARITHMIC: Order = Order::Polynomial { degree: 0, log: 1 };
+
+impl Order {
+    // The cost of running `inner` this many times
+    fn times(self, inner: Order) -> Order {
+        match (self, inner) {
+            (Order::Unknown, _) | (_, Order::Unknown) => Order::Unknown,
+            (Order::Exponential(base), _) | (_, Order::Exponential(base)) => Order::Exponential(base),
+            (Order::Polynomial { degree, log }, Order::Polynomial { degree: inner_degree, log: inner_log }) => {
+                Order::Polynomial { degree: degree + inner_degree, log: log + inner_log }
+            }
+        }
+    }
+
+    // The cost of running one after the other
+    fn max(self, other: Order) -> Order {
+        match (self, other) {
+            (Order::Unknown, _) | (_, Order::Unknown) => Order::Unknown,
+            (Order::Exponential(base), Order::Exponential(other_base)) => Order::Exponential(base.max(other_base)),
+            (Order::Exponential(base), _) | (_, Order::Exponential(base)) => Order::Exponential(base),
+            (Order::Polynomial { degree, log }, Order::Polynomial { degree: other_degree, log: other_log }) => {
+                if (degree, log) >= (other_degree, other_log) {
+                    self
+                } else {
+                    other
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Order::Unknown => write!(f, "unknown"),
+            Order::Exponential(base) => write!(f, "O({}ⁿ)", base),
+            Order::Polynomial { degree: 0, log: 0 } => write!(f, "O(1)"),
+            Order::Polynomial { degree, log } => {
+                let mut parts = Vec::new();
+                match degree {
+                    0 => {}
+                    1 => parts.push("n".to_owned()),
+                    _ => parts.push(format!("n{}", superscript(degree))),
+                }
+                match log {
+                    0 => {}
+                    1 => parts.push("log n".to_owned()),
+                    _ => parts.push(format!("log{} n", superscript(log))),
+                }
+                write!(f, "O({})", parts.join(" "))
+            }
+        }
+    }
+}
+
+fn superscript(value: u32) -> String {
+    const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    value.to_string().chars().map(|digit| DIGITS[digit.to_digit(10).unwrap_or(0) as usize]).collect()
+}
+
+#[derive(Debug, Clone)]
+struct Estimate {
+    order: Order,
+    // How the order was reached, one step per line
+    reasons: Vec<String>,
+}
+
+// A loop of a function body with the loops nested in it
+struct LoopNode {
+    // Tokens of the body from the loop keyword to the end of the loop
+    range: Range<usize>,
+    runs: Order,
+    reason: String,
+    children: Vec<LoopNode>,
+}
+
+impl LoopNode {
+    fn cost(&self) -> Order {
+        let inner = self.children.iter().fold(CONSTANT, |cost, child| cost.max(child.cost()));
+        self.runs.times(inner)
+    }
+
+    fn collect_reasons(&self, reasons: &mut Vec<String>) {
+        reasons.push(self.reason.clone());
+        for child in &self.children {
+            child.collect_reasons(reasons);
+        }
+    }
+
+    fn count(&self) -> usize {
+        1 + self.children.iter().map(LoopNode::count).sum::<usize>()
+    }
+}
+
+// How often a loop runs and the variable it counts with, if any
+struct LoopBound {
+    runs: Order,
+    reason: String,
+    variable: Option<String>,
+}
+
+impl LoopBound {
+    fn unknown(reason: String) -> Self {
+        LoopBound { runs: Order::Unknown, reason, variable: None }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StepKind {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+// A change of a variable: a step by an amount, which is None unless it is a literal, or
+// any other assignment
+#[derive(Debug, Clone)]
+enum Update {
+    Step(StepKind, Option<u64>),
+    Assign(Vec<Token>),
+}
+
+// Estimates the growth of a function from its loop nesting and direct recursion. Nested loops
+// multiply, consecutive ones take the largest, and anything that is not recognised makes the
+// whole estimate unknown. Calls of other functions count as O(1).
+fn estimate(function: &Function) -> Estimate {
+    let body = &function.body;
+    let halved = halved_variables(body);
+    let loops = find_loops(body, 0..body.len(), &[], &halved);
+    let mut reasons = Vec::new();
+    for node in &loops {
+        node.collect_reasons(&mut reasons);
+    }
+    let work = loops.iter().fold(CONSTANT, |cost, node| cost.max(node.cost()));
+    if loops.is_empty() {
+        reasons.push("no loops, so O(1) work per call".to_owned());
+    } else if loops.iter().map(LoopNode::count).sum::<usize>() > 1 {
+        reasons.push(format!("nested loops multiply and consecutive ones take the largest: {}", work));
+    }
+
+    let short_name = function.name.rsplit("::").next().unwrap_or(&function.name);
+    let calls = recursive_calls(body, short_name, &halved);
+    if calls.is_empty() || work == Order::Unknown {
+        return Estimate { order: work, reasons };
+    }
+    let mut call_lines: Vec<String> = calls.iter().map(|(index, _)| body[*index].line.to_string()).collect();
+    call_lines.dedup();
+    let lines = format!("{} {}", if call_lines.len() == 1 { "line" } else { "lines" }, call_lines.join(", "));
+    if let Some((index, _)) = calls.iter().find(|(index, _)| loops.iter().any(|node| node.range.contains(index))) {
+        reasons.push(format!("line {}: a recursive call inside a loop is not handled", body[*index].line));
+        return Estimate { order: Order::Unknown, reasons };
+    }
+
+    let count = calls_per_path(body, &calls);
+    let shrinks: Vec<Option<u64>> = calls.iter().map(|(_, shrink)| *shrink).collect();
+    let order = match shrinks[0] {
+        _ if shrinks.iter().any(|shrink| *shrink != shrinks[0]) => {
+            reasons.push(format!("{}: the recursive calls shrink their input differently", lines));
+            Order::Unknown
+        }
+        // Smaller by a constant
+        Some(1) if count == 1 => {
+            let order = LINEAR.times(work);
+            reasons.push(format!("{}: one recursive call on an input a constant smaller, n calls deep: {}", lines, order));
+            order
+        }
+        Some(1) => {
+            let order = Order::Exponential(count);
+            reasons.push(format!("{}: {} recursive calls on an input a constant smaller: {}", lines, count, order));
+            order
+        }
+        Some(divisor) => {
+            let order = master_theorem(count, divisor, work);
+            let calls = if count == 1 { "one recursive call".to_owned() } else { format!("{} recursive calls", count) };
+            match order {
+                Order::Unknown => reasons.push(format!("{}: {} on 1/{} of the input is not handled", lines, calls, divisor)),
+                _ => reasons.push(format!(
+                    "{}: {} on 1/{} of the input with {} other work, by the master theorem: {}",
+                    lines, calls, divisor, work, order
+                )),
+            }
+            order
+        }
+        None => {
+            reasons.push(format!("{}: cannot tell how the recursive calls shrink their input", lines));
+            Order::Unknown
+        }
+    };
+    Estimate { order, reasons }
+}
+
+// The most recursive calls made by one run of the body, where calls separated by `else` or
+// by the `:` of a conditional expression are alternatives, and so is a call after one whose
+// path returns, as in `if (x < a[mid]) return search(lo, mid); return search(mid, hi);`
+fn calls_per_path(body: &[Token], calls: &[(usize, Option<u64>)]) -> usize {
+    let mut most = 0;
+    let mut current = 0;
+    let mut previous: Option<usize> = None;
+    for (index, _) in calls {
+        let alternative = previous.is_some_and(|previous| {
+            let between = &body[previous..*index];
+            let statement = body[..previous].iter().rposition(|token| token.is(";") || token.is("{") || token.is("}")).map_or(0, |start| start + 1);
+            let conditional = body[statement..previous].iter().any(|token| token.is("?"));
+            between.iter().any(|token| token.is("else") || (conditional && token.is(":")))
+                || returns_between(body, statement, previous, *index)
+        });
+        current = if alternative { 1 } else { current + 1 };
+        most = most.max(current);
+        previous = Some(*index);
+    }
+    most
+}
+
+// Whether the path through the call at `call`, in the statement from `statement`, returns
+// before it reaches `next`: the call is returned, or a later statement of its block that
+// is not under an `if` or a loop returns. The statement holding `next` is not looked at,
+// as its own `return` comes after both calls
+fn returns_between(body: &[Token], statement: usize, call: usize, next: usize) -> bool {
+    let ends = body[call..next].iter().any(|token| token.is(";"));
+    if ends && body[statement..call].iter().any(|token| token.is("return")) {
+        return true;
+    }
+    let stop = body[call..next].iter().rposition(|token| token.is(";") || token.is("{") || token.is("}")).map_or(call, |end| call + end + 1);
+    let mut depth = 0i32;
+    let mut lowest = 0;
+    for (offset, token) in body[call..stop].iter().enumerate() {
+        if token.is("{") {
+            depth += 1;
+        } else if token.is("}") {
+            depth -= 1;
+            lowest = lowest.min(depth);
+        } else if token.is("return") && depth == lowest {
+            let before = &body[call + offset - 1];
+            if before.is(";") || before.is("{") || before.is("}") {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+// T(n) = calls * T(n / divisor) + work, for numbers of calls that are a power of the divisor
+fn master_theorem(calls: usize, divisor: u64, work: Order) -> Order {
+    let Order::Polynomial { degree, log } = work else {
+        return Order::Unknown;
+    };
+    let Some(critical) = (0..=8).find(|power| divisor.checked_pow(*power) == Some(calls as u64)) else {
+        return Order::Unknown;
+    };
+    match degree.cmp(&critical) {
+        std::cmp::Ordering::Less => Order::Polynomial { degree: critical, log: 0 },
+        std::cmp::Ordering::Equal => Order::Polynomial { degree, log: log + 1 },
+        std::cmp::Ordering::Greater => work,
+    }
+}
+
+// Calls of the function itself and what they shrink their input by: Some(1) for a constant
+// such as `n - 1`, Some(divisor) for a part such as `n / 2` or a midpoint, None when unknown
+fn recursive_calls(body: &[Token], name: &str, halved: &HashMap<String, u64>) -> Vec<(usize, Option<u64>)> {
+    if name.starts_with("operator") || name.starts_with('~') {
+        return Vec::new();
+    }
+    let mut calls = Vec::new();
+    for (index, token) in body.iter().enumerate() {
+        if token.kind != TokenKind::Identifier || token.text != name || !body.get(index + 1).is_some_and(|next| next.is("(")) {
+            continue;
+        }
+        let member = index > 0 && (body[index - 1].is(".") || body[index - 1].is("::") || body[index - 1].is("->"));
+        if member && !(index > 1 && body[index - 1].is("->") && body[index - 2].is("this")) {
+            continue;
+        }
+        let arguments = &body[index + 2..matching(body, index + 1).min(body.len())];
+        calls.push((index, shrink_of(arguments, halved)));
+    }
+    calls
+}
+
+fn shrink_of(arguments: &[Token], halved: &HashMap<String, u64>) -> Option<u64> {
+    if let Some(divisor) = divisor_of(arguments) {
+        return Some(divisor);
+    }
+    if let Some(divisor) = arguments.iter().find_map(|token| halved.get(&token.text)) {
+        return Some(*divisor);
+    }
+    let by_constant = arguments.windows(3).any(|window| {
+        let operand = matches!(window[0].kind, TokenKind::Identifier | TokenKind::Number) || window[0].is(")") || window[0].is("]");
+        operand && (window[1].is("-") || window[1].is("+")) && window[2].kind == TokenKind::Number
+    });
+    by_constant.then_some(1)
+}
+
+// The literal divisor of `/ k` or `>> k` in an expression
+fn divisor_of(expression: &[Token]) -> Option<u64> {
+    expression.windows(2).find_map(|window| {
+        let amount = literal(&window[1])?;
+        if window[0].is("/") && amount > 1 {
+            Some(amount)
+        } else if window[0].is(">>") && (1..64).contains(&amount) {
+            Some(1 << amount)
+        } else {
+            None
+        }
+    })
+}
+
+fn literal(token: &Token) -> Option<u64> {
+    if token.kind != TokenKind::Number {
+        return None;
+    }
+    let digits: String = token.text.chars().filter(|c| *c != '\'').collect();
+    let digits = digits.trim_end_matches(['u', 'U', 'l', 'L']);
+    match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => digits.parse().ok(),
+    }
+}
+
+// Variables assigned a part of something, such as `mid = (low + high) / 2`, with the divisor
+fn halved_variables(body: &[Token]) -> HashMap<String, u64> {
+    let mut halved = HashMap::new();
+    for (index, token) in body.iter().enumerate() {
+        if token.kind != TokenKind::Identifier || !body.get(index + 1).is_some_and(|next| next.is("=")) {
+            continue;
+        }
+        let value = expression_at(body, index + 2);
+        if let Some(divisor) = divisor_of(value) {
+            halved.insert(token.text.clone(), divisor);
+        }
+    }
+    halved
+}
+
+// The expression starting at `start`, up to a `;` or `,` outside brackets
+fn expression_at(tokens: &[Token], start: usize) -> &[Token] {
+    let mut index = start;
+    while index < tokens.len() {
+        let token = &tokens[index];
+        if token.is("(") || token.is("[") || token.is("{") {
+            index = matching(tokens, index) + 1;
+            continue;
+        }
+        if token.is(";") || token.is(",") || token.is(")") || token.is("]") || token.is("}") {
+            break;
+        }
+        index += 1;
+    }
+    &tokens[start.min(tokens.len())..index.min(tokens.len())]
+}
+
+// The index after the statement starting at `start`
+fn statement_end(tokens: &[Token], start: usize) -> usize {
+    let Some(token) = tokens.get(start) else {
+        return tokens.len();
+    };
+    if token.is("{") {
+        return matching(tokens, start) + 1;
+    }
+    if ["if", "for", "while", "switch"].iter().any(|keyword| token.is(keyword)) {
+        let open = if tokens.get(start + 1).is_some_and(|next| next.is("constexpr")) { start + 2 } else { start + 1 };
+        if tokens.get(open).is_some_and(|next| next.is("(")) {
+            let end = statement_end(tokens, matching(tokens, open) + 1);
+            if token.is("if") && tokens.get(end).is_some_and(|next| next.is("else")) {
+                return statement_end(tokens, end + 1);
+            }
+            return end;
+        }
+    }
+    if token.is("do") {
+        let body_end = statement_end(tokens, start + 1);
+        if tokens.get(body_end + 1).is_some_and(|next| next.is("(")) {
+            return matching(tokens, body_end + 1) + 2;
+        }
+        return body_end;
+    }
+    if token.is("try") {
+        let mut end = statement_end(tokens, start + 1);
+        while tokens.get(end).is_some_and(|next| next.is("catch")) && tokens.get(end + 1).is_some_and(|next| next.is("(")) {
+            end = statement_end(tokens, matching(tokens, end + 1) + 1);
+        }
+        return end;
+    }
+    // An expression statement up to its `;`, lambda bodies included
+    let mut index = start;
+    while index < tokens.len() {
+        let token = &tokens[index];
+        if token.is("(") || token.is("[") || token.is("{") {
+            index = matching(tokens, index) + 1;
+        } else if token.is(";") {
+            return index + 1;
+        } else {
+            index += 1;
+        }
+    }
+    tokens.len()
+}
+
+// The loops within `range` of the body, `outer` being the variables of the enclosing loops
+fn find_loops(body: &[Token], range: Range<usize>, outer: &[String], halved: &HashMap<String, u64>) -> Vec<LoopNode> {
+    let mut loops = Vec::new();
+    let mut index = range.start;
+    while index < range.end {
+        let token = &body[index];
+        let end = statement_end(body, index).min(range.end);
+        let (bound, inner) = if (token.is("for") || token.is("while")) && body.get(index + 1).is_some_and(|next| next.is("(")) {
+            let close = matching(body, index + 1).min(end);
+            if close >= end {
+                // The header is cut off by the end of the input
+                let bound = LoopBound::unknown("has a header that is not closed: unknown".to_owned());
+                loops.push(LoopNode {
+                    range: index..end,
+                    runs: bound.runs,
+                    reason: format!("line {}: `{}` {}", token.line, source_text(&body[index..end]), bound.reason),
+                    children: Vec::new(),
+                });
+                index = end.max(index + 1);
+                continue;
+            }
+            let header = index + 2..close;
+            let inner = close + 1..end;
+            let bound = if token.is("for") {
+                analyze_for(body, header, inner.clone(), outer)
+            } else {
+                analyze_while(body, header, inner.clone(), outer, halved)
+            };
+            (bound, inner)
+        } else if token.is("do") {
+            let body_end = statement_end(body, index + 1).min(end);
+            let open = (body_end + 1).min(end);
+            let close = if open < end { matching(body, open).min(end) } else { end };
+            let inner = index + 1..body_end;
+            (analyze_while(body, (open + 1).min(close)..close, inner.clone(), outer, halved), inner)
+        } else {
+            index += 1;
+            continue;
+        };
+
+        let mut nested_outer = outer.to_vec();
+        nested_outer.extend(bound.variable.clone());
+        let header_text = source_text(&body[index..inner.start.max(index + 1)]);
+        let (header_text, line) = if token.is("do") {
+            (format!("do ... {}", source_text(&body[inner.end..end])), body[index].line)
+        } else {
+            (header_text, body[index].line)
+        };
+        loops.push(LoopNode {
+            range: index..end,
+            runs: bound.runs,
+            reason: format!("line {}: `{}` {}", line, header_text.trim_end_matches(';'), bound.reason),
+            children: find_loops(body, inner, &nested_outer, halved),
+        });
+        index = end.max(index + 1);
+    }
+    loops
+}
+
+fn analyze_for(body: &[Token], header: Range<usize>, inner: Range<usize>, outer: &[String]) -> LoopBound {
+    let tokens = &body[header.clone()];
+    let parts = split_top_level(tokens, ";");
+    if parts.len() != 3 {
+        return match tokens.iter().position(|token| token.is(":")) {
+            Some(colon) => {
+                let range = &tokens[colon + 1..];
+                LoopBound {
+                    runs: LINEAR,
+                    reason: format!("runs once per element of {}: O(n){}", source_text(range), dependence(range, outer)),
+                    variable: tokens[..colon].iter().rev().find(|token| token.kind == TokenKind::Identifier).map(|token| token.text.clone()),
+                }
+            }
+            None => LoopBound::unknown("has a header that cannot be read: unknown".to_owned()),
+        };
+    }
+    let (initializer, condition, step) = (parts[0], parts[1], parts[2]);
+    let Some((variable, update, used)) = update_at(step, 0) else {
+        return LoopBound::unknown(format!("has a step `{}` that is not recognised: unknown", source_text(step)));
+    };
+    if used != step.len() {
+        return LoopBound::unknown(format!("has a step `{}` that is not recognised: unknown", source_text(step)));
+    }
+    if !updates(&body[inner], &variable).is_empty() {
+        return LoopBound::unknown(format!("changes {} in its body too: unknown", variable));
+    }
+    // The start value, from the initializer or the last assignment before the loop
+    let start = initial_value(initializer, &variable).or_else(|| last_assignment(&body[..header.start], &variable));
+    let mut bound = counted_loop(&variable, update, condition, start, outer);
+    bound.variable = Some(variable);
+    bound
+}
+
+fn analyze_while(
+    body: &[Token],
+    header: Range<usize>,
+    inner: Range<usize>,
+    outer: &[String],
+    halved: &HashMap<String, u64>,
+) -> LoopBound {
+    let condition = &body[header.clone()];
+    if condition.iter().any(|token| token.is("||") || token.is("or")) {
+        return LoopBound::unknown("has a condition with `||`: unknown".to_owned());
+    }
+    let mut variables: Vec<String> = Vec::new();
+    for (index, token) in condition.iter().enumerate() {
+        let call = condition.get(index + 1).is_some_and(|next| next.is("("));
+        let member = index > 0 && (condition[index - 1].is(".") || condition[index - 1].is("->") || condition[index - 1].is("::"));
+        let keyword = ["true", "false", "nullptr", "sizeof", "this"].contains(&token.text.as_str());
+        if token.kind == TokenKind::Identifier && !call && !member && !keyword && !variables.contains(&token.text) {
+            variables.push(token.text.clone());
+        }
+    }
+    if variables.is_empty() {
+        return LoopBound::unknown("has no variable in its condition: unknown".to_owned());
+    }
+
+    let loop_body = &body[inner];
+    let mut narrowed = Vec::new();
+    for variable in &variables {
+        let mut changes = updates(loop_body, variable);
+        changes.extend(updates(condition, variable));
+        match changes.as_slice() {
+            [] => continue,
+            [Update::Step(kind, amount)] => {
+                let start = last_assignment(&body[..header.start], variable);
+                let mut bound = counted_loop(variable, Update::Step(*kind, *amount), condition, start, outer);
+                bound.variable = Some(variable.clone());
+                return bound;
+            }
+            // Following a linked structure such as `node = node->next`
+            [Update::Assign(value)]
+                if value.len() == 3 && value[0].is(variable) && (value[1].is("->") || value[1].is(".")) =>
+            {
+                return LoopBound {
+                    runs: LINEAR,
+                    reason: format!("follows {} through a linked structure: O(n)", source_text(value)),
+                    variable: Some(variable.clone()),
+                };
+            }
+            _ => {}
+        }
+        // Binary search moves the ends of a range to its midpoint
+        let to_midpoint = changes.iter().all(|change| match change {
+            Update::Assign(value) => value.iter().any(|token| halved.contains_key(&token.text)),
+            Update::Step(..) => false,
+        });
+        if !to_midpoint {
+            return LoopBound::unknown(format!("changes {} in a way that is not recognised: unknown", variable));
+        }
+        narrowed.push(variable.as_str());
+    }
+    if narrowed.is_empty() {
+        return LoopBound::unknown(format!("never changes {} in its body: unknown", variables.join(" or ")));
+    }
+    LoopBound {
+        runs: LOGARITHMIC,
+        reason: format!("halves the range between {} each time: O(log n)", variables.join(" and ")),
+        variable: None,
+    }
+}
+
+// How often a loop runs that steps `variable` from `start` until `condition` fails
+fn counted_loop(variable: &str, update: Update, condition: &[Token], start: Option<&[Token]>, outer: &[String]) -> LoopBound {
+    let Update::Step(kind, amount) = update else {
+        return LoopBound::unknown(format!("assigns {} in its step: unknown", variable));
+    };
+    let Some(amount) = amount else {
+        return LoopBound::unknown(format!("steps {} by an amount that may not be constant: unknown", variable));
+    };
+    let comparison = comparison(condition, variable);
+    // `while (n)` and `while (n--)` count down to zero
+    let counts_down = condition.iter().filter(|token| token.kind == TokenKind::Identifier).count() == 1;
+    let upward = matches!(kind, StepKind::Add | StepKind::Multiply);
+    let (limit, size) = match comparison {
+        Some((operator, limit)) => {
+            let towards = if upward { ["<", "<=", "!="].contains(&operator) } else { [">", ">=", "!="].contains(&operator) };
+            if !towards {
+                return LoopBound::unknown(format!("moves {} away from its bound: unknown", variable));
+            }
+            let size = if upward { Some(limit) } else { start };
+            (Some(limit), size)
+        }
+        None if counts_down && !upward => (None, start),
+        None => return LoopBound::unknown(format!("compares {} in a way that is not recognised: unknown", variable)),
+    };
+    if matches!(kind, StepKind::Multiply | StepKind::Divide) && amount < 2 {
+        return LoopBound::unknown(format!("scales {} by {}: unknown", variable, amount));
+    }
+    if kind == StepKind::Multiply && start.is_some_and(|start| start.iter().all(|token| literal(token) == Some(0))) {
+        return LoopBound::unknown(format!("multiplies {} starting at 0, which never grows: unknown", variable));
+    }
+
+    // Without a start value, the variable comes from outside, such as a parameter
+    let fixed = |tokens: Option<&[Token]>| {
+        tokens.is_some_and(|tokens| tokens.iter().all(|token| token.kind == TokenKind::Number || token.kind == TokenKind::Punctuation))
+    };
+    if fixed(size) && fixed(start) && (limit.is_none() || fixed(limit)) {
+        return LoopBound { runs: CONSTANT, reason: "runs a fixed number of times: O(1)".to_owned(), variable: None };
+    }
+    let note = size.map_or_else(String::new, |size| dependence(size, outer));
+    let size = size.map_or_else(|| variable.to_owned(), source_text);
+    let (runs, how) = match kind {
+        StepKind::Add => (LINEAR, format!("counts {} up to {}", variable, size)),
+        StepKind::Subtract => (LINEAR, format!("counts {} down from {}", variable, size)),
+        StepKind::Multiply => (LOGARITHMIC, format!("multiplies {} by {} up to {}", variable, amount, size)),
+        StepKind::Divide => (LOGARITHMIC, format!("divides {} by {} from {}", variable, amount, size)),
+    };
+    LoopBound { runs, reason: format!("{}: {}{}", how, runs, note), variable: None }
+}
+
+// Notes that a bound follows the variable of an enclosing loop, which still makes it at most n
+fn dependence(size: &[Token], outer: &[String]) -> String {
+    match outer.iter().find(|variable| size.iter().any(|token| token.is(variable))) {
+        Some(variable) => format!(", as its bound follows the enclosing loop over {}", variable),
+        None => String::new(),
+    }
+}
+
+// The operator and the other side of the first comparison with `variable` in a condition,
+// turned around so that the variable is on the left
+fn comparison<'t>(condition: &'t [Token], variable: &str) -> Option<(&'static str, &'t [Token])> {
+    const OPERATORS: [(&str, &str); 5] = [("<", ">"), ("<=", ">="), (">", "<"), (">=", "<="), ("!=", "!=")];
+    let clauses = split_top_level(condition, "&&");
+    for clause in clauses {
+        let clause = strip_parentheses(clause);
+        for (operator, mirrored) in OPERATORS {
+            if clause.len() >= 3 && clause[0].is(variable) && clause[1].is(operator) {
+                return Some((operator, &clause[2..]));
+            }
+            let last = clause.len().wrapping_sub(1);
+            if clause.len() >= 3 && clause[last].is(variable) && clause[last - 1].is(operator) {
+                return Some((mirrored, &clause[..last - 1]));
+            }
+        }
+    }
+    None
+}
+
+fn strip_parentheses(mut tokens: &[Token]) -> &[Token] {
+    while tokens.len() >= 2 && tokens[0].is("(") && matching(tokens, 0) == tokens.len() - 1 {
+        tokens = &tokens[1..tokens.len() - 1];
+    }
+    tokens
+}
+
+// The parts of `tokens` between separators outside brackets
+fn split_top_level<'t>(tokens: &'t [Token], separator: &str) -> Vec<&'t [Token]> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut index = 0;
+    while index < tokens.len() {
+        let token = &tokens[index];
+        if token.is("(") || token.is("[") || token.is("{") {
+            index = matching(tokens, index) + 1;
+            continue;
+        }
+        if token.is(separator) {
+            parts.push(&tokens[start..index]);
+            start = index + 1;
+        }
+        index += 1;
+    }
+    parts.push(&tokens[start.min(tokens.len())..]);
+    parts
+}
+
+// The change of a variable at `index`, such as `++i`, `i *= 2` or `i = i + 1`, with the
+// index after it
+fn update_at(tokens: &[Token], index: usize) -> Option<(String, Update, usize)> {
+    let token = tokens.get(index)?;
+    let next = tokens.get(index + 1);
+    let step = |text: &str| match text {
+        "++" | "+=" | "+" => Some(StepKind::Add),
+        "--" | "-=" | "-" => Some(StepKind::Subtract),
+        "*=" | "*" | "<<=" | "<<" => Some(StepKind::Multiply),
+        "/=" | "/" | ">>=" | ">>" => Some(StepKind::Divide),
+        _ => None,
+    };
+    if (token.is("++") || token.is("--")) && next.is_some_and(|next| next.kind == TokenKind::Identifier) {
+        let kind = step(&token.text)?;
+        return Some((tokens[index + 1].text.clone(), Update::Step(kind, Some(1)), index + 2));
+    }
+    if token.kind != TokenKind::Identifier {
+        return None;
+    }
+    let variable = token.text.clone();
+    let next = next?;
+    if next.is("++") || next.is("--") {
+        return Some((variable, Update::Step(step(&next.text)?, Some(1)), index + 2));
+    }
+    let (kind, amount) = if ["+=", "-=", "*=", "/=", "<<=", ">>="].contains(&next.text.as_str()) {
+        (step(&next.text)?, expression_at(tokens, index + 2))
+    } else if next.is("=") {
+        let value = expression_at(tokens, index + 2);
+        let end = index + 2 + value.len();
+        // `i = i + 2` is a step, anything else an assignment
+        return match value {
+            [same, operator, rest @ ..] if same.is(&variable) && step(&operator.text).is_some() && !rest.is_empty() => {
+                let kind = step(&operator.text)?;
+                let shifted = operator.is("<<") || operator.is(">>");
+                Some((variable, Update::Step(kind, scaled(rest, shifted)), end))
+            }
+            _ => Some((variable, Update::Assign(value.to_vec()), end)),
+        };
+    } else {
+        return None;
+    };
+    let shifted = next.is("<<=") || next.is(">>=");
+    Some((variable, Update::Step(kind, scaled(amount, shifted)), index + 2 + amount.len()))
+}
+
+// A literal step amount, where shifting by k scales by 2^k
+fn scaled(amount: &[Token], shifted: bool) -> Option<u64> {
+    let [token] = amount else {
+        return None;
+    };
+    let value = literal(token)?;
+    match shifted {
+        true if (1..64).contains(&value) => Some(1 << value),
+        true => None,
+        false => Some(value),
+    }
+}
+
+// Every change of `variable` in `tokens`
+fn updates(tokens: &[Token], variable: &str) -> Vec<Update> {
+    let mut found = Vec::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        let member = index > 0 && (tokens[index - 1].is(".") || tokens[index - 1].is("->") || tokens[index - 1].is("::"));
+        match update_at(tokens, index) {
+            Some((name, update, next)) if name == variable && !member => {
+                found.push(update);
+                index = next;
+            }
+            _ => index += 1,
+        }
+    }
+    found
+}
+
+// The value of `variable` declared or assigned in a for-loop initializer
+fn initial_value<'t>(initializer: &'t [Token], variable: &str) -> Option<&'t [Token]> {
+    let position = initializer.iter().position(|token| token.is(variable))?;
+    let next = initializer.get(position + 1)?;
+    if next.is("=") {
+        return Some(expression_at(initializer, position + 2));
+    }
+    if next.is("{") || next.is("(") {
+        let close = matching(initializer, position + 1).min(initializer.len());
+        return Some(&initializer[position + 2..close]);
+    }
+    None
+}
+
+// The value last assigned to `variable` in `tokens`
+fn last_assignment<'t>(tokens: &'t [Token], variable: &str) -> Option<&'t [Token]> {
+    let mut value = None;
+    for (index, token) in tokens.iter().enumerate() {
+        if token.is(variable) && tokens.get(index + 1).is_some_and(|next| next.is("=")) {
+            value = Some(expression_at(tokens, index + 2));
+        }
+    }
+    value
+}
+
+// Source text of tokens for explanations, spaced the usual way
+fn source_text(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if index > 0 {
+            let previous = &tokens[index - 1];
+            let operand = |token: &Token| token.kind != TokenKind::Punctuation || token.is(")") || token.is("]");
+            let tight_after = ["(", "[", ".", "->", "::", "!", "~"].iter().any(|text| previous.is(text))
+                || ((previous.is("++") || previous.is("--")) && token.kind == TokenKind::Identifier);
+            let tight_before = [")", "]", ",", ";", ".", "->", "::"].iter().any(|text| token.is(text))
+                || ((token.is("(") || token.is("[")) && previous.kind == TokenKind::Identifier && !is_control(previous))
+                || ((token.is("++") || token.is("--")) && operand(previous));
+            if !tight_after && !tight_before {
+                text.push(' ');
+            }
+        }
+        text.push_str(&token.text);
+    }
+    text
+}
+
+fn is_control(token: &Token) -> bool {
+    ["if", "for", "while", "switch", "catch", "return"].iter().any(|keyword| token.is(keyword))
+}
+
+fn print_text(reports: &[FileReport]) {
+    for report in reports {
+        for diagnostic in &report.diagnostics {
+            println!(
+                "{}:{}:{}: {} [{}]: {}",
+                report.path, diagnostic.line, diagnostic.column, diagnostic.severity, diagnostic.rule, diagnostic.message
+            );
+        }
+    }
+    let diagnostics: Vec<&Diagnostic> = reports.iter().flat_map(|report| &report.diagnostics).collect();
+    let counts: Vec<String> = [Severity::Error, Severity::Warning, Severity::Note]
+        .iter()
+        .map(|severity| {
+            let count = diagnostics.iter().filter(|diagnostic| diagnostic.severity == *severity).count();
+            format!("{} {}{}", count, severity, if count == 1 { "" } else { "s" })
+        })
+        .collect();
+    let files = if reports.len() == 1 { "file" } else { "files" };
+    println!("{} {}: {}", reports.len(), files, counts.join(", "));
+    println!();
+
+    let mut functions: Vec<(&str, &FunctionReport)> =
+        reports.iter().flat_map(|report| report.functions.iter().map(move |function| (report.path.as_str(), function))).collect();
+    if functions.is_empty() {
+        println!("No function definitions found.");
         return;
     }
+    functions.sort_by_key(|(_, function)| std::cmp::Reverse(function.complexity));
+    let locations: Vec<String> =
+        functions.iter().map(|(path, function)| format!("{}:{}:{}", path, function.line, function.column)).collect();
+    let width = functions.iter().map(|(_, function)| function.name.chars().count()).max().unwrap_or(0).max("Function".len());
+    let location_width = locations.iter().map(|location| location.chars().count()).max().unwrap_or(0).max("Location".len());
+    println!(
+        "{:<width$}  {:<location_width$}  {:>6}  {:>10}  {:<12}",
+        "Function", "Location", "Lines", "Complexity", "Estimate",
+        width = width,
+        location_width = location_width
+    );
+    println!("{}", "-".repeat(width + location_width + 36));
+    for ((_, function), location) in functions.iter().zip(&locations) {
+        println!(
+            "{:<width$}  {:<location_width$}  {:>6}  {:>10}  {:<12}",
+            function.name,
+            location,
+            function.lines,
+            function.complexity,
+            function.estimate.order.to_string(),
+            width = width,
+            location_width = location_width
+        );
+    }
+
+    println!();
+    println!("How the estimates were reached:");
+    for ((_, function), location) in functions.iter().zip(&locations) {
+        println!("{} ({}): {}", function.name, location, function.estimate.order);
+        for reason in &function.estimate.reasons {
+            println!("    {}", reason);
+        }
+    }
+}
+
+fn json_report(reports: &[FileReport], config: &Config) -> Value {
+    let files: Vec<Value> = reports
+        .iter()
+        .map(|report| {
+            let functions: Vec<Value> = report
+                .functions
+                .iter()
+                .map(|function| {
+                    json!({
+                        "name": function.name,
+                        "line": function.line,
+                        "column": function.column,
+                        "lines": function.lines,
+                        "complexity": function.complexity,
+                        "estimate": function.estimate.order.to_string(),
+                        "reasons": function.estimate.reasons,
+                    })
+                })
+                .collect();
+            let diagnostics: Vec<Value> = report
+                .diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    json!({
+                        "rule": diagnostic.rule,
+                        "severity": diagnostic.severity.to_string(),
+                        "line": diagnostic.line,
+                        "column": diagnostic.column,
+                        "message": diagnostic.message,
+                    })
+                })
+                .collect();
+            json!({ "path": report.path, "functions": functions, "diagnostics": diagnostics })
+        })
+        .collect();
+    let count = |severity: Severity| {
+        reports.iter().flat_map(|report| &report.diagnostics).filter(|diagnostic| diagnostic.severity == severity).count()
+    };
+    json!({
+        "files": files,
+        "summary": {
+            "files": reports.len(),
+            "functions": reports.iter().map(|report| report.functions.len()).sum::<usize>(),
+            "errors": count(Severity::Error),
+            "warnings": count(Severity::Warning),
+            "notes": count(Severity::Note),
+            "failed": failed(reports, config),
+        },
+    })
+}
+
+// A SARIF 2.1.0 log with one run, whose rules are all rules and whose results are the
+// diagnostics, as read by code scanning tools
+fn sarif_report(reports: &[FileReport], config: &Config) -> Value {
+    let all_rules = rules(config);
+    let descriptors: Vec<Value> = all_rules
+        .iter()
+        .map(|rule| {
+            let setting = config.rules.get(rule.id()).cloned().unwrap_or_default();
+            json!({
+                "id": rule.id(),
+                "shortDescription": { "text": rule.description() },
+                "defaultConfiguration": {
+                    "enabled": setting.enabled.unwrap_or(rule.enabled_by_default()),
+                    "level": setting.severity.unwrap_or(rule.severity()).to_string(),
+                },
+            })
+        })
+        .collect();
+    let mut results = Vec::new();
+    for report in reports {
+        for diagnostic in &report.diagnostics {
+            results.push(json!({
+                "ruleId": diagnostic.rule,
+                "ruleIndex": all_rules.iter().position(|rule| rule.id() == diagnostic.rule),
+                "level": diagnostic.severity.to_string(),
+                "message": { "text": diagnostic.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": file_uri(&report.path) },
+                        "region": { "startLine": diagnostic.line, "startColumn": diagnostic.column },
+                    },
+                }],
+            }));
+        }
+    }
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": { "driver": { "name": "cpp-complexity", "rules": descriptors } },
+            "results": results,
+        }],
+    })
+}
+
+// A URI for a path, with `/` separators and other characters escaped: a `file://` URI for an
+// absolute path, including one with a Windows drive such as `C:/src`, and a relative
+// reference otherwise
+fn file_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.strip_prefix("./").unwrap_or(&path);
+    let mut uri = String::new();
+    let drive = path.len() >= 2 && path.as_bytes()[0].is_ascii_alphabetic() && path.as_bytes()[1] == b':';
+    let path = if drive {
+        uri.push_str("file:///");
+        uri.push_str(&path[..2]);
+        &path[2..]
+    } else {
+        if path.starts_with('/') {
+            uri.push_str("file://");
+        }
+        path
+    };
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+// Optional settings in the directory the tool is run from, see Config
+const CONFIG_FILE: &str = "complexity.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Settings read from CONFIG_FILE or --config, e.g.
+//
+//     indent_width = 2
+//     max_complexity = 15
+//     fail_on = "warning"
+//
+//     [rules.no-goto]
+//     severity = "error"
+//
+//     [rules.no-empty-lines]
+//     enabled = true
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default = "default_indent_width")]
+    indent_width: usize,
+    // Functions above this cyclomatic complexity break the `max-complexity` rule
+    #[serde(default = "default_max_complexity")]
+    max_complexity: usize,
+    // Diagnostics of this severity or worse make the exit code 1
+    #[serde(default = "default_fail_on")]
+    fail_on: Severity,
+    // Overrides by rule ID
+    #[serde(default)]
+    rules: HashMap<String, RuleConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    enabled: Option<bool>,
+    severity: Option<Severity>,
+}
+
+fn default_indent_width() -> usize {
+    4
+}
+
+fn default_max_complexity() -> usize {
+    10
+}
+
+fn default_fail_on() -> Severity {
+    Severity::Error
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            indent_width: default_indent_width(),
+            max_complexity: default_max_complexity(),
+            fail_on: default_fail_on(),
+            rules: HashMap::new(),
+        }
+    }
+}
+
+// The config in `path`, or the defaults when an optional file does not exist
+fn load_config(path: &Path, optional: bool) -> Result<Config, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if optional && e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let config: Config = toml::from_str(&text).map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+    let known: Vec<&str> = rules(&config).iter().map(|rule| rule.id()).collect();
+    if let Some(unknown) = config.rules.keys().find(|id| !known.contains(&id.as_str())) {
+        return Err(format!("Invalid config {}: unknown rule `{}`, expected one of {}", path.display(), unknown, known.join(", ")));
+    }
+    if config.indent_width == 0 {
+        return Err(format!("Invalid config {}: indent_width must be at least 1", path.display()));
+    }
+    Ok(config)
+}
+
+// A file as the rules see it
+struct Source<'a> {
+    lines: Vec<&'a str>,
+    tokens: Vec<Token>,
+    comments: Vec<Token>,
+    declarations: Declarations,
+}
+
+impl<'a> Source<'a> {
+    fn new(code: &'a str) -> Self {
+        let (tokens, comments) = tokenize(code);
+        let declarations = find_declarations(&tokens);
+        Source { lines: code.lines().collect(), tokens, comments, declarations }
+    }
+}
+
+// Where a rule is broken and how
+struct Violation {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl Violation {
+    fn at(token: &Token, message: String) -> Self {
+        Violation { line: token.line, column: token.column, message }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Diagnostic {
+    rule: &'static str,
+    severity: Severity,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+trait Rule {
+    // The name used in the config file and in reports, such as `no-goto`
+    fn id(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn severity(&self) -> Severity;
+    // Rules for house styles that most code does not follow are off unless enabled
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+    fn check(&self, source: &Source) -> Vec<Violation>;
+}
+
+// Every rule, whether enabled or not
+fn rules(config: &Config) -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(Indentation { width: config.indent_width }),
+        Box::new(UnmatchedBrackets),
+        Box::new(NoLineComments),
+        Box::new(NoBlockComments),
+        Box::new(NoEmptyLines),
+        Box::new(NoGoto),
+        Box::new(NoUsingNamespace),
+        Box::new(NoFunctionPrototypes),
+        Box::new(NoStaticGlobals),
+        Box::new(VariableNaming),
+        Box::new(MaxComplexity { limit: config.max_complexity }),
+    ]
+}
+
+// Runs every enabled rule and returns what they found in file order
+fn lint(source: &Source, config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for rule in rules(config) {
+        let setting = config.rules.get(rule.id()).cloned().unwrap_or_default();
+        if !setting.enabled.unwrap_or(rule.enabled_by_default()) {
+            continue;
+        }
+        let severity = setting.severity.unwrap_or(rule.severity());
+        for violation in rule.check(source) {
+            diagnostics.push(Diagnostic {
+                rule: rule.id(),
+                severity,
+                line: violation.line,
+                column: violation.column,
+                message: violation.message,
+            });
+        }
+    }
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    diagnostics
+}
+
+// Statements are indented with spaces by a multiple of the indent width. Lines continuing an
+// expression or inside brackets may align freely.
+struct Indentation {
+    width: usize,
+}
+
+impl Rule for Indentation {
+    fn id(&self) -> &'static str {
+        "indentation"
+    }
+
+    fn description(&self) -> &'static str {
+        "Indent statements with spaces by a multiple of the indent width"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, source: &Source) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let mut depth = 0;
+        let mut previous: Option<&Token> = None;
+        for token in &source.tokens {
+            let first_on_line = previous.is_none_or(|previous| previous.line != token.line);
+            let starts_statement = previous.is_none_or(|previous| {
+                [";", "{", "}", ":", ")", "else", "do"].iter().any(|text| previous.is(text))
+            });
+            if first_on_line && starts_statement && depth == 0 {
+                let line = source.lines.get(token.line - 1).copied().unwrap_or("");
+                let indent: String = line.chars().take(token.column - 1).collect();
+                if indent.contains('\t') {
+                    violations.push(Violation { line: token.line, column: 1, message: "indented with a tab".to_owned() });
+                } else if !indent.len().is_multiple_of(self.width) {
+                    violations.push(Violation {
+                        line: token.line,
+                        column: 1,
+                        message: format!("indented by {} spaces, which is not a multiple of {}", indent.len(), self.width),
+                    });
+                }
+            }
+            if token.is("(") || token.is("[") {
+                depth += 1;
+            } else if (token.is(")") || token.is("]")) && depth > 0 {
+                depth -= 1;
+            }
+            previous = Some(token);
+        }
+        violations
+    }
+}
+
+struct UnmatchedBrackets;
+
+impl Rule for UnmatchedBrackets {
+    fn id(&self) -> &'static str {
+        "unmatched-brackets"
+    }
+
+    fn description(&self) -> &'static str {
+        "Every bracket, brace and parenthesis is closed by its counterpart"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, source: &Source) -> Vec<Violation> {
+        const PAIRS: [(&str, &str); 3] = [("(", ")"), ("[", "]"), ("{", "}")];
+        let mut violations = Vec::new();
+        let mut open: Vec<&Token> = Vec::new();
+        for token in &source.tokens {
+            if PAIRS.iter().any(|(opening, _)| token.is(opening)) {
+                open.push(token);
+                continue;
+            }
+            let Some((opening, _)) = PAIRS.iter().find(|(_, closing)| token.is(closing)) else {
+                continue;
+            };
+            // A closer that matches further down closes the unclosed brackets above it too
+            match open.iter().rposition(|candidate| candidate.is(opening)) {
+                Some(position) => {
+                    for unclosed in open.drain(position + 1..) {
+                        violations.push(Violation::at(
+                            unclosed,
+                            format!("`{}` is not closed before `{}` on line {}", unclosed.text, token.text, token.line),
+                        ));
+                    }
+                    open.pop();
+                }
+                None => violations.push(Violation::at(token, format!("`{}` has no matching `{}`", token.text, opening))),
+            }
+        }
+        for unclosed in open {
+            violations.push(Violation::at(unclosed, format!("`{}` is never closed", unclosed.text)));
+        }
+        violations
+    }
+}
+
+struct NoLineComments;
+
+impl Rule for NoLineComments {
+    fn id(&self) -> &'static str {
+        "no-line-comments"
+    }
+
+    fn description(&self) -> &'static str {
+        "No `//` comments"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Note
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn check(&self, source: &Source) -> Vec<Violation> {
+        source
+            .comments
+            .iter()
+            .filter(|comment| comment.text.starts_with("//"))
+            .map(|comment| Violation::at(comment, "`//` comment".to_owned()))
+            .collect()
+    }
+}
+
+struct NoBlockComments;
+
+impl Rule for NoBlockComments {
+    fn id(&self) -> &'static str {
+        "no-block-comments"
+    }
+
+    fn description(&self) -> &'static str {
+        "No `/* */` comments"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Note
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
 
-    // Regex patterns for identifying common constructs
-    let for_loop_pattern = Regex::new(r#"for\s*\([^;]+;\s*[^;]+;\s*[^)]+\)"#).unwrap();
-    let while_loop_pattern = Regex::new(r#"while\s*\([^)]+\)"#).unwrap();
-    let if_statement_pattern = Regex::new(r#"if\s*\([^)]+\)"#).unwrap();
-    let function_definition_pattern = Regex::new(r#"[\w<>]+\s+\w+\s*\([^)]*\)\s*{\s*"#).unwrap();
+    fn check(&self, source: &Source) -> Vec<Violation> {
+        source
+            .comments
+            .iter()
+            .filter(|comment| comment.text.starts_with("/*"))
+            .map(|comment| Violation::at(comment, "`/* */` comment".to_owned()))
+            .collect()
+    }
+}
+
+struct NoEmptyLines;
+
+impl Rule for NoEmptyLines {
+    fn id(&self) -> &'static str {
+        "no-empty-lines"
+    }
+
+    fn description(&self) -> &'static str {
+        "No blank lines"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Note
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn check(&self, source: &Source) -> Vec<Violation> {
+        source
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.trim().is_empty())
+            .map(|(index, _)| Violation { line: index + 1, column: 1, message: "empty line".to_owned() })
+            .collect()
+    }
+}
+
+struct NoGoto;
 
-    // Regex patterns for identifying common constructs
-    let for_loop_pattern = Regex::new(r#"for\s*\([^;]+;\s*[^;]+;\s*[^)]+\)"#).unwrap();
-    let while_loop_pattern = Regex::new(r#"while\s*\([^)]+\)"#).unwrap();
-    let if_statement_pattern = Regex::new(r#"if\s*\([^)]+\)"#).unwrap();
-    let function_definition_pattern = Regex::new(r#"[\w<>]+\s+\w+\s*\([^)]*\)\s*{\s*"#).unwrap();
+impl Rule for NoGoto {
+    fn id(&self) -> &'static str {
+        "no-goto"
+    }
+
+    fn description(&self) -> &'static str {
+        "No `goto` statements"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
 
-    // Display results
-    println!("Occurrences of for loops: {}", for_loop_count);
-    println!("Occurrences of while loops: {}", while_loop_count);
-    println!("Occurrences of if statements: {}", if_statement_count);
-    println!("Occurrences of function definitions: {}", function_definition_count);
+    fn check(&self, source: &Source) -> Vec<Violation> {
+        source
+            .tokens
+            .iter()
+            .filter(|token| token.is("goto"))
+            .map(|token| Violation::at(token, "`goto` statement; use loops, `break` or early returns".to_owned()))
+            .collect()
+    }
 }
 
-// Function to perform additional validations on C++ code
-fn is_valid_cpp(code: &str) -> bool {
-    // Validation rules
-    let no_tabs_or_spaces_at_beginning = code.lines().all(|line| line.trim_start().is_empty());
-    let no_unmatched_braces = code.chars().filter(|&c| c == '{').count() == code.chars().filter(|&c| c == '}').count();
-    let no_unmatched_parentheses = code.chars().filter(|&c| c == '(').count() == code.chars().filter(|&c| c == ')').count();
-    let no_single_line_comments = !code.lines().any(|line| line.trim_start().starts_with("//"));
-    let no_multiple_line_comments = !code.contains("/*") && !code.contains("*/");
-    let no_empty_lines = code.lines().all(|line| !line.trim().is_empty());
-    let no_goto_statements = !code.contains("goto");
-    let no_using_namespace_directive = !code.contains("using namespace");
-    let no_function_prototypes = code.lines().all(|line| !line.contains(';') || line.contains('(') || line.contains(')'));
-    let proper_indentation = code.lines().all(|line| line.starts_with(' '));
-    let no_static_globals = !code.contains("static");
-    let proper_function_definitions = is_proper_function_definitions(code);
-    let proper_variable_naming = is_proper_variable_naming(code);    
+struct NoUsingNamespace;
 
-    if !no_tabs_or_spaces_at_beginning {
-        eprintln!("Validation failed: Remove leading spaces or tabs from the beginning of lines.");
-        return false;
+impl Rule for NoUsingNamespace {
+    fn id(&self) -> &'static str {
+        "no-using-namespace"
     }
 
-    if !no_unmatched_braces {
-        eprintln!("Validation failed: Check for unmatched braces.");
-        return false;
+    fn description(&self) -> &'static str {
+        "No `using namespace` directives"
     }
 
-    if !no_unmatched_parentheses {
-        eprintln!("Validation failed: Check for unmatched parentheses.");
-        return false;
+    fn severity(&self) -> Severity {
+        Severity::Warning
     }
 
-    if !no_single_line_comments {
-        eprintln!("Validation failed: Remove single-line comments (//) from the code.");
-        return false;
+    fn check(&self, source: &Source) -> Vec<Violation> {
+        let tokens = &source.tokens;
+        tokens
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| pair[0].is("using") && pair[1].is("namespace"))
+            .map(|(index, pair)| {
+                let name: String = tokens[index + 2..]
+                    .iter()
+                    .take_while(|token| !token.is(";"))
+                    .map(|token| token.text.as_str())
+                    .collect();
+                Violation::at(&pair[0], format!("`using namespace {}`; name what is used instead", name))
+            })
+            .collect()
     }
+}
+
+struct NoFunctionPrototypes;
 
-    if !no_multiple_line_comments {
-        eprintln!("Validation failed: Remove multi-line comments (/* */) from the code.");
-        return false;
+impl Rule for NoFunctionPrototypes {
+    fn id(&self) -> &'static str {
+        "no-function-prototypes"
     }
 
-    if !no_empty_lines {
-        eprintln!("Validation failed: Remove empty lines from the code.");
-        return false;
+    fn description(&self) -> &'static str {
+        "No function declarations without a body at namespace scope"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Note
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn check(&self, source: &Source) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for range in &source.declarations.globals {
+            let declaration = &source.tokens[range.clone()];
+            let Some(first) = declaration_start(declaration) else {
+                continue;
+            };
+            let declaration = &declaration[first..];
+            // `int total = sum(values);` calls a function rather than declaring one
+            let before_call = declaration.iter().take_while(|token| !token.is("("));
+            if before_call.clone().any(|token| token.is("=") || token.is("typedef") || token.is("using")) {
+                continue;
+            }
+            if let Some(Block::Function(name)) = function_name(declaration) {
+                violations.push(Violation::at(&declaration[0], format!("prototype of `{}`", name)));
+            }
+        }
+        violations
     }
+}
+
+struct NoStaticGlobals;
 
-    if !no_goto_statements {
-        eprintln!("Validation failed: Avoid using 'goto' statements.");
-        return false;
+impl Rule for NoStaticGlobals {
+    fn id(&self) -> &'static str {
+        "no-static-globals"
     }
 
-    if !no_using_namespace_directive {
-        eprintln!("Validation failed: Avoid using 'using namespace' directive.");
-        return false;
+    fn description(&self) -> &'static str {
+        "No `static` declarations at namespace scope; an unnamed namespace says the same"
     }
 
-    if !no_function_prototypes {
-        eprintln!("Validation failed: Remove function prototypes from the code.");
-        return false;
+    fn severity(&self) -> Severity {
+        Severity::Warning
     }
 
-    if !proper_indentation {
-        eprintln!("Validation failed: Ensure proper indentation in the code.");
-        return false;
+    fn check(&self, source: &Source) -> Vec<Violation> {
+        source
+            .declarations
+            .globals
+            .iter()
+            .filter_map(|range| source.tokens[range.clone()].iter().take_while(|token| !token.is("=")).find(|token| token.is("static")))
+            .map(|token| Violation::at(token, "`static` at namespace scope; prefer an unnamed namespace".to_owned()))
+            .collect()
     }
+}
+
+// Local variables start with a lower-case letter, except constants in upper case
+struct VariableNaming;
 
-    if !no_static_globals {
-        eprintln!("Validation failed: Avoid using 'static' for globals. Prefer unnamed namespaces.");
-        return false;
+impl Rule for VariableNaming {
+    fn id(&self) -> &'static str {
+        "variable-naming"
     }
 
-    if !proper_function_definitions {
-        eprintln!("Validation failed: Check for proper function definitions.");
-        return false;
+    fn description(&self) -> &'static str {
+        "Local variable names start with a lower-case letter"
     }
 
-    if !proper_variable_naming {
-        eprintln!("Validation failed: Check for proper variable naming.");
-        return false;
+    fn severity(&self) -> Severity {
+        Severity::Note
     }
 
-    // If all validations pass
-    true
+    fn check(&self, source: &Source) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for function in &source.declarations.functions {
+            for (name, constant) in declared_variables(&function.body) {
+                let upper_case = name.text.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+                if name.text.starts_with(|c: char| c.is_lowercase()) || (constant && upper_case) {
+                    continue;
+                }
+                violations.push(Violation::at(name, format!("variable `{}` should start with a lower-case letter", name.text)));
+            }
+        }
+        violations
+    }
 }
 
-// Function to check if function definitions follow a proper format
-fn is_proper_function_definitions(code: &str) -> bool {
-    let function_definition_pattern = Regex::new(r#"[\w<>]+\s+\w+\s*\([^)]*\)\s*{\s*"#).unwrap();  
+// Words before the type of a declaration; the built-in ones can be the whole type
+const DECLARATION_QUALIFIERS: &[&str] = &[
+    "const", "constexpr", "static", "volatile", "register", "thread_local", "unsigned", "signed", "long", "short",
+];
 
-    for cap in function_definition_pattern.captures_iter(code) {
-        let function_signature = cap.get(0).unwrap().as_str();
-        let opening_brace_index = function_signature.rfind('{');
-        let closing_brace_index = function_signature.rfind('}');
+// Statements that start like a declaration without being one
+const NOT_TYPES: &[&str] = &[
+    "return", "delete", "throw", "case", "default", "goto", "else", "new", "using", "typedef", "do", "break", "continue",
+    "co_return", "co_yield", "co_await", "public", "protected", "private",
+];
 
-        match (opening_brace_index, closing_brace_index) {
-            (Some(open), Some(close)) if open < close => continue,
-            _ => return false,
+// Names declared by statements such as `const std::vector<int>& values = ...;` in a body,
+// and whether they are constants
+fn declared_variables(body: &[Token]) -> Vec<(&Token, bool)> {
+    let mut found = Vec::new();
+    for start in 0..body.len() {
+        let after = |text: &str| start > 0 && body[start - 1].is(text);
+        let condition = after("(") && start > 1 && ["for", "if", "while", "switch"].iter().any(|keyword| body[start - 2].is(keyword));
+        if !(start == 0 || after(";") || after("{") || after("}") || condition) {
+            continue;
+        }
+        let mut index = start;
+        let mut constant = false;
+        while body.get(index).is_some_and(|token| DECLARATION_QUALIFIERS.contains(&token.text.as_str())) {
+            constant |= body[index].is("const") || body[index].is("constexpr");
+            index += 1;
+        }
+        let Some(ty) = body.get(index) else {
+            continue;
+        };
+        if ty.kind != TokenKind::Identifier || NOT_TYPES.contains(&ty.text.as_str()) {
+            continue;
         }
+        // `unsigned count = 0;` has no type name after the qualifiers
+        let terminators = ["=", ";", "{", "[", ",", ":"];
+        let bare = index > start && body.get(index + 1).is_some_and(|next| terminators.iter().any(|text| next.is(text)));
+        if !bare {
+            index += 1;
+            loop {
+                match body.get(index) {
+                    Some(token) if token.is("::") => index += 2,
+                    Some(token) if token.is("<") => {
+                        let mut depth = 0;
+                        while let Some(token) = body.get(index) {
+                            if token.is("<") {
+                                depth += 1;
+                            } else if token.is(">") || token.is(">>") {
+                                depth -= token.text.len() as i32;
+                            } else if token.is(";") || token.is("{") {
+                                break;
+                            }
+                            index += 1;
+                            if depth <= 0 {
+                                break;
+                            }
+                        }
+                    }
+                    _ => break,
+                }
+            }
+            while body.get(index).is_some_and(|token| ["*", "&", "&&", "const"].iter().any(|text| token.is(text))) {
+                constant |= body[index].is("const");
+                index += 1;
+            }
+        }
+        let Some(name) = body.get(index) else {
+            continue;
+        };
+        if name.kind == TokenKind::Identifier && body.get(index + 1).is_some_and(|next| terminators.iter().any(|text| next.is(text))) {
+            found.push((name, constant));
+        }
+    }
+    found
+}
+
+// Functions whose cyclomatic complexity is above the configured maximum
+struct MaxComplexity {
+    limit: usize,
+}
+
+impl Rule for MaxComplexity {
+    fn id(&self) -> &'static str {
+        "max-complexity"
     }
 
-    true
+    fn description(&self) -> &'static str {
+        "Functions stay within the maximum cyclomatic complexity"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, source: &Source) -> Vec<Violation> {
+        source
+            .declarations
+            .functions
+            .iter()
+            .filter_map(|function| {
+                let complexity = cyclomatic_complexity(&function.body);
+                (complexity > self.limit).then(|| Violation {
+                    line: function.line,
+                    column: function.column,
+                    message: format!(
+                        "`{}` has a cyclomatic complexity of {}, above the maximum of {}",
+                        function.name, complexity, self.limit
+                    ),
+                })
+            })
+            .collect()
+    }
 }
 
-// Function to check if variable names follow a proper format
-fn is_proper_variable_naming(code: &str) -> bool {
-    // Add your variable naming conventions or patterns here
-    let valid_variable_name_pattern = Regex::new(r#"\b[a-z]\w*\b"#).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The order estimated for the first function of the code
+    fn order_of(code: &str) -> String {
+        let source = Source::new(code);
+        let function = source.declarations.functions.first().expect("a function");
+        estimate(function).order.to_string()
+    }
 
-    valid_variable_name_pattern.is_match(code)    
+    #[test]
+    fn returned_calls_are_alternatives() {
+        let code = "int search(int lo, int hi) { int mid = (lo + hi) / 2; if (x < a[mid]) return search(lo, mid); return search(mid, hi); }";
+        assert_eq!(order_of(code), "O(log n)");
+    }
+
+    #[test]
+    fn calls_before_a_return_are_not_alternatives() {
+        assert_eq!(order_of("int fib(int n) { if (n < 2) return n; int a = fib(n - 1); return a + fib(n - 2); }"), "O(2ⁿ)");
+        assert_eq!(order_of("int sum(int n) { if (n < 2) return n; int l = sum(n / 2); return l + sum(n / 2); }"), "O(n)");
+        assert_eq!(order_of("int b(int n) { if (n < 1) return 0; b(n - 1); return b(n - 1); }"), "O(2ⁿ)");
+    }
+
+    #[test]
+    fn absolute_paths_are_file_uris() {
+        assert_eq!(file_uri("/tmp/cx/c.cpp"), "file:///tmp/cx/c.cpp");
+        assert_eq!(file_uri("C:\\src\\a b.cpp"), "file:///C:/src/a%20b.cpp");
+        assert_eq!(file_uri("./src/main.cpp"), "src/main.cpp");
+    }
+
+    #[test]
+    fn unclosed_loop_headers_are_unknown() {
+        for code in ["int f() { while (", "int f() { for (int i = 0; i < n; i", "int f() { for (auto x :"] {
+            assert_eq!(order_of(code), "unknown", "{}", code);
+        }
+    }
 }
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use serde_json::{json, Value};
+
+// Add the following dependencies to your Cargo.toml:
+// serde_json = "1.0"
+
+// Scripted stand-in for a language server, used to exercise the LSP client of
+// Intelligent Code Completion without installing rust-analyzer or clangd.
+//
+// The script is a JSON object mapping request methods to the result to send back:
+// {
+//   "textDocument/completion": [{ "label": "push", "kind": 2, "detail": "fn push(&mut self, value: T)" }],
+//   "textDocument/hover": { "contents": { "kind": "plaintext", "value": "Vec<i32>" } }
+// }
+// Requests without an entry get a null result ("initialize" gets empty capabilities).
+// Like a real server it exits with status 1 when told to exit before a shutdown request.
+//
+// Point the client at it with: COMPLETION_SERVER_RUST="fake-language-server script.json"
+
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse::<usize>().ok();
+        }
+    }
+
+    let length = match content_length {
+        Some(length) => length,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header")),
+    };
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+fn load_script(path: Option<String>) -> HashMap<String, Value> {
+    let path = match path {
+        Some(path) => path,
+        None => return HashMap::new(),
+    };
+
+    let content = fs::read_to_string(&path).expect("Failed to read the script file");
+    serde_json::from_str(&content).expect("The script file must be a JSON object")
+}
+
+fn main() -> io::Result<()> {
+    let script = load_script(env::args().nth(1));
+    let stdin = io::stdin();
+    let mut reader = stdin.lock();
+    let stdout = io::stdout();
+    let mut writer = stdout.lock();
+    let mut shut_down = false;
+
+    while let Some(message) = read_message(&mut reader)? {
+        let method = message["method"].as_str().unwrap_or_default().to_owned();
+        eprintln!("fake-language-server: received {}", method);
+
+        if method == "exit" {
+            if !shut_down {
+                process::exit(1);
+            }
+            break;
+        }
+        if method == "shutdown" {
+            shut_down = true;
+        }
+
+        // Notifications have no id and get no response
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => continue,
+        };
+
+        let result = match script.get(&method) {
+            Some(result) => result.clone(),
+            None if method == "initialize" => json!({ "capabilities": {} }),
+            None => Value::Null,
+        };
+        write_message(&mut writer, &json!({ "jsonrpc": "2.0", "id": id, "result": result }))?;
+    }
+
+    Ok(())
+}
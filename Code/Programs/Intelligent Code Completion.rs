@@ -1,403 +1,4448 @@
-extern crate racer;
-extern crate cppcomplete;
-extern crate syntect;
-extern crate rustfmt;
-
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
 use syntect::easy::HighlightLines;
-use syntect::highlighting::{Style, ThemeSet};
-use syntect::parsing::SyntaxSet;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::{css_for_theme_with_class_style, highlighted_html_for_string, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
 
-use syn::{Item, parse_quote};
-use proc_macro2::TokenStream;
-use quote::quote;
-use syn::parse_quote;
+use syn::{Ident, Item, ItemStruct, ReturnType, Signature, Visibility};
+use syn::visit::{self, Visit};
+use quote::{quote, ToTokens};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
 
 // Add the following dependencies to your Cargo.toml:
-// syn = "1.0"
+// syntect = "5.0"
+// syn = { version = "1.0", features = ["full", "visit"] }
+// serde = { version = "1.0", features = ["derive"] }
+// toml = "0.8"
 // quote = "1.0"
-// proc-macro2 = "1.0"
+// proc-macro2 = { version = "1.0", features = ["span-locations"] }
 // regex = "1.4"
+// serde_json = "1.0"
+// url = "2.5"
+
+// Everything the tool needs to know about a programming language.
+// Register new implementations in LANGUAGES; nothing else has to change.
+trait Language: Sync {
+    // Display name, e.g. "C++"
+    fn name(&self) -> &'static str;
+
+    // LSP language identifier, also used for the COMPLETION_SERVER_<ID> override
+    fn id(&self) -> &'static str;
+
+    // File extensions without the dot; the first one selects the syntect syntax
+    fn extensions(&self) -> &'static [&'static str];
+
+    // Interpreter names recognised in a `#!` line
+    fn interpreters(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    // Rough likelihood that an unnamed snippet is written in this language
+    fn content_score(&self, code: &str) -> usize;
+
+    // Language server command line used when COMPLETION_SERVER_<ID> is not set
+    fn default_server(&self) -> &'static str;
+
+    fn extract_symbols(&self, code: &str) -> Result<Vec<Symbol>, String>;
+
+    // Built-in snippet completions; users add their own in the snippets directory
+    fn snippets(&self) -> Vec<Snippet> {
+        Vec::new()
+    }
+
+    fn analyze(&self, code: &str) {
+        match self.extract_symbols(code) {
+            Ok(symbols) => {
+                for symbol in symbols {
+                    println!(
+                        "Found {} {:?} {} at line {}: {}",
+                        self.name(),
+                        symbol.kind,
+                        symbol.path,
+                        symbol.range.start_line,
+                        symbol.detail
+                    );
+                }
+            }
+            Err(err) => eprintln!("Failed to parse {} code: {}", self.name(), err),
+        }
+    }
+}
+
+struct RustLanguage;
+struct CppLanguage;
+struct PythonLanguage;
+struct GoLanguage;
+struct JavaScriptLanguage;
 
-struct CodeSnippet {
-    language: Language,
-    content: String,
+static LANGUAGES: &[&dyn Language] = &[&RustLanguage, &CppLanguage, &PythonLanguage, &GoLanguage, &JavaScriptLanguage];
+
+// Compiles each pattern once; detection runs on every request
+fn cached_regex(pattern: &'static str) -> Regex {
+    static CACHE: OnceLock<Mutex<HashMap<&'static str, Regex>>> = OnceLock::new();
+    let mut cache = CACHE.get_or_init(Default::default).lock().unwrap();
+    cache.entry(pattern).or_insert_with(|| Regex::new(pattern).unwrap()).clone()
 }
 
-enum Language {
-    Rust,
-    Cpp,
+// Counts how many of the given patterns occur in the code
+fn count_matches(code: &str, patterns: &[&'static str]) -> usize {
+    patterns
+        .iter()
+        .map(|pattern| cached_regex(pattern).find_iter(code).count())
+        .sum()
 }
 
-struct UserPreferences {
-    perform_analysis: bool,
-    syntax_highlighting: bool,
-    perform_formatting: bool,
+impl Language for RustLanguage {
+    fn name(&self) -> &'static str {
+        "Rust"
+    }
+
+    fn id(&self) -> &'static str {
+        "rust"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["rs"]
+    }
+
+    fn content_score(&self, code: &str) -> usize {
+        count_matches(code, &[r"\bfn\s+\w+", r"\blet\s+(mut\s+)?\w+", r"\bimpl\b", r"\buse\s+\w+::", r"\w+!\("])
+    }
+
+    fn default_server(&self) -> &'static str {
+        "rust-analyzer"
+    }
+
+    fn extract_symbols(&self, code: &str) -> Result<Vec<Symbol>, String> {
+        collect_rust_symbols(code).map_err(|e| e.to_string())
+    }
+
+    fn snippets(&self) -> Vec<Snippet> {
+        vec![
+            Snippet::new("fn", "function", "fn ${1:name}(${2:arguments}) {\n    ${0}\n}"),
+            Snippet::new("impl", "impl block", "impl ${1:Type} {\n    ${0}\n}"),
+            Snippet::new("impl-trait", "trait implementation", "impl ${1:Trait} for ${2:Type} {\n    ${0}\n}"),
+            Snippet::new("match", "match expression", "match ${1:value} {\n    ${2:pattern} => ${3:()},\n    _ => ${0:()},\n}"),
+            Snippet::new("for", "for loop", "for ${1:item} in ${2:items} {\n    ${0}\n}"),
+            Snippet::new("while", "while loop", "while ${1:condition} {\n    ${0}\n}"),
+            Snippet::new("if-let", "if let expression", "if let ${1:Some(value)} = ${2:option} {\n    ${0}\n}"),
+            Snippet::new("struct", "struct definition", "struct ${1:Name} {\n    ${2:field}: ${3:Type},\n}"),
+            Snippet::new("test", "test function", "#[test]\nfn ${1:name}() {\n    ${0}\n}"),
+        ]
+    }
+
+    fn analyze(&self, code: &str) {
+        analyze_rust_code(code);
+    }
+}
+
+impl Language for CppLanguage {
+    fn name(&self) -> &'static str {
+        "C++"
+    }
+
+    fn id(&self) -> &'static str {
+        "cpp"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["cpp", "cc", "cxx", "hpp", "hh", "h"]
+    }
+
+    fn content_score(&self, code: &str) -> usize {
+        3 * count_matches(code, &[r"#include\s*[<\x22]"])
+            + count_matches(code, &[r"\bstd::", r"\btemplate\s*<", r"\bnamespace\s+\w+", r"\w+::\w+\("])
+    }
+
+    fn default_server(&self) -> &'static str {
+        "clangd"
+    }
+
+    fn extract_symbols(&self, code: &str) -> Result<Vec<Symbol>, String> {
+        Ok(collect_cpp_symbols(code))
+    }
+
+    fn analyze(&self, code: &str) {
+        analyze_cpp_code(code);
+    }
+}
+
+impl Language for PythonLanguage {
+    fn name(&self) -> &'static str {
+        "Python"
+    }
+
+    fn id(&self) -> &'static str {
+        "python"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["py", "pyi"]
+    }
+
+    fn interpreters(&self) -> &'static [&'static str] {
+        &["python", "python3", "python2", "pypy", "pypy3"]
+    }
+
+    fn content_score(&self, code: &str) -> usize {
+        count_matches(
+            code,
+            &[r"(?m)^\s*def\s+\w+\(.*\)\s*(->.*)?:\s*$", r"(?m)^\s*class\s+\w+.*:\s*$", r"(?m)^\s*(from\s+\w+\s+)?import\s+\w+", r"\bself\."],
+        )
+    }
+
+    fn default_server(&self) -> &'static str {
+        "pylsp"
+    }
+
+    fn extract_symbols(&self, code: &str) -> Result<Vec<Symbol>, String> {
+        Ok(collect_python_symbols(code))
+    }
+}
+
+impl Language for GoLanguage {
+    fn name(&self) -> &'static str {
+        "Go"
+    }
+
+    fn id(&self) -> &'static str {
+        "go"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["go"]
+    }
+
+    fn content_score(&self, code: &str) -> usize {
+        3 * count_matches(code, &[r"(?m)^package\s+\w+"])
+            + count_matches(code, &[r"\bfunc\s+(\(.*?\)\s*)?\w+\(", r":=", r"(?m)^type\s+\w+\s+(struct|interface)"])
+    }
+
+    fn default_server(&self) -> &'static str {
+        "gopls"
+    }
+
+    fn extract_symbols(&self, code: &str) -> Result<Vec<Symbol>, String> {
+        Ok(collect_go_symbols(code))
+    }
+}
+
+impl Language for JavaScriptLanguage {
+    fn name(&self) -> &'static str {
+        "JavaScript"
+    }
+
+    fn id(&self) -> &'static str {
+        "javascript"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["js", "mjs", "cjs", "jsx"]
+    }
+
+    fn interpreters(&self) -> &'static [&'static str] {
+        &["node", "nodejs", "deno", "bun"]
+    }
+
+    fn content_score(&self, code: &str) -> usize {
+        count_matches(
+            code,
+            &[r"\bfunction\s*\*?\s*\w*\s*\(", r"\b(const|let|var)\s+\w+\s*=", r"=>", r"\bconsole\.\w+", r"\brequire\(|\bexport\s+"],
+        )
+    }
+
+    fn default_server(&self) -> &'static str {
+        "typescript-language-server --stdio"
+    }
+
+    fn extract_symbols(&self, code: &str) -> Result<Vec<Symbol>, String> {
+        Ok(collect_javascript_symbols(code))
+    }
+}
+
+fn language_for_extension(extension: &str) -> Option<&'static dyn Language> {
+    LANGUAGES
+        .iter()
+        .copied()
+        .find(|language| language.extensions().contains(&extension))
+}
+
+fn detect_language(file_path: &str, code: &str) -> &'static dyn Language {
+    detect_language_or(file_path, code, &RustLanguage)
+}
+
+// Picks a language from the file extension, then a `#!` line, then the content of the code
+fn detect_language_or(file_path: &str, code: &str, fallback: &'static dyn Language) -> &'static dyn Language {
+    if let Some(language) = Path::new(file_path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(language_for_extension)
+    {
+        return language;
+    }
+
+    if let Some(shebang) = code.lines().next().and_then(|line| line.strip_prefix("#!")) {
+        // `#!/usr/bin/env python3` or `#!/usr/local/bin/node --harmony`
+        let mut words = shebang.split_whitespace();
+        let mut program = words.next().unwrap_or_default().rsplit('/').next().unwrap_or_default();
+        if program == "env" {
+            program = words.find(|word| !word.starts_with('-')).unwrap_or_default();
+        }
+        if let Some(language) = LANGUAGES.iter().copied().find(|language| language.interpreters().contains(&program)) {
+            return language;
+        }
+    }
+
+    // Ties go to the language registered first
+    LANGUAGES
+        .iter()
+        .rev()
+        .map(|language| (language.content_score(code), *language))
+        .max_by_key(|(score, _)| *score)
+        .filter(|(score, _)| *score > 0)
+        .map_or(fallback, |(_, language)| language)
+}
+
+fn make_symbol(name: &str, kind: SymbolKind, detail: String, scope: &[String], line: usize, column: usize) -> Symbol {
+    Symbol {
+        name: name.to_owned(),
+        path: scope.iter().map(|part| part.as_str()).chain(std::iter::once(name)).collect::<Vec<&str>>().join("."),
+        kind,
+        detail,
+        container: if scope.is_empty() { None } else { Some(scope.join(".")) },
+        parent: None,
+        range: SourceRange {
+            start_line: line,
+            start_column: column,
+            end_line: line,
+            end_column: column + name.chars().count(),
+        },
+    }
+}
+
+// Points every symbol at the enclosing symbol its container names: the one with that path,
+// or failing that the one with that name (a Go method's receiver type)
+fn link_parents(symbols: &mut [Symbol]) {
+    for index in 0..symbols.len() {
+        let Some(container) = symbols[index].container.clone() else { continue };
+        let candidates = || symbols.iter().enumerate().filter(|(other, symbol)| *other != index && symbol.kind != SymbolKind::Impl);
+        symbols[index].parent = candidates()
+            .find(|(_, symbol)| symbol.path == container)
+            .or_else(|| candidates().find(|(_, symbol)| symbol.name == container))
+            .map(|(other, _)| other);
+    }
+}
+
+// 1-based column of a regex capture within its line
+fn capture_column(line: &str, capture: &regex::Match) -> usize {
+    line[..capture.start()].chars().count() + 1
+}
+
+// Classes, methods and functions, nested by indentation
+fn collect_python_symbols(code: &str) -> Vec<Symbol> {
+    let definition = Regex::new(r"^(\s*)(?:async\s+)?(def|class)\s+(\w+)\s*(\([^)]*\)?)?\s*(->\s*[^:]+)?").unwrap();
+    let mut symbols = Vec::new();
+    // Enclosing definitions as (indentation, name, is_class)
+    let mut scopes: Vec<(usize, String, bool)> = Vec::new();
+
+    for (index, line) in code.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let indentation = line.len() - line.trim_start().len();
+        while scopes.last().is_some_and(|(scope_indentation, _, _)| *scope_indentation >= indentation) {
+            scopes.pop();
+        }
+
+        if let Some(capture) = definition.captures(line) {
+            let keyword = &capture[2];
+            let name = capture.get(3).unwrap();
+            let parameters = capture.get(4).map_or("", |parameters| parameters.as_str());
+            let return_type = capture.get(5).map_or("", |return_type| return_type.as_str().trim());
+            let in_class = scopes.last().is_some_and(|(_, _, is_class)| *is_class);
+
+            let (kind, detail) = match keyword {
+                "class" => (SymbolKind::Struct, format!("class {}{}", name.as_str(), parameters)),
+                _ if in_class => (SymbolKind::Method, format!("def {}{} {}", name.as_str(), parameters, return_type)),
+                _ => (SymbolKind::Function, format!("def {}{} {}", name.as_str(), parameters, return_type)),
+            };
+            let scope: Vec<String> = scopes.iter().map(|(_, name, _)| name.clone()).collect();
+            symbols.push(make_symbol(name.as_str(), kind, detail.trim_end().to_owned(), &scope, index + 1, capture_column(line, &name)));
+            scopes.push((indentation, name.as_str().to_owned(), keyword == "class"));
+        }
+    }
+
+    link_parents(&mut symbols);
+    symbols
+}
+
+// Package, functions, methods (by receiver type), struct types with their fields and interfaces
+fn collect_go_symbols(code: &str) -> Vec<Symbol> {
+    let package = Regex::new(r"^package\s+(\w+)").unwrap();
+    let function = Regex::new(r"^func\s+(?:\(\s*\w*\s*\*?\s*(\w+)[^)]*\)\s*)?(\w+)\s*(\[[^\]]*\])?\s*(\(.*)$").unwrap();
+    let type_declaration = Regex::new(r"^type\s+(\w+)(\[[^\]]*\])?\s+(struct|interface)?").unwrap();
+    let field = Regex::new(r"^\s*(\w+(?:\s*,\s*\w+)*)\s+([^/{]+?)\s*(?:`.*`)?\s*(?://.*)?$").unwrap();
+
+    let mut symbols = Vec::new();
+    let mut package_scope: Vec<String> = Vec::new();
+    let mut current_struct: Option<String> = None;
+
+    for (index, line) in code.lines().enumerate() {
+        if let Some(struct_name) = &current_struct {
+            if line.trim_start().starts_with('}') {
+                current_struct = None;
+            } else if let Some(capture) = field.captures(line) {
+                let mut scope = package_scope.clone();
+                scope.push(struct_name.clone());
+                let names = capture.get(1).unwrap();
+                for name in names.as_str().split(',').map(str::trim) {
+                    let column = line.find(name).map_or(1, |offset| line[..offset].chars().count() + 1);
+                    symbols.push(make_symbol(name, SymbolKind::Field, format!("{} {}", name, capture[2].trim()), &scope, index + 1, column));
+                }
+            }
+            continue;
+        }
+
+        if let Some(capture) = package.captures(line) {
+            let name = capture.get(1).unwrap();
+            symbols.push(make_symbol(name.as_str(), SymbolKind::Module, format!("package {}", name.as_str()), &[], index + 1, capture_column(line, &name)));
+            package_scope = vec![name.as_str().to_owned()];
+        } else if let Some(capture) = function.captures(line) {
+            let name = capture.get(2).unwrap();
+            let signature = line.trim_end().trim_end_matches('{').trim_end().to_owned();
+            let mut scope = package_scope.clone();
+            let kind = match capture.get(1) {
+                Some(receiver) => {
+                    scope.push(receiver.as_str().to_owned());
+                    SymbolKind::Method
+                }
+                None => SymbolKind::Function,
+            };
+            symbols.push(make_symbol(name.as_str(), kind, signature, &scope, index + 1, capture_column(line, &name)));
+        } else if let Some(capture) = type_declaration.captures(line) {
+            let name = capture.get(1).unwrap();
+            let kind = match capture.get(3).map(|keyword| keyword.as_str()) {
+                Some("struct") => SymbolKind::Struct,
+                Some("interface") => SymbolKind::Trait,
+                _ => SymbolKind::Struct,
+            };
+            let detail = line.trim_end().trim_end_matches('{').trim_end().to_owned();
+            symbols.push(make_symbol(name.as_str(), kind, detail, &package_scope, index + 1, capture_column(line, &name)));
+            if kind == SymbolKind::Struct && line.trim_end().ends_with('{') {
+                current_struct = Some(name.as_str().to_owned());
+            }
+        }
+    }
+
+    link_parents(&mut symbols);
+    symbols
+}
+
+// Functions, arrow functions bound to variables, classes and their methods
+fn collect_javascript_symbols(code: &str) -> Vec<Symbol> {
+    let function = Regex::new(r"^\s*(?:export\s+)?(?:default\s+)?(?:async\s+)?function\s*\*?\s*(\w+)\s*(\([^)]*\))").unwrap();
+    let arrow = Regex::new(r"^\s*(?:export\s+)?(?:const|let|var)\s+(\w+)\s*=\s*(?:async\s*)?(\([^)]*\)|\w+)\s*=>").unwrap();
+    let class = Regex::new(r"^\s*(?:export\s+)?(?:default\s+)?class\s+(\w+)(\s+extends\s+[\w.]+)?").unwrap();
+    let method = Regex::new(r"^\s*(?:static\s+)?(?:async\s+)?(?:get\s+|set\s+)?\*?\s*(#?\w+)\s*(\([^)]*\))\s*\{").unwrap();
+
+    let mut symbols = Vec::new();
+    let mut depth: usize = 0;
+    // Name of the class being read and the brace depth of its body
+    let mut current_class: Option<(String, usize)> = None;
+
+    for (index, line) in code.lines().enumerate() {
+        let code_part = line.split("//").next().unwrap_or_default();
+
+        if let Some((class_name, class_depth)) = &current_class {
+            if depth == *class_depth {
+                if let Some(capture) = method.captures(code_part) {
+                    let name = capture.get(1).unwrap();
+                    if !matches!(name.as_str(), "if" | "for" | "while" | "switch" | "catch" | "function") {
+                        let detail = format!("{}{}", name.as_str(), &capture[2]);
+                        symbols.push(make_symbol(name.as_str(), SymbolKind::Method, detail, std::slice::from_ref(class_name), index + 1, capture_column(line, &name)));
+                    }
+                }
+            }
+        } else if let Some(capture) = class.captures(code_part) {
+            let name = capture.get(1).unwrap();
+            let detail = format!("class {}{}", name.as_str(), capture.get(2).map_or("", |extends| extends.as_str()));
+            symbols.push(make_symbol(name.as_str(), SymbolKind::Struct, detail, &[], index + 1, capture_column(line, &name)));
+            current_class = Some((name.as_str().to_owned(), depth + 1));
+        } else if depth == 0 {
+            if let Some(capture) = function.captures(code_part) {
+                let name = capture.get(1).unwrap();
+                let detail = format!("function {}{}", name.as_str(), &capture[2]);
+                symbols.push(make_symbol(name.as_str(), SymbolKind::Function, detail, &[], index + 1, capture_column(line, &name)));
+            } else if let Some(capture) = arrow.captures(code_part) {
+                let name = capture.get(1).unwrap();
+                let detail = format!("const {} = {} =>", name.as_str(), &capture[2]);
+                symbols.push(make_symbol(name.as_str(), SymbolKind::Function, detail, &[], index + 1, capture_column(line, &name)));
+            }
+        }
+
+        for c in code_part.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        if current_class.as_ref().is_some_and(|(_, class_depth)| depth < *class_depth) {
+            current_class = None;
+        }
+    }
+
+    link_parents(&mut symbols);
+    symbols
+}
+
+fn write_code_to_file(code: &str, file_path: &str) {
+    fs::write(file_path, code).expect("Failed to write to the file");    
+}
+
+// A completion candidate returned by a language server
+#[derive(Debug, Clone)]
+struct Completion {
+    label: String,
+    kind: Option<String>,
+    detail: Option<String>,
+    documentation: Option<String>,
+    // Text to insert instead of the label, in snippet syntax for snippets
+    insert_text: Option<String>,
+}
+
+// Command used to start the language server for a language
+struct LanguageServerConfig {
+    command: String,
+    args: Vec<String>,
+}
+
+impl LanguageServerConfig {
+    // Defaults to the language's usual server; override with e.g. COMPLETION_SERVER_RUST="ra-multiplex --stdio"
+    fn for_language(language: &dyn Language) -> Self {
+        let variable = format!("COMPLETION_SERVER_{}", language.id().to_uppercase());
+        let command_line = env::var(variable).unwrap_or_else(|_| language.default_server().to_owned());
+        let mut parts = command_line.split_whitespace().map(|part| part.to_owned());
+
+        LanguageServerConfig {
+            command: parts.next().unwrap_or_else(|| language.default_server().to_owned()),
+            args: parts.collect(),
+        }
+    }
+}
+
+// Minimal Language Server Protocol client talking JSON-RPC to a server over stdio
+struct LspClient {
+    server: Child,
+    writer: ChildStdin,
+    reader: BufReader<ChildStdout>,
+    next_id: u64,
+    // Text of the opened documents, to express positions in UTF-16 code units
+    documents: HashMap<String, String>,
+}
+
+impl LspClient {
+    fn start(config: &LanguageServerConfig) -> Result<Self, String> {
+        let mut server = Command::new(&config.command)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start language server '{}': {}", config.command, e))?;
+
+        let writer = server.stdin.take().ok_or("Language server has no stdin")?;
+        let reader = BufReader::new(server.stdout.take().ok_or("Language server has no stdout")?);
+
+        Ok(LspClient {
+            server,
+            writer,
+            reader,
+            next_id: 1,
+            documents: HashMap::new(),
+        })
+    }
+
+    fn send(&mut self, message: &Value) -> Result<(), String> {
+        write_lsp_message(&mut self.writer, message).map_err(|e| format!("Failed to write to language server: {}", e))
+    }
+
+    fn receive(&mut self) -> Result<Value, String> {
+        read_lsp_message(&mut self.reader)?.ok_or_else(|| "Language server closed the connection".to_owned())
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Result<(), String> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        loop {
+            let message = self.receive()?;
+
+            // Answer requests coming from the server (e.g. window/workDoneProgress/create) so it does not stall
+            if message.get("method").is_some() {
+                if let Some(server_id) = message.get("id") {
+                    self.send(&json!({ "jsonrpc": "2.0", "id": server_id, "result": null }))?;
+                }
+                continue;
+            }
+
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                return Err(format!("{} failed: {}", method, error["message"].as_str().unwrap_or("unknown error")));
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    fn initialize(&mut self, root_uri: &str) -> Result<(), String> {
+        self.request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": root_uri,
+                "capabilities": {
+                    "textDocument": {
+                        "completion": { "completionItem": { "documentationFormat": ["plaintext", "markdown"] } },
+                        "hover": { "contentFormat": ["plaintext", "markdown"] }
+                    }
+                }
+            }),
+        )?;
+        self.notify("initialized", json!({}))
+    }
+
+    fn did_open(&mut self, uri: &str, language_id: &str, text: &str) -> Result<(), String> {
+        self.documents.insert(uri.to_owned(), text.to_owned());
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": language_id, "version": 1, "text": text }
+            }),
+        )
+    }
+
+    // `line` and `column` are 1-based, as shown in editors; LSP positions are 0-based
+    fn completion(&mut self, uri: &str, line: usize, column: usize) -> Result<Vec<Completion>, String> {
+        let result = self.request("textDocument/completion", self.text_document_position(uri, line, column))?;
+
+        // The result is either a CompletionItem[] or a CompletionList { items }
+        let items = match &result {
+            Value::Array(items) => items.clone(),
+            Value::Object(list) => list.get("items").and_then(Value::as_array).cloned().unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+        Ok(items
+            .iter()
+            .filter_map(|item| {
+                Some(Completion {
+                    label: item.get("label")?.as_str()?.to_owned(),
+                    kind: item.get("kind").and_then(Value::as_u64).map(|kind| completion_kind_name(kind).to_owned()),
+                    detail: item.get("detail").and_then(Value::as_str).map(|detail| detail.to_owned()),
+                    documentation: item.get("documentation").and_then(markup_to_string),
+                    insert_text: item.get("insertText").and_then(Value::as_str).map(|text| text.to_owned()),
+                })
+            })
+            .collect())
+    }
+
+    fn hover(&mut self, uri: &str, line: usize, column: usize) -> Result<Option<String>, String> {
+        let result = self.request("textDocument/hover", self.text_document_position(uri, line, column))?;
+        Ok(result.get("contents").and_then(markup_to_string))
+    }
+
+    fn text_document_position(&self, uri: &str, line: usize, column: usize) -> Value {
+        let text = self.documents.get(uri).map_or("", String::as_str);
+        json!({ "textDocument": { "uri": uri }, "position": lsp_position(text, line, column) })
+    }
+
+    fn shutdown(mut self) -> Result<(), String> {
+        self.request("shutdown", Value::Null)?;
+        self.notify("exit", Value::Null)?;
+        let status = self
+            .server
+            .wait()
+            .map_err(|e| format!("Failed to wait for language server: {}", e))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("Language server exited with {}", status))
+        }
+    }
+}
+
+// A server left behind by an error is killed; after a clean shutdown this does nothing
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        let _ = self.server.kill();
+        let _ = self.server.wait();
+    }
+}
+
+// Reads one `Content-Length` framed JSON-RPC message; returns None at the end of the stream
+fn read_lsp_message(reader: &mut impl BufRead) -> Result<Option<Value>, String> {
+    let mut content_length = None;
+
+    loop {
+        let mut header = String::new();
+        let read = reader
+            .read_line(&mut header)
+            .map_err(|e| format!("Failed to read message header: {}", e))?;
+        if read == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse::<usize>().ok();
+        }
+    }
+
+    let length = content_length.ok_or("Received a message without Content-Length")?;
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| format!("Failed to read message body: {}", e))?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| format!("Invalid JSON-RPC message: {}", e))
+}
+
+fn write_lsp_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+// LSP positions are 0-based and count UTF-16 code units; ours are 1-based and count characters
+fn lsp_position(text: &str, line: usize, column: usize) -> Value {
+    let line_text = text.lines().nth(line.saturating_sub(1)).unwrap_or_default();
+    let character: usize = line_text.chars().take(column.saturating_sub(1)).map(char::len_utf16).sum();
+    json!({ "line": line.saturating_sub(1), "character": character })
+}
+
+// The 0-based character column of a 0-based UTF-16 column on the given line
+fn character_column(text: &str, line: usize, utf16_column: usize) -> usize {
+    let mut units = 0;
+    text.lines()
+        .nth(line)
+        .unwrap_or_default()
+        .chars()
+        .take_while(|c| {
+            units += c.len_utf16();
+            units <= utf16_column
+        })
+        .count()
+}
+
+// Hover contents and documentation may be a string, a MarkupContent, a MarkedString or a list of those
+fn markup_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Object(markup) => markup.get("value").and_then(Value::as_str).map(|text| text.to_owned()),
+        Value::Array(parts) => {
+            let parts: Vec<String> = parts.iter().filter_map(markup_to_string).collect();
+            if parts.is_empty() {
+                None
+            } else {
+                Some(parts.join("\n"))
+            }
+        }
+        _ => None,
+    }
+}
+
+fn completion_kind_name(kind: u64) -> &'static str {
+    match kind {
+        1 => "text",
+        2 => "method",
+        3 => "function",
+        4 => "constructor",
+        5 => "field",
+        6 => "variable",
+        7 => "class",
+        8 => "interface",
+        9 => "module",
+        10 => "property",
+        11 => "unit",
+        12 => "value",
+        13 => "enum",
+        14 => "keyword",
+        15 => "snippet",
+        16 => "color",
+        17 => "file",
+        18 => "reference",
+        19 => "folder",
+        20 => "enum member",
+        21 => "constant",
+        22 => "struct",
+        23 => "event",
+        24 => "operator",
+        25 => "type parameter",
+        _ => "unknown",
+    }
+}
+
+fn file_uri(file_path: &str) -> Result<String, String> {
+    let path = fs::canonicalize(file_path).map_err(|e| format!("Failed to resolve {}: {}", file_path, e))?;
+    // Percent-encoded, so paths with spaces stay valid URIs
+    Url::from_file_path(&path)
+        .map(String::from)
+        .map_err(|_| format!("Cannot express {} as a file URI", path.display()))
+}
+
+// Decodes a `file://` URI such as `file:///home/me/Modal%20Logic.rs`
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+// Starts `server`, opens the file and runs `query` against it
+fn with_language_server<T>(
+    language: &dyn Language,
+    server: &LanguageServerConfig,
+    file_path: &str,
+    query: impl FnOnce(&mut LspClient, &str) -> Result<T, String>,
+) -> Result<T, String> {
+    let uri = file_uri(file_path)?;
+    let root = Path::new(file_path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let root_uri = file_uri(&root.to_string_lossy())?;
+    let text = fs::read_to_string(file_path).map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+
+    let mut client = LspClient::start(server)?;
+    let value = client
+        .initialize(&root_uri)
+        .and_then(|()| client.did_open(&uri, language.id(), &text))
+        .and_then(|()| query(&mut client, &uri))?;
+    // The answer is already in; a server that does not shut down cleanly is only worth a warning
+    if let Err(err) = client.shutdown() {
+        eprintln!("Warning: {}", err);
+    }
+    Ok(value)
+}
+
+fn get_completions(
+    language: &dyn Language,
+    server: &LanguageServerConfig,
+    file_path: &str,
+    line: usize,
+    column: usize,
+) -> Result<Vec<Completion>, String> {
+    with_language_server(language, server, file_path, |client, uri| client.completion(uri, line, column))
+}
+
+fn get_hover(
+    language: &dyn Language,
+    server: &LanguageServerConfig,
+    file_path: &str,
+    line: usize,
+    column: usize,
+) -> Result<Option<String>, String> {
+    with_language_server(language, server, file_path, |client, uri| client.hover(uri, line, column))
+}
+
+fn analyze_code(language: &dyn Language, code: &str) {
+    // Extract function signatures, types and other declarations
+    println!("Performing {} analysis:", language.name());
+    language.analyze(code);
+}
+
+fn analyze_rust_code(code: &str) {
+    if let Ok(syntax_tree) = syn::parse_file(code) {
+        // The same walk that feeds completion, so items in inline modules are reported too
+        walk_rust_items(&syntax_tree.items, "crate", &mut |item, _| match item {
+            Item::Fn(func) => analyze_rust_function(func),
+            Item::Struct(st) => analyze_rust_struct(st),
+            _ => {}
+        });
+    } else {
+        for diagnostic in collect_rust_diagnostics(code) {
+            eprintln!("{}", render_diagnostic(&diagnostic, "<snippet>", code));
+        }
+    }   
+}
+
+fn analyze_rust_function(func: &syn::ItemFn) {
+    println!("Found Rust function: {}", func.sig.ident);
+    let signature = FunctionSignature::from_signature(&func.sig, None);
+
+    // Display function visibility
+    match &func.vis {
+        syn::Visibility::Public(_) => println!("Function is public."),
+        syn::Visibility::Crate(_) => println!("Function has crate visibility."),
+        syn::Visibility::Restricted(_) => println!("Function has restricted visibility."),
+        syn::Visibility::Inherited => {} // Do nothing for inherited visibility
+    }
+
+    // Display function parameters
+    for (name, ty) in &signature.parameters {
+        println!("Parameter {}: {}", name, ty);
+    }
+
+    // Display return type
+    match &signature.return_type {
+        Some(return_type) => println!("Return Type: {}", return_type),
+        None => println!("Function has no return type."),
+    }
+}
+
+fn analyze_rust_struct(struct_item: &ItemStruct) {
+    println!("Found Rust struct: {}", struct_item.ident);
+
+    // Display struct visibility
+    match &struct_item.vis {
+        Visibility::Public(_) => println!("Struct is public."),
+        Visibility::Crate(_) => println!("Struct has crate visibility."),
+        Visibility::Restricted(_) => println!("Struct has restricted visibility."),
+        Visibility::Inherited => {} // Do nothing for inherited visibility
+    }
+
+    // Display struct fields and their types
+    for field in &struct_item.fields {
+        if let Some(ident) = &field.ident {
+            let field_type = &field.ty;
+            println!("Struct field {}: {:?}", ident, quote! {#field_type});
+        }
+    }
+
+    // Generate code snippet for struct initialization
+    let struct_name = &struct_item.ident;
+    let struct_fields: Vec<Ident> = struct_item.fields.iter().filter_map(|f| f.ident.clone()).collect();
+    let init_code = quote! {
+        let instance = #struct_name {
+            #(#struct_fields: Default::default()),*
+        };
+    };
+
+    println!("Code snippet for struct initialization:\n{}", init_code);
+}
+
+// A completion that expands to a code skeleton; the body uses LSP snippet syntax
+// with tab stops `$1`, placeholders `${1:name}` and the final cursor position `$0`
+#[derive(Debug, Clone)]
+struct Snippet {
+    trigger: String,
+    description: String,
+    body: String,
+}
+
+impl Snippet {
+    fn new(trigger: &str, description: &str, body: &str) -> Self {
+        Snippet {
+            trigger: trigger.to_owned(),
+            description: description.to_owned(),
+            body: body.to_owned(),
+        }
+    }
+
+    // The body as it reads once expanded, with placeholders replaced by their default text
+    fn preview(&self) -> String {
+        let placeholder = Regex::new(r"\$\{\d+:([^}]*)\}").unwrap();
+        let tab_stop = Regex::new(r"\$\{\d+\}|\$\d+").unwrap();
+        let text = placeholder.replace_all(&self.body, "$1");
+        tab_stop.replace_all(&text, "").into_owned()
+    }
+
+    fn to_completion(&self) -> Completion {
+        Completion {
+            label: self.trigger.clone(),
+            kind: Some("snippet".to_owned()),
+            detail: Some(self.description.clone()),
+            documentation: Some(self.preview()),
+            insert_text: Some(self.body.clone()),
+        }
+    }
+}
+
+// Directory holding the user's snippet files, one `<language id>.json` per language
+fn snippets_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("snippets"))
+}
+
+// Reads a snippet file in the format editors already use:
+// { "Name": { "prefix": "trigger", "body": ["line", "line"], "description": "..." } }
+fn load_snippet_file(path: &Path) -> Result<Vec<Snippet>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let definitions: serde_json::Map<String, Value> =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid snippet file {}: {}", path.display(), e))?;
+
+    let mut snippets = Vec::new();
+    for (name, definition) in definitions {
+        let lines_of = |value: &Value| -> Option<Vec<String>> {
+            match value {
+                Value::String(text) => Some(vec![text.clone()]),
+                Value::Array(lines) => lines.iter().map(|line| line.as_str().map(|line| line.to_owned())).collect(),
+                _ => None,
+            }
+        };
+        let prefixes = lines_of(&definition["prefix"])
+            .ok_or_else(|| format!("Snippet '{}' in {} needs a \"prefix\"", name, path.display()))?;
+        let body = lines_of(&definition["body"])
+            .ok_or_else(|| format!("Snippet '{}' in {} needs a \"body\"", name, path.display()))?
+            .join("\n");
+        let description = definition["description"].as_str().unwrap_or(&name);
+
+        for prefix in prefixes {
+            snippets.push(Snippet::new(&prefix, description, &body));
+        }
+    }
+    Ok(snippets)
+}
+
+// Built-in snippets of the language followed by the user's, which replace built-ins with the same trigger
+fn load_snippets(language: &dyn Language) -> Vec<Snippet> {
+    let mut snippets = language.snippets();
+
+    if let Some(path) = snippets_dir().map(|dir| dir.join(format!("{}.json", language.id()))) {
+        if path.exists() {
+            match load_snippet_file(&path) {
+                Ok(user_snippets) => {
+                    snippets.retain(|snippet| !user_snippets.iter().any(|user| user.trigger == snippet.trigger));
+                    snippets.extend(user_snippets);
+                }
+                Err(err) => eprintln!("Warning: {}", err),
+            }
+        }
+    }
+    snippets
+}
+
+fn snippet_completions(snippets: &[Snippet], prefix: &str) -> Vec<Completion> {
+    snippets
+        .iter()
+        .filter(|snippet| snippet.trigger.starts_with(prefix))
+        .map(Snippet::to_completion)
+        .collect()
+}
+
+// Parameters and return type of a function, shared by the analysis output and signature help
+#[derive(Debug, Clone)]
+struct FunctionSignature {
+    name: String,
+    // Type or trait of a method or associated function
+    container: Option<String>,
+    // `self`, `&self` or `&mut self`
+    receiver: Option<String>,
+    // (pattern, type) pairs, without the receiver
+    parameters: Vec<(String, String)>,
+    return_type: Option<String>,
+}
+
+impl FunctionSignature {
+    fn from_signature(sig: &Signature, container: Option<String>) -> Self {
+        let mut receiver = None;
+        let mut parameters = Vec::new();
+        for input in &sig.inputs {
+            match input {
+                syn::FnArg::Receiver(self_argument) => receiver = Some(tokens_to_string(self_argument)),
+                syn::FnArg::Typed(typed) => parameters.push((tokens_to_string(&typed.pat), tokens_to_string(&typed.ty))),
+            }
+        }
+        let return_type = match &sig.output {
+            ReturnType::Default => None,
+            ReturnType::Type(_, ty) => Some(tokens_to_string(ty)),
+        };
+
+        FunctionSignature {
+            name: sig.ident.to_string(),
+            container,
+            receiver,
+            parameters,
+            return_type,
+        }
+    }
+
+    // `fn name(&self, a: i32) -> i32` and the byte range of each parameter within it
+    fn label(&self) -> (String, Vec<(usize, usize)>) {
+        let mut label = format!("fn {}(", self.name);
+        let mut ranges = Vec::new();
+
+        if let Some(receiver) = &self.receiver {
+            label.push_str(receiver);
+            if !self.parameters.is_empty() {
+                label.push_str(", ");
+            }
+        }
+        for (index, (pattern, ty)) in self.parameters.iter().enumerate() {
+            if index > 0 {
+                label.push_str(", ");
+            }
+            let start = label.len();
+            label.push_str(&format!("{}: {}", pattern, ty));
+            ranges.push((start, label.len()));
+        }
+        label.push(')');
+        if let Some(return_type) = &self.return_type {
+            label.push_str(&format!(" -> {}", return_type));
+        }
+        (label, ranges)
+    }
+}
+
+// Signatures of the functions, methods and trait methods declared in the code
+fn collect_function_signatures(code: &str) -> syn::Result<Vec<FunctionSignature>> {
+    fn collect(items: &[Item], signatures: &mut Vec<FunctionSignature>) {
+        for item in items {
+            match item {
+                Item::Fn(function) => signatures.push(FunctionSignature::from_signature(&function.sig, None)),
+                Item::Impl(implementation) => {
+                    let container = tokens_to_string(&implementation.self_ty);
+                    for impl_item in &implementation.items {
+                        if let syn::ImplItem::Method(method) = impl_item {
+                            signatures.push(FunctionSignature::from_signature(&method.sig, Some(container.clone())));
+                        }
+                    }
+                }
+                Item::Trait(trait_item) => {
+                    for item in &trait_item.items {
+                        if let syn::TraitItem::Method(method) = item {
+                            signatures.push(FunctionSignature::from_signature(&method.sig, Some(trait_item.ident.to_string())));
+                        }
+                    }
+                }
+                Item::Mod(module) => {
+                    if let Some((_, items)) = &module.content {
+                        collect(items, signatures);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let file = syn::parse_file(code)?;
+    let mut signatures = Vec::new();
+    collect(&file.items, &mut signatures);
+    Ok(signatures)
+}
+
+// The call surrounding the cursor
+struct CallContext {
+    name: String,
+    // `Type` in `Type::name(`
+    qualifier: Option<String>,
+    is_method: bool,
+    // 0-based index of the argument being typed
+    argument: usize,
+}
+
+// Walks back from the cursor to the unclosed `(` of the call, counting the commas on the way
+fn call_context(text_before: &str) -> Option<CallContext> {
+    let chars: Vec<char> = text_before.chars().collect();
+    let mut depth = 0;
+    let mut argument = 0;
+    let mut position = chars.len();
+
+    let open_paren = loop {
+        position = position.checked_sub(1)?;
+        match chars[position] {
+            ')' | ']' | '}' => depth += 1,
+            '[' | '{' if depth > 0 => depth -= 1,
+            '(' if depth > 0 => depth -= 1,
+            '(' => break position,
+            ',' if depth == 0 => argument += 1,
+            // A statement or block boundary means the cursor is not inside a call
+            ';' | '{' | '[' => return None,
+            '"' => {
+                // Skip back over a string literal
+                loop {
+                    position = position.checked_sub(1)?;
+                    if chars[position] == '"' && (position == 0 || chars[position - 1] != '\\') {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    };
+
+    let is_identifier = |c: char| c.is_alphanumeric() || c == '_';
+    let mut end = open_paren;
+    while end > 0 && chars[end - 1].is_whitespace() {
+        end -= 1;
+    }
+    let mut start = end;
+    while start > 0 && is_identifier(chars[start - 1]) {
+        start -= 1;
+    }
+    if start == end {
+        return None;
+    }
+    let name: String = chars[start..end].iter().collect();
+
+    let before: String = chars[..start].iter().collect();
+    let before = before.trim_end();
+    let is_method = before.ends_with('.');
+    let qualifier = before.strip_suffix("::").map(|path| {
+        path.chars()
+            .rev()
+            .take_while(|&c| is_identifier(c))
+            .collect::<Vec<char>>()
+            .into_iter()
+            .rev()
+            .collect::<String>()
+    });
+
+    Some(CallContext {
+        name,
+        qualifier: qualifier.filter(|qualifier| !qualifier.is_empty()),
+        is_method,
+        argument,
+    })
+}
+
+// The code with the 1-based line emptied, keeping the other lines where they are
+fn without_line(code: &str, line: usize) -> String {
+    let lines: Vec<&str> = code
+        .lines()
+        .enumerate()
+        .map(|(index, text)| if index + 1 == line { "" } else { text })
+        .collect();
+    lines.join("\n")
+}
+
+// The signature of the function called at the cursor and the index of the current parameter
+fn signature_help(code: &str, line: usize, column: usize) -> Option<(FunctionSignature, usize)> {
+    let call = call_context(&text_before_position(code, line, column))?;
+
+    // Code being typed rarely parses; retry without the line under the cursor
+    let signatures = collect_function_signatures(code)
+        .or_else(|_| collect_function_signatures(&without_line(code, line)))
+        .ok()?;
+
+    let candidates = signatures.into_iter().filter(|signature| signature.name == call.name);
+    let signature = candidates
+        .max_by_key(|signature| {
+            let same_qualifier = call.qualifier.is_some() && signature.container == call.qualifier;
+            let same_call_style = signature.receiver.is_some() == call.is_method;
+            (same_qualifier, same_call_style)
+        })?;
+
+    // `Type::method(value, ...)` passes the receiver as the first argument
+    let argument = if !call.is_method && signature.receiver.is_some() {
+        call.argument.checked_sub(1)?
+    } else {
+        call.argument
+    };
+    Some((signature, argument))
+}
+
+// The label with the current parameter in bold
+fn render_signature_help(signature: &FunctionSignature, argument: usize) -> String {
+    let (label, ranges) = signature.label();
+    match ranges.get(argument) {
+        Some(&(start, end)) => format!("{}\x1b[1;4m{}\x1b[0m{}", &label[..start], &label[start..end], &label[end..]),
+        None => label,
+    }
+}
+
+fn lsp_signature_help(signature: &FunctionSignature, argument: usize) -> Value {
+    let (label, ranges) = signature.label();
+    // LSP offsets count UTF-16 code units
+    let utf16_offset = |byte: usize| label[..byte].encode_utf16().count();
+    let parameters: Vec<Value> = ranges
+        .iter()
+        .map(|&(start, end)| json!({ "label": [utf16_offset(start), utf16_offset(end)] }))
+        .collect();
+    json!({
+        "signatures": [{ "label": label, "parameters": parameters }],
+        "activeSignature": 0,
+        "activeParameter": argument,
+    })
+}
+
+// `signature --file x.rs --line L --col C [--format text|json]`
+fn run_signature_command(args: &[String]) -> Result<(), String> {
+    let options = parse_complete_args(args)?;
+    let code = fs::read_to_string(&options.file).map_err(|e| format!("Failed to read {}: {}", options.file, e))?;
+    let help = signature_help(&code, options.line, options.column);
+
+    match (options.format, help) {
+        (OutputFormat::Json, Some((signature, argument))) => {
+            let (label, _) = signature.label();
+            let output = json!({
+                "label": label,
+                "name": signature.name,
+                "container": signature.container,
+                "parameters": signature
+                    .parameters
+                    .iter()
+                    .map(|(pattern, ty)| json!({ "name": pattern, "type": ty }))
+                    .collect::<Vec<Value>>(),
+                "return_type": signature.return_type,
+                "active_parameter": argument,
+            });
+            println!("{}", serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?);
+        }
+        (OutputFormat::Json, None) => println!("null"),
+        (OutputFormat::Text, Some((signature, argument))) => println!("{}", render_signature_help(&signature, argument)),
+        (OutputFormat::Text, None) => println!("No call at {}:{}", options.line, options.column),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SymbolKind {
+    Function,
+    Method,
+    Struct,
+    Field,
+    Enum,
+    Variant,
+    Trait,
+    Impl,
+    Module,
+}
+
+// 1-based start and end positions of a symbol in its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceRange {
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+}
+
+impl SourceRange {
+    // Needs proc-macro2's "span-locations" feature, otherwise every span is at line 0
+    fn from_span(span: proc_macro2::Span) -> Self {
+        SourceRange {
+            start_line: span.start().line,
+            start_column: span.start().column + 1,
+            end_line: span.end().line,
+            end_column: span.end().column + 1,
+        }
+    }
+}
+
+// A named item found by the analysis, with a one-line description used for completion details and hover
+#[derive(Debug, Clone)]
+struct Symbol {
+    name: String,
+    // Module path of the symbol, e.g. `crate::shapes::Point`
+    path: String,
+    kind: SymbolKind,
+    detail: String,
+    container: Option<String>,
+    // Index of the enclosing symbol (struct, impl, module, class…) in the same file's list
+    parent: Option<usize>,
+    range: SourceRange,
+}
+
+// Renders a syntax node as source text, without the spaces `quote` puts between tokens
+fn tokens_to_string(tokens: &impl ToTokens) -> String {
+    let mut text = tokens.to_token_stream().to_string();
+    for (spaced, tight) in [
+        (" :: ", "::"),
+        (" < ", "<"),
+        ("< ", "<"),
+        (" >", ">"),
+        (" ,", ","),
+        ("& ", "&"),
+        ("( ", "("),
+        (" )", ")"),
+        ("[ ", "["),
+        (" ]", "]"),
+        (" ;", ";"),
+        (" :", ":"),
+    ] {
+        text = text.replace(spaced, tight);
+    }
+    text
+}
+
+fn format_signature(sig: &Signature) -> String {
+    let inputs: Vec<String> = sig.inputs.iter().map(tokens_to_string).collect();
+    let output = match &sig.output {
+        ReturnType::Default => String::new(),
+        ReturnType::Type(_, ty) => format!(" -> {}", tokens_to_string(ty)),
+    };
+    format!("fn {}({}){}", sig.ident, inputs.join(", "), output)
+}
+
+fn collect_rust_symbols(code: &str) -> syn::Result<Vec<Symbol>> {
+    collect_rust_symbols_in_module(code, "crate")
+}
+
+// Collects the symbols of a file whose items live in the module `module_path`
+fn collect_rust_symbols_in_module(code: &str, module_path: &str) -> syn::Result<Vec<Symbol>> {
+    let syntax_tree = syn::parse_file(code)?;
+    let mut symbols = Vec::new();
+    // Index of the symbol of every inline module, by module path; the walker reaches a
+    // module's items only after the module itself
+    let mut modules: HashMap<String, usize> = HashMap::new();
+    walk_rust_items(&syntax_tree.items, module_path, &mut |item, item_module_path| {
+        if let Item::Mod(module) = item {
+            modules.insert(format!("{}::{}", item_module_path, module.ident), symbols.len());
+        }
+        let parent = modules.get(item_module_path).copied();
+        collect_item_symbols(item, item_module_path, parent, &mut symbols)
+    });
+    Ok(symbols)
+}
+
+// Calls `visit` with every item and the path of its module; inline modules are
+// walked after their siblings
+fn walk_rust_items(items: &[Item], module_path: &str, visit: &mut impl FnMut(&Item, &str)) {
+    for item in items {
+        visit(item, module_path);
+    }
+    for item in items {
+        if let Item::Mod(module) = item {
+            if let Some((_, module_items)) = &module.content {
+                walk_rust_items(module_items, &format!("{}::{}", module_path, module.ident), visit);
+            }
+        }
+    }
+}
+
+// Collects the symbols of one item; `parent` is the symbol of the inline module holding it.
+// Members (fields, variants, methods) get the item's own symbol as their parent
+fn collect_item_symbols(item: &Item, module_path: &str, parent: Option<usize>, symbols: &mut Vec<Symbol>) {
+    let item_index = symbols.len();
+    let mut push = |name: String, kind: SymbolKind, detail: String, container: Option<String>, span: proc_macro2::Span| {
+        let path = match &container {
+            Some(container) => format!("{}::{}::{}", module_path, container, name),
+            None => format!("{}::{}", module_path, name),
+        };
+        symbols.push(Symbol {
+            name,
+            path,
+            kind,
+            detail,
+            parent: if container.is_some() { Some(item_index) } else { parent },
+            container,
+            range: SourceRange::from_span(span),
+        });
+    };
+
+    match item {
+        Item::Fn(func) => push(
+            func.sig.ident.to_string(),
+            SymbolKind::Function,
+            format_signature(&func.sig),
+            None,
+            func.sig.ident.span(),
+        ),
+        Item::Struct(st) => {
+            let struct_name = st.ident.to_string();
+            push(struct_name.clone(), SymbolKind::Struct, format!("struct {}", struct_name), None, st.ident.span());
+
+            for field in &st.fields {
+                if let Some(ident) = &field.ident {
+                    push(
+                        ident.to_string(),
+                        SymbolKind::Field,
+                        format!("{}: {}", ident, tokens_to_string(&field.ty)),
+                        Some(struct_name.clone()),
+                        ident.span(),
+                    );
+                }
+            }
+        }
+        Item::Enum(en) => {
+            let enum_name = en.ident.to_string();
+            push(enum_name.clone(), SymbolKind::Enum, format!("enum {}", enum_name), None, en.ident.span());
+
+            for variant in &en.variants {
+                push(
+                    variant.ident.to_string(),
+                    SymbolKind::Variant,
+                    format!("{}::{}", enum_name, tokens_to_string(variant)),
+                    Some(enum_name.clone()),
+                    variant.ident.span(),
+                );
+            }
+        }
+        Item::Trait(tr) => {
+            let trait_name = tr.ident.to_string();
+            push(trait_name.clone(), SymbolKind::Trait, format!("trait {}", trait_name), None, tr.ident.span());
+
+            for trait_item in &tr.items {
+                if let syn::TraitItem::Method(method) = trait_item {
+                    push(
+                        method.sig.ident.to_string(),
+                        SymbolKind::Method,
+                        format_signature(&method.sig),
+                        Some(trait_name.clone()),
+                        method.sig.ident.span(),
+                    );
+                }
+            }
+        }
+        Item::Impl(imp) => {
+            let self_type = tokens_to_string(&imp.self_ty);
+            let detail = match &imp.trait_ {
+                Some((_, trait_path, _)) => format!("impl {} for {}", tokens_to_string(trait_path), self_type),
+                None => format!("impl {}", self_type),
+            };
+            push(self_type.clone(), SymbolKind::Impl, detail, None, imp.impl_token.span);
+
+            for impl_item in &imp.items {
+                if let syn::ImplItem::Method(method) = impl_item {
+                    push(
+                        method.sig.ident.to_string(),
+                        SymbolKind::Method,
+                        format_signature(&method.sig),
+                        Some(self_type.clone()),
+                        method.sig.ident.span(),
+                    );
+                }
+            }
+        }
+        Item::Mod(module) => {
+            let module_name = module.ident.to_string();
+            push(module_name.clone(), SymbolKind::Module, format!("mod {}", module_name), None, module.ident.span());
+        }
+        _ => {}
+    }
+}
+
+// Symbols of every `.rs` file under a crate directory, re-parsed only when a file changes
+struct SymbolIndex {
+    root: PathBuf,
+    files: HashMap<PathBuf, IndexedFile>,
+}
+
+struct IndexedFile {
+    modified: Option<SystemTime>,
+    symbols: Vec<Symbol>,
+    error: Option<String>,
+}
+
+impl SymbolIndex {
+    fn build(root: &Path) -> Self {
+        let mut index = SymbolIndex {
+            root: root.to_path_buf(),
+            files: HashMap::new(),
+        };
+        index.refresh();
+        index
+    }
+
+    // Re-parses new and modified files and forgets deleted ones; returns how many files were parsed
+    fn refresh(&mut self) -> usize {
+        let mut paths = Vec::new();
+        find_rust_files(&self.root, &mut paths);
+
+        self.files.retain(|path, _| paths.contains(path));
+
+        let mut parsed = 0;
+        for path in paths {
+            let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+            let unchanged = self
+                .files
+                .get(&path)
+                .is_some_and(|indexed| modified.is_some() && indexed.modified == modified);
+            if !unchanged {
+                self.update_file(&path);
+                parsed += 1;
+            }
+        }
+        parsed
+    }
+
+    // Re-indexes a single file from disk
+    fn update_file(&mut self, path: &Path) {
+        match fs::read_to_string(path) {
+            Ok(code) => {
+                let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+                self.update_file_text(path, &code, modified);
+            }
+            Err(_) => {
+                self.files.remove(path);
+            }
+        }
+    }
+
+    // Re-indexes a file from text that may not be saved yet (e.g. an editor buffer); text that
+    // does not parse keeps the symbols of the last version that did, next to the error
+    fn update_file_text(&mut self, path: &Path, code: &str, modified: Option<SystemTime>) {
+        let module_path = module_path_for_file(&self.root, path);
+        let (symbols, error) = match collect_rust_symbols_in_module(code, &module_path) {
+            Ok(symbols) => (symbols, None),
+            Err(err) => {
+                let last_symbols = self.files.remove(path).map(|indexed| indexed.symbols).unwrap_or_default();
+                (last_symbols, Some(err.to_string()))
+            }
+        };
+        self.files.insert(
+            path.to_path_buf(),
+            IndexedFile {
+                modified,
+                symbols,
+                error,
+            },
+        );
+    }
+
+    fn symbols(&self) -> impl Iterator<Item = (&PathBuf, &Symbol)> {
+        self.files
+            .iter()
+            .flat_map(|(path, indexed)| indexed.symbols.iter().map(move |symbol| (path, symbol)))
+    }
+
+    fn errors(&self) -> impl Iterator<Item = (&PathBuf, &String)> {
+        self.files
+            .iter()
+            .filter_map(|(path, indexed)| indexed.error.as_ref().map(|error| (path, error)))
+    }
+}
+
+fn find_rust_files(dir: &Path, paths: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if path.is_dir() {
+            // Skip build output and hidden directories such as .git
+            if name != "target" && !name.starts_with('.') {
+                find_rust_files(&path, paths);
+            }
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            paths.push(path);
+        }
+    }
+}
+
+// `src/lib.rs` and `src/main.rs` are the crate root, `src/a/b.rs` and `src/a/b/mod.rs` are `crate::a::b`
+fn module_path_for_file(root: &Path, path: &Path) -> String {
+    let src = root.join("src");
+    let relative = path
+        .strip_prefix(&src)
+        .or_else(|_| path.strip_prefix(root))
+        .unwrap_or(path);
+
+    let mut segments: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if let Some(last) = segments.last() {
+        if last == "mod" || (segments.len() == 1 && (last == "lib" || last == "main")) {
+            segments.pop();
+        }
+    }
+
+    std::iter::once("crate".to_owned()).chain(segments).collect::<Vec<String>>().join("::")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CppTokenKind {
+    Identifier,
+    Number,
+    StringLiteral,
+    CharLiteral,
+    Punctuation,
+}
+
+#[derive(Debug, Clone)]
+struct CppToken {
+    kind: CppTokenKind,
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl CppToken {
+    fn is(&self, text: &str) -> bool {
+        matches!(self.kind, CppTokenKind::Punctuation | CppTokenKind::Identifier) && self.text == text
+    }
+
+    fn is_identifier(&self) -> bool {
+        self.kind == CppTokenKind::Identifier
+    }
+}
+
+// Index just past a quoted literal starting at `start`, honouring backslash escapes
+fn end_of_quoted(chars: &[char], start: usize) -> usize {
+    let quote = chars[start];
+    let mut i = start + 1;
+    while i < chars.len() && chars[i] != quote && chars[i] != '\n' {
+        if chars[i] == '\\' {
+            i += 1;
+        }
+        i += 1;
+    }
+    (i + 1).min(chars.len())
+}
+
+// Index just past a raw string R"delim( ... )delim" whose opening quote is at `quote`
+fn end_of_raw_string(chars: &[char], quote: usize) -> usize {
+    let open = (quote + 1..chars.len()).find(|&i| chars[i] == '(').unwrap_or(chars.len());
+    let mut terminator = vec![')'];
+    terminator.extend_from_slice(&chars[quote + 1..open]);
+    terminator.push('"');
+
+    (open..chars.len())
+        .find(|&i| chars[i..].starts_with(&terminator))
+        .map_or(chars.len(), |i| i + terminator.len())
+}
+
+// Splits C++ source into tokens, dropping comments and preprocessor lines.
+// `>>` is always two `>` tokens so that nested template argument lists close correctly.
+fn tokenize_cpp(code: &str) -> Vec<CppToken> {
+    let chars: Vec<char> = code.chars().collect();
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(chars.iter().enumerate().filter(|(_, c)| **c == '\n').map(|(i, _)| i + 1))
+        .collect();
+    // 1-based line and column of a character index
+    let position = |index: usize| {
+        let line = line_starts.partition_point(|&start| start <= index);
+        (line, index - line_starts[line - 1] + 1)
+    };
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut at_line_start = true;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let start = i;
+
+        if c == '\n' {
+            at_line_start = true;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        // Preprocessor directives run to the end of the line, including backslash continuations
+        if c == '#' && at_line_start {
+            while i < chars.len() && !(chars[i] == '\n' && chars[i - 1] != '\\') {
+                i += 1;
+            }
+            continue;
+        }
+        at_line_start = false;
+
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        if c == '/' && next == Some('*') {
+            i = (i + 2..chars.len())
+                .find(|&k| chars[k] == '*' && chars.get(k + 1) == Some(&'/'))
+                .map_or(chars.len(), |k| k + 2);
+            continue;
+        }
+
+        let kind = if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+
+            // Encoding prefixes (u8, u, U, L) and raw strings (R"delim( ... )delim")
+            let is_prefix = matches!(word.as_str(), "R" | "u8R" | "uR" | "UR" | "LR" | "u8" | "u" | "U" | "L");
+            if is_prefix && chars.get(i) == Some(&'"') {
+                i = if word.ends_with('R') {
+                    end_of_raw_string(&chars, i)
+                } else {
+                    end_of_quoted(&chars, i)
+                };
+                CppTokenKind::StringLiteral
+            } else if is_prefix && chars.get(i) == Some(&'\'') {
+                i = end_of_quoted(&chars, i);
+                CppTokenKind::CharLiteral
+            } else {
+                CppTokenKind::Identifier
+            }
+        } else if c.is_ascii_digit() || (c == '.' && next.is_some_and(|n| n.is_ascii_digit())) {
+            // Digits, digit separators, suffixes and exponents such as 1'000, 0x1Fu, 1.5e-3f
+            i += 1;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric()
+                    || chars[i] == '.'
+                    || chars[i] == '\''
+                    || ((chars[i] == '+' || chars[i] == '-') && matches!(chars[i - 1], 'e' | 'E' | 'p' | 'P')))
+            {
+                i += 1;
+            }
+            CppTokenKind::Number
+        } else if c == '"' {
+            i = end_of_quoted(&chars, i);
+            CppTokenKind::StringLiteral
+        } else if c == '\'' {
+            i = end_of_quoted(&chars, i);
+            CppTokenKind::CharLiteral
+        } else {
+            let text_from = |length: usize| -> String { chars[i..(i + length).min(chars.len())].iter().collect() };
+            let length = if matches!(text_from(3).as_str(), "..." | "<=>" | "->*" | "<<=") {
+                3
+            } else if matches!(
+                text_from(2).as_str(),
+                "::" | "->" | "&&" | "||" | "==" | "!=" | "<=" | ">=" | "++" | "--" | "+=" | "-=" | "*=" | "/="
+                    | "%=" | "&=" | "|=" | "^=" | "<<" | ".*"
+            ) {
+                2
+            } else {
+                1
+            };
+            i += length;
+            CppTokenKind::Punctuation
+        };
+
+        let (line, column) = position(start);
+        tokens.push(CppToken {
+            kind,
+            text: chars[start..i].iter().collect(),
+            line,
+            column,
+        });
+    }
+
+    tokens
+}
+
+// Joins tokens back into readable source, e.g. `const std::vector<int>& values`
+fn join_cpp_tokens(tokens: &[CppToken]) -> String {
+    let mut text = String::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if index > 0 {
+            let previous = &tokens[index - 1];
+            let word_like = |token: &CppToken| token.kind != CppTokenKind::Punctuation;
+            let needs_space = (word_like(previous) && word_like(token))
+                || previous.is(",")
+                || (word_like(previous) && token.is("=") && !previous.is("operator"))
+                || previous.is("=")
+                || ((previous.is(">") || previous.is(")")) && word_like(token))
+                || ((previous.is("&") || previous.is("&&") || previous.is("*")) && word_like(token));
+            if needs_space {
+                text.push(' ');
+            }
+        }
+        text.push_str(&token.text);
+    }
+    text
+}
+
+// Declaration specifiers that are not part of a function's return type
+const CPP_SPECIFIERS: &[&str] = &[
+    "virtual", "static", "inline", "explicit", "constexpr", "consteval", "friend", "extern", "mutable",
+];
+
+struct CppParser<'a> {
+    tokens: &'a [CppToken],
+    position: usize,
+    symbols: Vec<Symbol>,
+}
+
+impl<'a> CppParser<'a> {
+    fn peek(&self) -> Option<&'a CppToken> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.peek().is_some_and(|token| token.is(text))
+    }
+
+    // Skips a balanced (), [], {} or <> group starting at the current token, stopping at the
+    // end of the file so that `self.position` never passes it
+    fn skip_group(&mut self) {
+        let (open, close) = match self.peek().map(|token| token.text.as_str()) {
+            Some("(") => ("(", ")"),
+            Some("[") => ("[", "]"),
+            Some("{") => ("{", "}"),
+            Some("<") => ("<", ">"),
+            Some(_) => {
+                self.position += 1;
+                return;
+            }
+            None => return,
+        };
+
+        let mut depth = 0;
+        while let Some(token) = self.peek() {
+            self.position += 1;
+            if token.is(open) {
+                depth += 1;
+            } else if token.is(close) {
+                depth -= 1;
+                if depth == 0 {
+                    return;
+                }
+            } else if open == "<" && (token.is(";") || token.is("{")) {
+                // Not a template argument list after all
+                return;
+            }
+        }
+    }
+
+    fn skip_past_semicolon(&mut self) {
+        while let Some(token) = self.peek() {
+            if token.is(";") {
+                self.position += 1;
+                return;
+            }
+            if token.is("(") || token.is("{") || token.is("[") {
+                self.skip_group();
+            } else {
+                self.position += 1;
+            }
+        }
+    }
+
+    fn push(&mut self, name: &str, kind: SymbolKind, detail: String, scope: &[String], token: &CppToken) {
+        let container = if scope.is_empty() { None } else { Some(scope.join("::")) };
+        let path = scope.iter().cloned().chain(std::iter::once(name.to_owned())).collect::<Vec<String>>().join("::");
+        self.symbols.push(Symbol {
+            name: name.to_owned(),
+            path,
+            kind,
+            detail,
+            container,
+            parent: None,
+            range: SourceRange {
+                start_line: token.line,
+                start_column: token.column,
+                end_line: token.line,
+                end_column: token.column + token.text.chars().count(),
+            },
+        });
+    }
+
+    // Parses declarations until the closing brace of the current scope (or the end of the file)
+    fn parse_scope(&mut self, scope: &[String], in_class: bool) {
+        let mut template: Option<String> = None;
+
+        while let Some(token) = self.peek() {
+            match token.text.as_str() {
+                "}" if token.kind == CppTokenKind::Punctuation => {
+                    self.position += 1;
+                    return;
+                }
+                ";" if token.kind == CppTokenKind::Punctuation => {
+                    self.position += 1;
+                }
+                "namespace" => {
+                    self.position += 1;
+                    let mut name_tokens = Vec::new();
+                    while let Some(token) = self.peek() {
+                        if token.is("{") || token.is(";") || token.is("=") {
+                            break;
+                        }
+                        name_tokens.push(token.clone());
+                        self.position += 1;
+                    }
+
+                    if self.peek_is("{") {
+                        self.position += 1;
+                        let name = join_cpp_tokens(&name_tokens);
+                        let name = if name.is_empty() { "(anonymous)".to_owned() } else { name };
+                        let anchor = name_tokens.first().cloned().unwrap_or_else(|| token.clone());
+                        self.push(&name, SymbolKind::Module, format!("namespace {}", name), scope, &anchor);
+
+                        let mut inner_scope = scope.to_vec();
+                        inner_scope.push(name);
+                        self.parse_scope(&inner_scope, false);
+                    } else {
+                        // Namespace alias: namespace fs = std::filesystem;
+                        self.skip_past_semicolon();
+                    }
+                }
+                "template" => {
+                    self.position += 1;
+                    let start = self.position;
+                    self.skip_group();
+                    template = Some(format!("template{}", join_cpp_tokens(&self.tokens[start..self.position])));
+                    continue;
+                }
+                "public" | "private" | "protected" if in_class => {
+                    self.position += 1;
+                    if self.peek_is(":") {
+                        self.position += 1;
+                    }
+                }
+                "using" | "typedef" | "static_assert" => self.skip_past_semicolon(),
+                "class" | "struct" | "union" => self.parse_class(scope, template.take()),
+                "enum" => self.parse_enum(scope),
+                "extern" if self.tokens.get(self.position + 1).is_some_and(|next| next.kind == CppTokenKind::StringLiteral) => {
+                    // extern "C" { ... } declares its contents in the enclosing scope
+                    self.position += 2;
+                    if self.peek_is("{") {
+                        self.position += 1;
+                        self.parse_scope(scope, false);
+                    }
+                }
+                _ => self.parse_declaration(scope, in_class, template.take()),
+            }
+            template = None;
+        }
+    }
+
+    fn parse_class(&mut self, scope: &[String], template: Option<String>) {
+        let keyword = self.tokens[self.position].text.clone();
+        self.position += 1;
+
+        // Skip attributes such as [[nodiscard]] and alignas(16)
+        while self.peek_is("[") || self.peek_is("alignas") {
+            if self.peek_is("alignas") {
+                self.position += 1;
+            }
+            self.skip_group();
+        }
+
+        let name_token = match self.peek() {
+            Some(token) if token.is_identifier() => token.clone(),
+            // Anonymous class used as a type, e.g. struct { int x; } point;
+            _ => {
+                self.parse_declaration(scope, false, None);
+                return;
+            }
+        };
+        self.position += 1;
+        if self.peek_is("<") {
+            // Specialization arguments
+            self.skip_group();
+        }
+        if self.peek_is("final") {
+            self.position += 1;
+        }
+
+        let mut bases = String::new();
+        if self.peek_is(":") {
+            self.position += 1;
+            let start = self.position;
+            while let Some(token) = self.peek() {
+                if token.is("{") || token.is(";") {
+                    break;
+                }
+                if token.is("<") {
+                    self.skip_group();
+                } else {
+                    self.position += 1;
+                }
+            }
+            bases = format!(" : {}", join_cpp_tokens(&self.tokens[start..self.position]));
+        }
+
+        if !self.peek_is("{") {
+            // Forward declaration, or a variable of an elaborated type: struct stat info;
+            self.skip_past_semicolon();
+            return;
+        }
+        self.position += 1;
+
+        let name = name_token.text.clone();
+        let detail = match &template {
+            Some(template) => format!("{} {} {}{}", template, keyword, name, bases),
+            None => format!("{} {}{}", keyword, name, bases),
+        };
+        self.push(&name, SymbolKind::Struct, detail, scope, &name_token);
+
+        let mut inner_scope = scope.to_vec();
+        inner_scope.push(name);
+        self.parse_scope(&inner_scope, true);
+
+        // Declarators after the closing brace: } instance;
+        self.skip_past_semicolon();
+    }
+
+    fn parse_enum(&mut self, scope: &[String]) {
+        self.position += 1;
+        let scoped = self.peek_is("class") || self.peek_is("struct");
+        if scoped {
+            self.position += 1;
+        }
+
+        let name_token = match self.peek() {
+            Some(token) if token.is_identifier() => token.clone(),
+            _ => {
+                // Anonymous enum: record the enumerators in the enclosing scope
+                while !self.peek_is("{") && self.peek().is_some() {
+                    self.position += 1;
+                }
+                self.parse_enumerators(None, scope);
+                self.skip_past_semicolon();
+                return;
+            }
+        };
+        self.position += 1;
+
+        let mut underlying = String::new();
+        if self.peek_is(":") {
+            self.position += 1;
+            let start = self.position;
+            while !self.peek_is("{") && !self.peek_is(";") && self.peek().is_some() {
+                self.position += 1;
+            }
+            underlying = format!(" : {}", join_cpp_tokens(&self.tokens[start..self.position]));
+        }
+
+        if !self.peek_is("{") {
+            self.skip_past_semicolon();
+            return;
+        }
+
+        let name = name_token.text.clone();
+        let keyword = if scoped { "enum class" } else { "enum" };
+        self.push(&name, SymbolKind::Enum, format!("{} {}{}", keyword, name, underlying), scope, &name_token);
+        self.parse_enumerators(Some(&name), scope);
+        self.skip_past_semicolon();
+    }
+
+    fn parse_enumerators(&mut self, enum_name: Option<&str>, scope: &[String]) {
+        if !self.peek_is("{") {
+            return;
+        }
+        self.position += 1;
+
+        let mut inner_scope = scope.to_vec();
+        if let Some(enum_name) = enum_name {
+            inner_scope.push(enum_name.to_owned());
+        }
+
+        let mut expect_name = true;
+        while let Some(token) = self.peek() {
+            if token.is("}") {
+                self.position += 1;
+                return;
+            }
+            if token.is(",") {
+                expect_name = true;
+                self.position += 1;
+            } else if expect_name && token.is_identifier() {
+                let detail = match enum_name {
+                    Some(enum_name) => format!("{}::{}", enum_name, token.text),
+                    None => token.text.clone(),
+                };
+                self.push(&token.text, SymbolKind::Variant, detail, &inner_scope, token);
+                expect_name = false;
+                self.position += 1;
+            } else if token.is("(") || token.is("{") || token.is("[") {
+                self.skip_group();
+            } else {
+                self.position += 1;
+            }
+        }
+    }
+
+    // Functions, member functions, data members and variables
+    fn parse_declaration(&mut self, scope: &[String], in_class: bool, template: Option<String>) {
+        let start = self.position;
+        let mut name_index = None;
+
+        // Find the declarator: the parameter list of a function, or the end of a variable declaration
+        while let Some(token) = self.peek() {
+            let after_operator = self.position > start && self.tokens[self.position - 1].is("operator");
+            if after_operator && !token.is("(") {
+                // The symbol of an operator name, e.g. `operator=` or `operator<`
+                self.position += 1;
+                continue;
+            }
+            if token.is(";") || token.is("{") || token.is("=") || token.is("}") {
+                break;
+            }
+            if token.is("(") {
+                // `operator()` names its own parentheses
+                let previous = self.position.checked_sub(1).map(|index| &self.tokens[index]);
+                if previous.is_some_and(|previous| previous.is("operator")) {
+                    self.skip_group();
+                    continue;
+                }
+                // A name before the parameters: an identifier, a template's `>` or an operator symbol
+                let named = self.position > start
+                    && (previous.is_some_and(|previous| previous.is_identifier() || previous.is(">"))
+                        || self.operator_name_start(start, self.position - 1).is_some());
+                if named {
+                    name_index = Some(self.position - 1);
+                    break;
+                }
+                self.skip_group();
+            } else if token.is("[") || (token.is("<") && self.position > start && self.tokens[self.position - 1].is_identifier()) {
+                // Array bounds and template arguments
+                self.skip_group();
+            } else {
+                self.position += 1;
+            }
+        }
+
+        match name_index {
+            Some(name_index) => self.parse_function(scope, in_class, template, start, name_index),
+            None => {
+                if self.peek_is("{") {
+                    // Brace initializer: int values{1, 2};
+                    self.skip_group();
+                }
+                if self.peek_is("}") {
+                    self.record_data_members(scope, in_class, start, self.position);
+                    return;
+                }
+                self.skip_past_semicolon();
+                self.record_data_members(scope, in_class, start, self.position);
+            }
+        }
+    }
+
+    // Records each declarator of a data member declaration such as `float x, y = 0, z[3];`
+    fn record_data_members(&mut self, scope: &[String], in_class: bool, start: usize, end: usize) {
+        if !in_class {
+            return;
+        }
+
+        // Split at top-level commas; a `<` right after an identifier opens a template argument list
+        let mut declarators: Vec<&'a [CppToken]> = Vec::new();
+        let mut depth = 0;
+        let mut declarator_start = start;
+        for index in start..end {
+            let token = &self.tokens[index];
+            if token.is("(") || token.is("[") || token.is("{") || (token.is("<") && index > start && self.tokens[index - 1].is_identifier()) {
+                depth += 1;
+            } else if token.is(")") || token.is("]") || token.is("}") || (token.is(">") && depth > 0) {
+                depth -= 1;
+            } else if token.is(",") && depth == 0 {
+                declarators.push(&self.tokens[declarator_start..index]);
+                declarator_start = index + 1;
+            } else if token.is(";") && depth == 0 {
+                break;
+            }
+        }
+        let last_end = (start..end).find(|&index| self.tokens[index].is(";")).unwrap_or(end);
+        declarators.push(&self.tokens[declarator_start..last_end.max(declarator_start)]);
+
+        // The name is the last identifier before an initializer, array bound or bit-field width
+        let declarator_name = |declarator: &'a [CppToken]| -> Option<(usize, &'a CppToken)> {
+            let end = declarator
+                .iter()
+                .position(|token| token.is("=") || token.is("[") || token.is("{") || token.is(":"))
+                .unwrap_or(declarator.len());
+            declarator[..end]
+                .iter()
+                .enumerate()
+                .rev()
+                .find(|(_, token)| token.is_identifier())
+        };
+
+        // Pointer and reference markers belong to each declarator, the rest of the first one's prefix is shared
+        let base_type: Vec<CppToken> = match declarators.first().and_then(|first| declarator_name(first).map(|(name_position, _)| &first[..name_position])) {
+            Some(prefix) if !prefix.is_empty() => prefix.iter().filter(|token| !token.is("*") && !token.is("&")).cloned().collect(),
+            _ => return,
+        };
+
+        for (index, declarator) in declarators.iter().enumerate() {
+            if let Some((name_position, name_token)) = declarator_name(declarator) {
+                let type_text = if index == 0 {
+                    join_cpp_tokens(&declarator[..name_position])
+                } else {
+                    let mut tokens = base_type.clone();
+                    tokens.extend_from_slice(&declarator[..name_position]);
+                    join_cpp_tokens(&tokens)
+                };
+                let detail = format!("{} {}", type_text, name_token.text);
+                self.push(&name_token.text, SymbolKind::Field, detail, scope, name_token);
+            }
+        }
+    }
+
+    // Index of the `operator` keyword when the tokens up to `name_end` spell an operator name such as `operator==`
+    fn operator_name_start(&self, start: usize, name_end: usize) -> Option<usize> {
+        (name_end.saturating_sub(3).max(start)..=name_end)
+            .find(|&index| self.tokens[index].is("operator"))
+            .filter(|&index| {
+                self.tokens[index + 1..=name_end]
+                    .iter()
+                    .all(|token| token.kind == CppTokenKind::Punctuation || token.is_identifier() && index + 1 == name_end)
+            })
+    }
+
+    fn parse_function(&mut self, scope: &[String], in_class: bool, template: Option<String>, start: usize, name_index: usize) {
+        // The name may be qualified (Widget::resize), a destructor (~Widget), a template (max<int>) or an operator
+        let mut name_start = name_index;
+        let name_end = name_index + 1;
+        if let Some(operator_start) = self.operator_name_start(start, name_index) {
+            name_start = operator_start;
+        } else if self.tokens[name_index].is(">") {
+            let mut depth = 0;
+            while name_start > start {
+                if self.tokens[name_start].is(">") {
+                    depth += 1;
+                } else if self.tokens[name_start].is("<") {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                name_start -= 1;
+            }
+            name_start = name_start.saturating_sub(1).max(start);
+        }
+        if name_start > start && self.tokens[name_start - 1].is("~") {
+            name_start -= 1;
+        }
+        let mut qualifier_start = name_start;
+        while qualifier_start >= start + 2
+            && self.tokens[qualifier_start - 1].is("::")
+            && self.tokens[qualifier_start - 2].is_identifier()
+        {
+            qualifier_start -= 2;
+        }
+
+        let name_token = self.tokens[name_start].clone();
+        let name = join_cpp_tokens(&self.tokens[name_start..name_end]);
+        let qualifiers: Vec<String> = self.tokens[qualifier_start..name_start]
+            .iter()
+            .filter(|token| token.is_identifier())
+            .map(|token| token.text.clone())
+            .collect();
+
+        let return_tokens: Vec<CppToken> = self.tokens[start..qualifier_start]
+            .iter()
+            .filter(|token| !CPP_SPECIFIERS.contains(&token.text.as_str()))
+            .cloned()
+            .collect();
+        let specifiers: Vec<&str> = self.tokens[start..qualifier_start]
+            .iter()
+            .filter(|token| CPP_SPECIFIERS.contains(&token.text.as_str()))
+            .map(|token| token.text.as_str())
+            .collect();
+
+        // Parameter list
+        let parameters_start = self.position;
+        self.skip_group();
+        let parameters = join_cpp_tokens(&self.tokens[parameters_start..self.position]);
+
+        // Trailing qualifiers: const, volatile, &, &&, noexcept(...), override, final, -> type, = 0/default/delete
+        let mut trailing = Vec::new();
+        let mut trailing_return = None;
+        while let Some(token) = self.peek() {
+            match token.text.as_str() {
+                "const" | "volatile" | "&" | "&&" | "override" | "final" => {
+                    trailing.push(token.text.clone());
+                    self.position += 1;
+                }
+                "noexcept" | "throw" => {
+                    let qualifier_start = self.position;
+                    self.position += 1;
+                    if self.peek_is("(") {
+                        self.skip_group();
+                    }
+                    trailing.push(join_cpp_tokens(&self.tokens[qualifier_start..self.position]));
+                }
+                "->" => {
+                    self.position += 1;
+                    let type_start = self.position;
+                    while let Some(token) = self.peek() {
+                        if token.is("{") || token.is(";") || token.is("=") || token.is("override") || token.is("final") {
+                            break;
+                        }
+                        if token.is("<") || token.is("(") {
+                            self.skip_group();
+                        } else {
+                            self.position += 1;
+                        }
+                    }
+                    trailing_return = Some(join_cpp_tokens(&self.tokens[type_start..self.position]));
+                }
+                "=" => {
+                    // = 0, = default, = delete
+                    let qualifier_start = self.position;
+                    self.position += 1;
+                    if self.peek().is_some_and(|token| token.kind != CppTokenKind::Punctuation) {
+                        self.position += 1;
+                    }
+                    trailing.push(join_cpp_tokens(&self.tokens[qualifier_start..self.position]));
+                }
+                "[" => self.skip_group(),
+                _ => break,
+            }
+        }
+
+        let is_method = in_class || !qualifiers.is_empty();
+        let mut container_scope = scope.to_vec();
+        container_scope.extend(qualifiers);
+
+        let return_type = match trailing_return {
+            Some(trailing_return) => trailing_return,
+            None => join_cpp_tokens(&return_tokens),
+        };
+        let mut detail = String::new();
+        if let Some(template) = &template {
+            detail.push_str(template);
+            detail.push(' ');
+        }
+        for specifier in &specifiers {
+            detail.push_str(specifier);
+            detail.push(' ');
+        }
+        if !return_type.is_empty() {
+            detail.push_str(&return_type);
+            detail.push(' ');
+        }
+        detail.push_str(&name);
+        detail.push_str(&parameters);
+        for qualifier in &trailing {
+            detail.push(' ');
+            detail.push_str(qualifier);
+        }
+
+        let kind = if is_method { SymbolKind::Method } else { SymbolKind::Function };
+        self.push(&name, kind, detail, &container_scope, &name_token);
+
+        // Constructor initializer list: : member(value), other{value}
+        if self.peek_is(":") {
+            self.position += 1;
+            while let Some(token) = self.peek() {
+                if token.is("(") || token.is("{") {
+                    let is_body = token.is("{")
+                        && self.position > 0
+                        && (self.tokens[self.position - 1].is(")") || self.tokens[self.position - 1].is("}"));
+                    if is_body {
+                        break;
+                    }
+                    self.skip_group();
+                } else {
+                    self.position += 1;
+                }
+            }
+        }
+
+        if self.peek_is("{") {
+            self.skip_group();
+        } else if self.peek_is(";") {
+            self.position += 1;
+        }
+    }
+}
+
+// Parses C++ declarations into the same symbol model as the Rust analysis
+fn collect_cpp_symbols(code: &str) -> Vec<Symbol> {
+    let tokens = tokenize_cpp(code);
+    let mut parser = CppParser {
+        tokens: &tokens,
+        position: 0,
+        symbols: Vec::new(),
+    };
+
+    // A stray closing brace ends parse_scope early; keep going with the rest of the file
+    while parser.position < tokens.len() {
+        parser.parse_scope(&[], false);
+    }
+    link_parents(&mut parser.symbols);
+    parser.symbols
+}
+
+fn analyze_cpp_code(code: &str) {
+    for symbol in collect_cpp_symbols(code) {
+        let kind = match symbol.kind {
+            SymbolKind::Module => "namespace",
+            SymbolKind::Struct => "class",
+            SymbolKind::Field => "data member",
+            SymbolKind::Method => "member function",
+            SymbolKind::Enum => "enum",
+            SymbolKind::Variant => "enumerator",
+            _ => "function",
+        };
+        println!(
+            "Found C++ {} {} at line {}: {}",
+            kind, symbol.path, symbol.range.start_line, symbol.detail
+        );
+    }
+}
+
+// Where a completion candidate is defined, from closest to furthest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Locality {
+    Local,
+    Crate,
+    External,
+}
+
+// Individual contributions to a completion's score, kept for debugging the ordering
+#[derive(Debug, Clone, Copy, Default)]
+struct ScoreBreakdown {
+    fuzzy: i32,
+    locality: i32,
+    expected_type: i32,
+    recency: i32,
+}
+
+impl ScoreBreakdown {
+    fn total(&self) -> i32 {
+        self.fuzzy + self.locality + self.expected_type + self.recency
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RankedCompletion {
+    completion: Completion,
+    locality: Locality,
+    breakdown: ScoreBreakdown,
+}
+
+impl RankedCompletion {
+    fn score(&self) -> i32 {
+        self.breakdown.total()
+    }
+}
+
+// What is known about the code around the cursor
+struct RankingContext {
+    prefix: String,
+    local_names: HashSet<String>,
+    crate_names: HashSet<String>,
+    expected_type: Option<String>,
+    // Identifiers used before the cursor, the most recently used last
+    recent_uses: Vec<String>,
+}
+
+impl RankingContext {
+    // `line` and `column` are 1-based
+    fn from_code(code: &str, line: usize, column: usize) -> Self {
+        let before_cursor = text_before_position(code, line, column);
+        let current_line = before_cursor.rsplit('\n').next().unwrap_or_default();
+
+        let prefix: String = current_line
+            .chars()
+            .rev()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect::<Vec<char>>()
+            .into_iter()
+            .rev()
+            .collect();
+
+        let local_pattern = cached_regex(
+            r"\blet\s+(?:mut\s+)?([A-Za-z_]\w*)|[(,]\s*(?:mut\s+)?([A-Za-z_]\w*)\s*:[^:]|\b(?:auto|int|long|double|float|bool|char|size_t|std::\w+(?:<[^;=(){}]*>)?)\s+&?([A-Za-z_]\w*)\s*[=;({]",
+        );
+        let local_names = local_pattern
+            .captures_iter(&before_cursor)
+            .filter_map(|capture| capture.iter().skip(1).flatten().next())
+            .map(|name| name.as_str().to_owned())
+            .collect();
+
+        let definition_pattern = cached_regex(r"\b(?:fn|struct|enum|trait|mod|const|static|type|class|namespace)\s+([A-Za-z_]\w*)");
+        let crate_names = definition_pattern
+            .captures_iter(code)
+            .map(|capture| capture[1].to_owned())
+            .collect();
+
+        // `let x: Type = pre` or `field: Type = pre` on the current line
+        let expected_type_pattern = cached_regex(r":\s*([^:=;{}]+?)\s*=\s*[\w.]*$");
+        let expected_type = expected_type_pattern
+            .captures(current_line)
+            .map(|capture| normalize_type(&capture[1]));
+
+        let identifier_pattern = cached_regex(r"[A-Za-z_]\w*");
+        let prefix_start = before_cursor.len() - prefix.len();
+        let recent_uses = identifier_pattern
+            .find_iter(&before_cursor[..prefix_start])
+            .map(|identifier| identifier.as_str().to_owned())
+            .collect();
+
+        RankingContext {
+            prefix,
+            local_names,
+            crate_names,
+            expected_type,
+            recent_uses,
+        }
+    }
+
+    fn locality(&self, completion: &Completion) -> Locality {
+        if self.local_names.contains(&completion.label) {
+            Locality::Local
+        } else if self.crate_names.contains(&completion.label) {
+            Locality::Crate
+        } else {
+            Locality::External
+        }
+    }
+}
+
+fn text_before_position(code: &str, line: usize, column: usize) -> String {
+    // `split` keeps the empty line after a trailing newline, which `lines` drops
+    let mut lines: Vec<&str> = code
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .take(line)
+        .collect();
+    let last: String = lines
+        .pop()
+        .unwrap_or_default()
+        .chars()
+        .take(column.saturating_sub(1))
+        .collect();
+    lines
+        .iter()
+        .map(|line| line.to_string())
+        .chain(std::iter::once(last))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn normalize_type(ty: &str) -> String {
+    ty.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+// The type a candidate produces, read from details such as `x: i32`, `fn f() -> i32` or a bare `i32`
+fn completion_type(completion: &Completion) -> Option<String> {
+    let detail = completion.detail.as_deref()?;
+    if let Some((_, return_type)) = detail.rsplit_once("->") {
+        return Some(normalize_type(return_type));
+    }
+    if detail.starts_with("fn ") {
+        return Some("()".to_owned());
+    }
+    match detail.split_once(':') {
+        Some((_, ty)) if !ty.starts_with(':') => Some(normalize_type(ty)),
+        _ => Some(normalize_type(detail)),
+    }
+}
+
+// Scores how well `pattern` matches `candidate` as a case-insensitive subsequence; None when it does not.
+// Consecutive characters, word starts and an exact prefix are rewarded, skipped characters cost a point.
+fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i32> {
+    if pattern.is_empty() {
+        return Some(0);
+    }
+
+    let candidate_chars: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut previous_match: Option<usize> = None;
+    let mut position = 0;
+
+    for pattern_char in pattern.chars() {
+        let pattern_char = pattern_char.to_ascii_lowercase();
+        let found = (position..candidate_chars.len())
+            .find(|&index| candidate_chars[index].to_ascii_lowercase() == pattern_char)?;
+
+        score += 10;
+        if found == 0 {
+            score += 15;
+        } else {
+            let before = candidate_chars[found - 1];
+            let word_start = before == '_' || (before.is_lowercase() && candidate_chars[found].is_uppercase());
+            if word_start {
+                score += 6;
+            }
+        }
+        match previous_match {
+            Some(previous) if found == previous + 1 => score += 8,
+            Some(previous) => score -= (found - previous - 1) as i32,
+            None => score -= found as i32,
+        }
+
+        previous_match = Some(found);
+        position = found + 1;
+    }
+
+    if candidate.starts_with(pattern) {
+        score += 20;
+    }
+    // Prefer shorter candidates among otherwise equal matches
+    score -= (candidate_chars.len() as i32 - pattern.chars().count() as i32).min(10);
+    Some(score)
+}
+
+fn rank_completions(completions: Vec<Completion>, context: &RankingContext) -> Vec<RankedCompletion> {
+    let mut ranked: Vec<RankedCompletion> = completions
+        .into_iter()
+        .filter_map(|completion| {
+            let fuzzy = fuzzy_score(&context.prefix, &completion.label)?;
+            let locality = context.locality(&completion);
+
+            let locality_score = match locality {
+                Locality::Local => 30,
+                Locality::Crate => 15,
+                Locality::External => 0,
+            };
+
+            let expected_type_score = match (&context.expected_type, completion_type(&completion)) {
+                (Some(expected), Some(actual)) if *expected == actual => 25,
+                _ => 0,
+            };
+
+            // Up to 20 points, fading over the last 20 identifiers used before the cursor
+            let recency_score = context
+                .recent_uses
+                .iter()
+                .rev()
+                .position(|name| *name == completion.label)
+                .map_or(0, |distance| 20 - distance.min(20) as i32);
+
+            Some(RankedCompletion {
+                completion,
+                locality,
+                breakdown: ScoreBreakdown {
+                    fuzzy,
+                    locality: locality_score,
+                    expected_type: expected_type_score,
+                    recency: recency_score,
+                },
+            })
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| {
+        b.score()
+            .cmp(&a.score())
+            .then_with(|| a.completion.label.cmp(&b.completion.label))
+    });
+    ranked
+}
+
+fn display_completions(language: &dyn Language, completions: Vec<String>) {
+    println!("{} Completions:", language.name());
+
+    for (index, completion) in completions.iter().enumerate() {
+        println!("{}. {}", index + 1, completion);
+    }
+}
+
+fn format_score(ranked: &RankedCompletion) -> String {
+    format!(
+        "score {} = fuzzy {} + {:?} {} + expected type {} + recency {}",
+        ranked.score(),
+        ranked.breakdown.fuzzy,
+        ranked.locality,
+        ranked.breakdown.locality,
+        ranked.breakdown.expected_type,
+        ranked.breakdown.recency
+    )
+}
+
+fn show_additional_info(language: &dyn Language, completion: &Completion, file_path: &str, line: usize, column: usize) {
+    println!("Additional Info for {}:", completion.label);
+    if let Some(kind) = &completion.kind {
+        println!("Kind: {}", kind);
+    }
+    if let Some(detail) = &completion.detail {
+        println!("Detail: {}", detail);
+    }
+
+    match &completion.documentation {
+        Some(documentation) => println!("{}", documentation),
+        // Fall back to the hover information of the symbol under the cursor
+        None => match get_hover(language, &LanguageServerConfig::for_language(language), file_path, line, column) {
+            Ok(Some(hover)) => println!("{}", hover),
+            Ok(None) => println!("No documentation available."),
+            Err(err) => eprintln!("Error: {}", err),
+        },
+    }
+}
+
+// 1-based line and column just past the last character of the code
+fn end_of_code_position(code: &str) -> (usize, usize) {
+    let line = code.lines().count().max(1);
+    let column = code.lines().last().map_or(0, |last_line| last_line.chars().count()) + 1;
+    (line, column)
+}
+
+// Syntax definition for the language, plain text when syntect has none
+fn find_syntax<'a>(syntax_set: &'a SyntaxSet, language: &dyn Language) -> &'a SyntaxReference {
+    language
+        .extensions()
+        .iter()
+        .find_map(|extension| syntax_set.find_syntax_by_extension(extension))
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text())
+}
+
+// Formats the code with the `rustfmt` binary, leaving it as it is when that fails
+fn format_code(code: &str) -> String {
+    match run_rustfmt(code) {
+        Ok(formatted) => formatted,
+        Err(err) => {
+            eprintln!("Error formatting code: {}", err);
+            code.to_owned()
+        }
+    }
+}
+
+fn run_rustfmt(code: &str) -> Result<String, String> {
+    let mut child = Command::new("rustfmt")
+        .args(["--emit", "stdout", "--quiet"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Failed to start rustfmt: {}", err))?;
+    // Written from a thread so that a large output cannot block rustfmt while it is still reading
+    let mut stdin = child.stdin.take().ok_or("rustfmt has no stdin")?;
+    let input = code.to_owned();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output().map_err(|err| format!("rustfmt failed: {}", err))?;
+    let _ = writer.join();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(stderr.lines().next().unwrap_or("rustfmt failed").to_owned());
+    }
+    String::from_utf8(output.stdout).map_err(|err| format!("rustfmt printed invalid UTF-8: {}", err))
+}
+
+#[derive(Debug, Clone)]
+struct UserPreferences {
+    perform_analysis: bool,
+    syntax_highlighting: bool,
+    perform_formatting: bool,
+    // `default`, `ocean`, a built-in syntect theme or a `.tmTheme` path
+    theme: String,
+    // Language id used when neither the file name nor the content gives the language away
+    default_language: Option<String>,
+    max_completions: usize,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        UserPreferences {
+            perform_analysis: true,
+            syntax_highlighting: true,
+            perform_formatting: false,
+            theme: "default".to_owned(),
+            default_language: None,
+            max_completions: 20,
+        }
+    }
+}
+
+const PREFERENCE_KEYS: &[&str] = &["analysis", "highlighting", "formatting", "theme", "default_language", "max_completions"];
+
+// Name of the file that overrides preferences for a project and its subdirectories
+const PROJECT_CONFIG_FILE: &str = ".intelligent-code-completion.toml";
+
+// One layer of settings: a config file, the first-run answers or `--set` flags.
+// Every key is optional so a layer only changes what it lists.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct PreferenceOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    analysis: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    highlighting: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    formatting: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    theme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default_language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completions: Option<usize>,
+}
+
+impl PreferenceOverrides {
+    // None when the file does not exist
+    fn load(path: &Path) -> Result<Option<Self>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let overrides: PreferenceOverrides =
+            toml::from_str(&content).map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+        overrides.validate().map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Some(overrides))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let content = toml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(id) = &self.default_language {
+            language_for_id(id)?;
+        }
+        if self.max_completions == Some(0) {
+            return Err("max_completions must be at least 1".to_owned());
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let parse_bool = |value: &str| match value {
+            "true" | "yes" | "on" => Ok(true),
+            "false" | "no" | "off" => Ok(false),
+            _ => Err(format!("{} expects true or false, got '{}'", key, value)),
+        };
+        match key {
+            "analysis" => self.analysis = Some(parse_bool(value)?),
+            "highlighting" => self.highlighting = Some(parse_bool(value)?),
+            "formatting" => self.formatting = Some(parse_bool(value)?),
+            "theme" => self.theme = Some(value.to_owned()),
+            "default_language" => self.default_language = Some(value.to_owned()),
+            "max_completions" => {
+                let limit = value
+                    .parse()
+                    .map_err(|_| format!("max_completions expects a number, got '{}'", value))?;
+                self.max_completions = Some(limit);
+            }
+            _ => return Err(unknown_preference(key)),
+        }
+        self.validate()
+    }
+
+    fn unset(&mut self, key: &str) -> Result<(), String> {
+        match key {
+            "analysis" => self.analysis = None,
+            "highlighting" => self.highlighting = None,
+            "formatting" => self.formatting = None,
+            "theme" => self.theme = None,
+            "default_language" => self.default_language = None,
+            "max_completions" => self.max_completions = None,
+            _ => return Err(unknown_preference(key)),
+        }
+        Ok(())
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        match key {
+            "analysis" => self.analysis.is_some(),
+            "highlighting" => self.highlighting.is_some(),
+            "formatting" => self.formatting.is_some(),
+            "theme" => self.theme.is_some(),
+            "default_language" => self.default_language.is_some(),
+            "max_completions" => self.max_completions.is_some(),
+            _ => false,
+        }
+    }
+
+    fn apply(&self, preferences: &mut UserPreferences) {
+        if let Some(analysis) = self.analysis {
+            preferences.perform_analysis = analysis;
+        }
+        if let Some(highlighting) = self.highlighting {
+            preferences.syntax_highlighting = highlighting;
+        }
+        if let Some(formatting) = self.formatting {
+            preferences.perform_formatting = formatting;
+        }
+        if let Some(theme) = &self.theme {
+            preferences.theme = theme.clone();
+        }
+        if let Some(default_language) = &self.default_language {
+            preferences.default_language = Some(default_language.clone());
+        }
+        if let Some(max_completions) = self.max_completions {
+            preferences.max_completions = max_completions;
+        }
+    }
+}
+
+impl UserPreferences {
+    // The value of a key as it would be written in the config file
+    fn get(&self, key: &str) -> Result<String, String> {
+        Ok(match key {
+            "analysis" => self.perform_analysis.to_string(),
+            "highlighting" => self.syntax_highlighting.to_string(),
+            "formatting" => self.perform_formatting.to_string(),
+            "theme" => format!("{:?}", self.theme),
+            "default_language" => match &self.default_language {
+                Some(id) => format!("{:?}", id),
+                None => "(detect)".to_owned(),
+            },
+            "max_completions" => self.max_completions.to_string(),
+            _ => return Err(unknown_preference(key)),
+        })
+    }
+
+    // Language used when detection has nothing to go on
+    fn fallback_language(&self) -> &'static dyn Language {
+        self.default_language
+            .as_deref()
+            .and_then(|id| language_for_id(id).ok())
+            .unwrap_or(&RustLanguage)
+    }
+}
+
+fn unknown_preference(key: &str) -> String {
+    format!("Unknown setting '{}', expected one of {}", key, PREFERENCE_KEYS.join(", "))
+}
+
+fn language_for_id(id: &str) -> Result<&'static dyn Language, String> {
+    LANGUAGES.iter().copied().find(|language| language.id() == id).ok_or_else(|| {
+        let ids: Vec<&str> = LANGUAGES.iter().map(|language| language.id()).collect();
+        format!("Unknown language '{}', expected one of {}", id, ids.join(", "))
+    })
+}
+
+// $XDG_CONFIG_HOME/intelligent-code-completion, falling back to ~/.config
+fn config_dir() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("intelligent-code-completion"))
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+// The nearest project config file in the current directory or one of its parents
+fn project_config_path() -> Option<PathBuf> {
+    let current = env::current_dir().ok()?;
+    current
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+// Settings layers from lowest to highest priority, each with a description of where it came from
+fn preference_layers(flags: &PreferenceOverrides) -> Result<Vec<(String, PreferenceOverrides)>, String> {
+    let mut layers = Vec::new();
+    for path in user_config_path().into_iter().chain(project_config_path()) {
+        if let Some(overrides) = PreferenceOverrides::load(&path)? {
+            layers.push((path.display().to_string(), overrides));
+        }
+    }
+    layers.push(("command line".to_owned(), flags.clone()));
+    Ok(layers)
+}
+
+fn load_preferences(flags: &PreferenceOverrides) -> Result<UserPreferences, String> {
+    let mut preferences = UserPreferences::default();
+    for (_, overrides) in preference_layers(flags)? {
+        overrides.apply(&mut preferences);
+    }
+    Ok(preferences)
+}
+
+// Removes `--set key=value` flags from the arguments and collects them
+fn take_preference_flags(args: &mut Vec<String>) -> Result<PreferenceOverrides, String> {
+    let mut flags = PreferenceOverrides::default();
+    while let Some(position) = args.iter().position(|arg| arg == "--set") {
+        let assignment = args.get(position + 1).ok_or("Missing key=value after --set")?.clone();
+        let (key, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("--set expects key=value, got '{}'", assignment))?;
+        flags.set(key.trim(), value.trim())?;
+        args.drain(position..position + 2);
+    }
+    Ok(flags)
+}
+
+// First run: ask once and remember the answers in the user config file
+fn get_user_preferences() -> PreferenceOverrides {
+    // Allow the user to interactively choose preferences
+    println!("Do you want to perform code analysis? (y/n):");
+    let perform_analysis = read_yes_no_input();
+
+    println!("Do you want to enable syntax highlighting? (y/n):");
+    let syntax_highlighting = read_yes_no_input();
+
+    let theme = if syntax_highlighting {
+        match choose_syntax_theme() {
+            SyntaxTheme::Default => "default".to_owned(),
+            SyntaxTheme::Ocean => "ocean".to_owned(),
+            SyntaxTheme::Custom(name) => name,
+        }
+    } else {
+        "default".to_owned()
+    };
+
+    println!("Do you want to perform code formatting? (y/n):");
+    let perform_formatting = read_yes_no_input();
+
+    PreferenceOverrides {
+        analysis: Some(perform_analysis),
+        highlighting: Some(syntax_highlighting),
+        formatting: Some(perform_formatting),
+        theme: Some(theme),
+        ..PreferenceOverrides::default()
+    }
+}
+
+fn read_yes_no_input() -> bool {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("Failed to read line");
+    input.trim().to_lowercase() == "y"        
+}
+
+// `config [show | path | get <key> | set <key> <value> | unset <key> | edit] [--project]`
+fn run_config_command(args: &[String], flags: &PreferenceOverrides) -> Result<(), String> {
+    let project = args.iter().any(|arg| arg == "--project");
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).filter(|arg| *arg != "--project").collect();
+
+    // `set`, `unset` and `edit` change the user file unless --project is given
+    let target = || -> Result<PathBuf, String> {
+        if project {
+            let current = env::current_dir().map_err(|e| e.to_string())?;
+            Ok(project_config_path().unwrap_or_else(|| current.join(PROJECT_CONFIG_FILE)))
+        } else {
+            user_config_path().ok_or_else(|| "Cannot find the config directory; set XDG_CONFIG_HOME or HOME".to_owned())
+        }
+    };
+
+    match args.as_slice() {
+        [] | ["show"] => {
+            let layers = preference_layers(flags)?;
+            let preferences = load_preferences(flags)?;
+            for key in PREFERENCE_KEYS {
+                let source = layers
+                    .iter()
+                    .rev()
+                    .find(|(_, overrides)| overrides.contains(key))
+                    .map_or("default", |(source, _)| source.as_str());
+                println!("{} = {}  # {}", key, preferences.get(key)?, source);
+            }
+        }
+        ["path"] => {
+            let user = user_config_path().map_or("(unknown)".to_owned(), |path| path.display().to_string());
+            println!("user: {}", user);
+            match project_config_path() {
+                Some(path) => println!("project: {}", path.display()),
+                None => println!("project: none (create {} with `config set --project`)", PROJECT_CONFIG_FILE),
+            }
+        }
+        ["get", key] => println!("{}", load_preferences(flags)?.get(key)?),
+        ["set", key, value] => {
+            let path = target()?;
+            let mut overrides = PreferenceOverrides::load(&path)?.unwrap_or_default();
+            overrides.set(key, value)?;
+            overrides.save(&path)?;
+            println!("Set {} in {}", key, path.display());
+        }
+        ["unset", key] => {
+            let path = target()?;
+            let mut overrides = PreferenceOverrides::load(&path)?.unwrap_or_default();
+            overrides.unset(key)?;
+            overrides.save(&path)?;
+            println!("Removed {} from {}", key, path.display());
+        }
+        ["edit"] => {
+            let path = target()?;
+            if !path.exists() {
+                PreferenceOverrides::default().save(&path)?;
+            }
+            let editor = env::var("VISUAL").or_else(|_| env::var("EDITOR")).unwrap_or_else(|_| "vi".to_owned());
+            let status = Command::new(&editor)
+                .arg(&path)
+                .status()
+                .map_err(|e| format!("Failed to start editor '{}': {}", editor, e))?;
+            if !status.success() {
+                return Err(format!("Editor '{}' exited with {}", editor, status));
+            }
+            // Report mistakes now rather than on the next run
+            PreferenceOverrides::load(&path)?;
+        }
+        _ => return Err("Usage: config [show | path | get <key> | set <key> <value> | unset <key> | edit] [--project]".to_owned()),
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+enum SyntaxTheme {
+    Default,
+    Ocean,
+    // A built-in theme name or the path of a `.tmTheme` file
+    Custom(String),
+}
+
+impl SyntaxTheme {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "default" => SyntaxTheme::Default,
+            "ocean" => SyntaxTheme::Ocean,
+            other => SyntaxTheme::Custom(other.to_owned()),
+        }
+    }
+}
+
+// Where highlighted code goes
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HighlightFormat {
+    // 24-bit colour escape sequences for the terminal
+    Ansi,
+    // Standalone HTML page with a `style` attribute on every span
+    HtmlInline,
+    // Standalone HTML page with CSS classes and a stylesheet generated from the theme
+    HtmlClasses,
+}
+
+impl HighlightFormat {
+    fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "ansi" => Ok(HighlightFormat::Ansi),
+            "html" | "html-inline" => Ok(HighlightFormat::HtmlInline),
+            "html-classes" => Ok(HighlightFormat::HtmlClasses),
+            other => Err(format!("Unknown output '{}', expected ansi, html or html-classes", other)),
+        }
+    }
+}
+
+fn choose_syntax_theme() -> SyntaxTheme {
+    println!("Choose a syntax highlighting theme:");
+    println!("1. Default");
+    println!("2. Ocean");
+    println!("3. Custom");
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("Failed to read line");
+
+    match input.trim().parse::<u32>() {
+        Ok(choice) => match choice {
+            1 => SyntaxTheme::Default,
+            2 => SyntaxTheme::Ocean,
+            3 => {
+                println!("Enter a built-in theme name or the path of a .tmTheme file:");
+                let mut theme_name = String::new();
+                io::stdin().read_line(&mut theme_name).expect("Failed to read line");
+                SyntaxTheme::Custom(theme_name.trim().to_owned())
+            }
+            _ => SyntaxTheme::Default, // Default theme if an invalid choice is made
+        },
+        Err(_) => SyntaxTheme::Default, // Default theme if parsing fails
+    }
+}
+
+// Built-in themes by name; anything ending in `.tmTheme` is read from disk
+fn load_theme(theme: &SyntaxTheme) -> Result<Theme, String> {
+    let name = match theme {
+        SyntaxTheme::Default => "base16-ocean.dark",
+        SyntaxTheme::Ocean => "base16-ocean.dark",
+        SyntaxTheme::Custom(name) => name,
+    };
+
+    if name.ends_with(".tmTheme") || Path::new(name).is_file() {
+        return ThemeSet::get_theme(name).map_err(|e| format!("Failed to load theme {}: {}", name, e));
+    }
+
+    let mut theme_set = ThemeSet::load_defaults();
+    theme_set.themes.remove(name).ok_or_else(|| {
+        let available: Vec<&String> = theme_set.themes.keys().collect();
+        format!("Unknown theme '{}'; built-in themes are {:?}", name, available)
+    })
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// A complete HTML document around highlighted `<pre>` markup
+fn html_page(title: &str, style: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        html_escape(title),
+        style,
+        body
+    )
+}
+
+// Renders the code highlighted for the language with the theme, in the requested format
+fn render_highlighted(
+    code: &str,
+    language: &dyn Language,
+    theme: &SyntaxTheme,
+    format: HighlightFormat,
+    title: &str,
+) -> Result<String, String> {
+    let syntax_set = SyntaxSet::load_defaults_newlines();
+    let syntax = find_syntax(&syntax_set, language);
+    let theme = load_theme(theme)?;
+
+    match format {
+        HighlightFormat::Ansi => {
+            let mut highlighter = HighlightLines::new(syntax, &theme);
+            let mut output = String::new();
+            for line in LinesWithEndings::from(code) {
+                let ranges = highlighter.highlight_line(line, &syntax_set).map_err(|e| e.to_string())?;
+                output.push_str(&as_24_bit_terminal_escaped(&ranges[..], false));
+            }
+            // Leave the terminal in its normal colours
+            output.push_str("\x1b[0m");
+            Ok(output)
+        }
+        HighlightFormat::HtmlInline => {
+            let body = highlighted_html_for_string(code, &syntax_set, syntax, &theme).map_err(|e| e.to_string())?;
+            Ok(html_page(title, "pre { padding: 1em; }\n", &body))
+        }
+        HighlightFormat::HtmlClasses => {
+            let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, &syntax_set, ClassStyle::Spaced);
+            for line in LinesWithEndings::from(code) {
+                generator
+                    .parse_html_for_line_which_includes_newline(line)
+                    .map_err(|e| e.to_string())?;
+            }
+            // The stylesheet colours `.code` with the theme's foreground and background
+            let css = css_for_theme_with_class_style(&theme, ClassStyle::Spaced).map_err(|e| e.to_string())?;
+            let body = format!("<pre class=\"code\">{}</pre>\n", generator.finalize());
+            Ok(html_page(title, &format!("{}pre {{ padding: 1em; }}\n", css), &body))
+        }
+    }
+}
+
+fn perform_syntax_highlighting(code: &str, theme: SyntaxTheme, language: &dyn Language) {
+    match render_highlighted(code, language, &theme, HighlightFormat::Ansi, "") {
+        Ok(highlighted) => println!("{}", highlighted),
+        Err(err) => {
+            eprintln!("{}. Performing default syntax highlighting.", err);
+            if let Ok(highlighted) = render_highlighted(code, language, &SyntaxTheme::Default, HighlightFormat::Ansi, "") {
+                println!("{}", highlighted);
+            }
+        }
+    }
+}
+
+// `highlight <file> [--output ansi|html|html-classes] [--theme name|file.tmTheme] [--out path]`
+fn run_highlight_command(args: &[String], preferences: &UserPreferences) -> Result<(), String> {
+    let mut file = None;
+    let mut format = HighlightFormat::Ansi;
+    let mut theme = SyntaxTheme::from_name(&preferences.theme);
+    let mut destination = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--output" => format = HighlightFormat::from_name(value()?)?,
+            "--theme" => theme = SyntaxTheme::from_name(value()?),
+            "--out" => destination = Some(value()?.clone()),
+            other if other.starts_with("--") => return Err(format!("Unknown argument '{}'", other)),
+            other => file = Some(other.to_owned()),
+        }
+    }
+
+    let file = file.ok_or("Missing file to highlight")?;
+    let code = fs::read_to_string(&file).map_err(|e| format!("Failed to read {}: {}", file, e))?;
+    let language = detect_language_or(&file, &code, preferences.fallback_language());
+    let highlighted = render_highlighted(&code, language, &theme, format, &file)?;
+
+    match destination {
+        Some(destination) => fs::write(&destination, highlighted).map_err(|e| format!("Failed to write {}: {}", destination, e)),
+        None => {
+            print!("{}", highlighted);
+            io::stdout().flush().map_err(|e| e.to_string())
+        }
+    }
+}
+
+// LSP SymbolKind and CompletionItemKind numbers for our symbol kinds
+fn lsp_symbol_kind(kind: SymbolKind) -> u64 {
+    match kind {
+        SymbolKind::Function => 12,
+        SymbolKind::Method => 6,
+        SymbolKind::Struct => 23,
+        SymbolKind::Field => 8,
+        SymbolKind::Enum => 10,
+        SymbolKind::Variant => 22,
+        SymbolKind::Trait => 11,
+        SymbolKind::Impl => 5,
+        SymbolKind::Module => 2,
+    }
+}
+
+fn lsp_completion_kind(kind: SymbolKind) -> u64 {
+    match kind {
+        SymbolKind::Function => 3,
+        SymbolKind::Method => 2,
+        SymbolKind::Struct => 22,
+        SymbolKind::Field => 5,
+        SymbolKind::Enum => 13,
+        SymbolKind::Variant => 20,
+        SymbolKind::Trait => 8,
+        SymbolKind::Impl => 7,
+        SymbolKind::Module => 9,
+    }
+}
+
+fn lsp_range(text: &str, range: &SourceRange) -> Value {
+    json!({
+        "start": lsp_position(text, range.start_line, range.start_column),
+        "end": lsp_position(text, range.end_line, range.end_column)
+    })
+}
+
+// Returns the identifier prefix ending at the 0-based position and whether it follows a `.`
+fn prefix_at(text: &str, line: usize, character: usize) -> (String, bool) {
+    let line_text: Vec<char> = text.lines().nth(line).unwrap_or_default().chars().collect();
+    let end = character.min(line_text.len());
+    let mut start = end;
+    while start > 0 && (line_text[start - 1].is_alphanumeric() || line_text[start - 1] == '_') {
+        start -= 1;
+    }
+    let after_dot = start > 0 && line_text[start - 1] == '.';
+    (line_text[start..end].iter().collect(), after_dot)
+}
+
+// Returns the whole identifier under the 0-based position
+fn word_at(text: &str, line: usize, character: usize) -> Option<String> {
+    let line_text: Vec<char> = text.lines().nth(line)?.chars().collect();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut start = character.min(line_text.len());
+    let mut end = start;
+    while start > 0 && is_word(line_text[start - 1]) {
+        start -= 1;
+    }
+    while end < line_text.len() && is_word(line_text[end]) {
+        end += 1;
+    }
+    if start == end {
+        None
+    } else {
+        Some(line_text[start..end].iter().collect())
+    }
+}
+
+// Markdown for the symbol `name`, fenced as code of the document's language
+fn hover_text(symbols: &[Symbol], name: &str, language_id: &str) -> Option<String> {
+    let symbol = symbols
+        .iter()
+        .find(|symbol| symbol.name == name && symbol.kind != SymbolKind::Impl)?;
+    let text = match symbol.kind {
+        SymbolKind::Struct => {
+            let fields: Vec<String> = symbols
+                .iter()
+                .filter(|field| field.kind == SymbolKind::Field && field.container.as_deref() == Some(name))
+                .map(|field| format!("    {},", field.detail))
+                .collect();
+            if fields.is_empty() {
+                format!("{};", symbol.detail)
+            } else {
+                format!("{} {{\n{}\n}}", symbol.detail, fields.join("\n"))
+            }
+        }
+        SymbolKind::Field | SymbolKind::Method | SymbolKind::Variant => {
+            format!("{}\n{}", symbol.container.as_deref().unwrap_or_default(), symbol.detail)
+        }
+        _ => format!("{}\n{}", symbol.path, symbol.detail),
+    };
+    Some(format!("```{}\n{}\n```", language_id, text))
+}
+
+fn completion_items(symbols: &[Symbol], snippets: &[Snippet], text: &str, line: usize, character: usize) -> Value {
+    let (prefix, after_dot) = prefix_at(text, line, character);
+    let mut items: Vec<Value> = symbols
+        .iter()
+        // After a `.` only fields and methods make sense; elsewhere offer functions, types and modules
+        .filter(|symbol| match symbol.kind {
+            SymbolKind::Field | SymbolKind::Method => after_dot,
+            SymbolKind::Impl => false,
+            _ => !after_dot,
+        })
+        .filter(|symbol| symbol.name.starts_with(&prefix))
+        .map(|symbol| {
+            json!({
+                "label": symbol.name,
+                "kind": lsp_completion_kind(symbol.kind),
+                "detail": symbol.detail,
+            })
+        })
+        .collect();
+
+    if !after_dot {
+        items.extend(snippets.iter().filter(|snippet| snippet.trigger.starts_with(&prefix)).map(|snippet| {
+            json!({
+                "label": snippet.trigger,
+                "kind": 15,
+                "detail": snippet.description,
+                "documentation": snippet.preview(),
+                "insertText": snippet.body,
+                "insertTextFormat": 2,
+            })
+        }));
+    }
+    json!({ "isIncomplete": false, "items": items })
+}
+
+fn document_symbols(text: &str, symbols: &[Symbol]) -> Value {
+    let symbol_json = |symbol: &Symbol, children: Vec<Value>| {
+        json!({
+            "name": symbol.name,
+            "detail": symbol.detail,
+            "kind": lsp_symbol_kind(symbol.kind),
+            "range": lsp_range(text, &symbol.range),
+            "selectionRange": lsp_range(text, &symbol.range),
+            "children": children,
+        })
+    };
+
+    // Each symbol under its parent, at any depth
+    fn children_of(parent: Option<usize>, symbols: &[Symbol], symbol_json: &dyn Fn(&Symbol, Vec<Value>) -> Value) -> Vec<Value> {
+        symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| symbol.parent == parent)
+            .map(|(index, symbol)| symbol_json(symbol, children_of(Some(index), symbols, symbol_json)))
+            .collect()
+    }
+    Value::Array(children_of(None, symbols, &symbol_json))
+}
+
+// JSON-RPC error code for requests the server does not implement
+const METHOD_NOT_FOUND: i64 = -32601;
+
+// Serves completions, document symbols and hover for Rust files over stdio
+fn run_language_server() -> Result<(), String> {
+    let stdin = io::stdin();
+    let mut reader = stdin.lock();
+    let stdout = io::stdout();
+    let mut writer = stdout.lock();
+    let mut documents: HashMap<String, String> = HashMap::new();
+    let mut index: Option<SymbolIndex> = None;
+
+    while let Some(message) = read_lsp_message(&mut reader)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_owned();
+        let file_path = uri_to_path(&uri);
+        let line = params["position"]["line"].as_u64().unwrap_or(0) as usize;
+        let utf16_character = params["position"]["character"].as_u64().unwrap_or(0) as usize;
+        let character = documents
+            .get(&uri)
+            .map_or(utf16_character, |text| character_column(text, line, utf16_character));
+
+        // Symbols of the requested document; code being typed rarely parses, so requests at a
+        // position retry without the line under the cursor; None if the document still does not parse
+        let document_symbols_of = || -> Option<Vec<Symbol>> {
+            documents
+                .get(&uri)
+                .and_then(|text| {
+                    let language = detect_language(&uri, text);
+                    language
+                        .extract_symbols(text)
+                        .or_else(|err| match params.get("position") {
+                            Some(_) => language.extract_symbols(&without_line(text, line + 1)),
+                            None => Err(err),
+                        })
+                        .ok()
+                })
+        };
+
+        // The document's own symbols followed by those of every other file in the project; a
+        // document that does not parse falls back to the index's last good symbols for its file
+        let project_symbols = || -> Vec<Symbol> {
+            let document = document_symbols_of();
+            let parsed = document.is_some();
+            let mut symbols = document.unwrap_or_default();
+            if let Some(index) = &index {
+                symbols.extend(
+                    index
+                        .symbols()
+                        .filter(|(path, _)| !parsed || file_path.as_ref() != Some(*path))
+                        .map(|(_, symbol)| symbol.clone()),
+                );
+            }
+            symbols
+        };
+
+        let result = match method {
+            "initialize" => {
+                let root = params["rootUri"]
+                    .as_str()
+                    .and_then(uri_to_path)
+                    .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+                index = root.map(|root| SymbolIndex::build(&root));
+
+                json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "completionProvider": { "triggerCharacters": ["."] },
+                        "signatureHelpProvider": { "triggerCharacters": ["(", ","] },
+                        "documentSymbolProvider": true,
+                        "hoverProvider": true
+                    },
+                    "serverInfo": { "name": "intelligent-code-completion" }
+                })
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                if let (Some(index), Some(path)) = (&mut index, &file_path) {
+                    index.update_file_text(path, text, None);
+                }
+                publish_diagnostics(&mut writer, &uri, text)?;
+                documents.insert(uri, text.to_owned());
+                continue;
+            }
+            "textDocument/didChange" => {
+                // Full document sync: the last change holds the whole text
+                if let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    // Unsaved text has no modification time, so the next refresh re-reads the file
+                    if let (Some(index), Some(path)) = (&mut index, &file_path) {
+                        index.update_file_text(path, text, None);
+                    }
+                    publish_diagnostics(&mut writer, &uri, text)?;
+                    documents.insert(uri, text.to_owned());
+                }
+                continue;
+            }
+            "textDocument/didSave" => {
+                if let (Some(index), Some(path)) = (&mut index, &file_path) {
+                    index.update_file(path);
+                }
+                continue;
+            }
+            "workspace/didChangeWatchedFiles" => {
+                if let Some(index) = &mut index {
+                    index.refresh();
+                }
+                continue;
+            }
+            "textDocument/didClose" => {
+                documents.remove(&uri);
+                continue;
+            }
+            "textDocument/completion" => {
+                let text = documents.get(&uri).cloned().unwrap_or_default();
+                let snippets = load_snippets(detect_language(&uri, &text));
+                completion_items(&project_symbols(), &snippets, &text, line, character)
+            }
+            "textDocument/documentSymbol" => {
+                let text = documents.get(&uri).map_or("", String::as_str);
+                document_symbols(text, &document_symbols_of().unwrap_or_default())
+            }
+            "textDocument/signatureHelp" => documents
+                .get(&uri)
+                .and_then(|text| signature_help(text, line + 1, character + 1))
+                .map_or(Value::Null, |(signature, argument)| lsp_signature_help(&signature, argument)),
+            "textDocument/hover" => {
+                let hover = documents
+                    .get(&uri)
+                    .and_then(|text| {
+                        let word = word_at(text, line, character)?;
+                        hover_text(&project_symbols(), &word, detect_language(&uri, text).id())
+                    });
+                match hover {
+                    Some(hover) => json!({ "contents": { "kind": "markdown", "value": hover } }),
+                    None => Value::Null,
+                }
+            }
+            "shutdown" => Value::Null,
+            "exit" => break,
+            // Other notifications, e.g. `initialized` or `$/cancelRequest`, need no answer
+            _ if message.get("id").is_none() => continue,
+            _ => {
+                let error = json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": { "code": METHOD_NOT_FOUND, "message": format!("Unsupported method {}", method) }
+                });
+                write_lsp_message(&mut writer, &error).map_err(|e| format!("Failed to write response: {}", e))?;
+                continue;
+            }
+        };
+
+        // Notifications (no id) never get a response
+        if let Some(id) = message.get("id") {
+            write_lsp_message(&mut writer, &json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+                .map_err(|e| format!("Failed to write response: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Sends the diagnostics of a Rust document; other languages get none
+fn publish_diagnostics(writer: &mut impl Write, uri: &str, text: &str) -> Result<(), String> {
+    if detect_language(uri, text).id() != "rust" {
+        return Ok(());
+    }
+    let diagnostics: Vec<Value> = collect_rust_diagnostics(text).iter().map(|diagnostic| lsp_diagnostic(text, diagnostic)).collect();
+    let notification = json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics }
+    });
+    write_lsp_message(writer, &notification).map_err(|e| format!("Failed to write diagnostics: {}", e))
+}
+
+fn print_symbol_index(root: &Path) {
+    let index = SymbolIndex::build(root);
+    let mut symbols: Vec<(&PathBuf, &Symbol)> = index.symbols().collect();
+    symbols.sort_by(|a, b| a.1.path.cmp(&b.1.path));
+
+    for (path, symbol) in symbols {
+        println!(
+            "{:?} {} ({}:{}:{}) {}",
+            symbol.kind,
+            symbol.path,
+            path.display(),
+            symbol.range.start_line,
+            symbol.range.start_column,
+            symbol.detail
+        );
+    }
+    for (path, error) in index.errors() {
+        eprintln!("Failed to parse {}: {}", path.display(), error);
+    }
 }
 
-fn write_code_to_file(language: Language, code: &str, file_path: &str) {
-    fs::write(file_path, code).expect("Failed to write to the file");    
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    Error,
+    Warning,
 }
 
-fn get_rust_completions(file_path: &str, line: usize, column: usize) -> racer::Result<Vec<racer::Match>> {
-    racer::complete_from_file(file_path, line, column)    
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
 }
 
-fn get_cpp_completions(file_path: &str, line: usize, column: usize) -> cppcomplete::Result<Vec<cppcomplete::Completion>> {
-    cppcomplete::get_completions(file_path, line, column)    
+// A problem found in a source file, rendered like rustc's output or as JSON
+#[derive(Debug, Clone)]
+struct Diagnostic {
+    severity: Severity,
+    code: &'static str,
+    message: String,
+    range: SourceRange,
+    // Text printed after the carets
+    label: Option<String>,
+    notes: Vec<String>,
 }
 
-fn analyze_code(language: Language, code: &str) {
-    match language {
-        Language::Rust => {
-            // Perform Rust-specific analysis if needed
-            // For example, extract variable types, function signatures, etc.
-            println!("Performing Rust analysis:");
-            analyze_rust_code(code);
+// Names that resolve without being declared in the file
+const RUST_PRELUDE_NAMES: &[&str] = &["drop", "std", "core", "alloc", "crate", "super", "self", "Self"];
+
+// Parse errors, or when the file parses, lightweight warnings about its own items and bindings
+fn collect_rust_diagnostics(code: &str) -> Vec<Diagnostic> {
+    let file = match syn::parse_file(code) {
+        Ok(file) => file,
+        Err(err) => {
+            return err
+                .into_iter()
+                .map(|error| Diagnostic {
+                    severity: Severity::Error,
+                    code: "parse-error",
+                    message: error.to_string(),
+                    range: SourceRange::from_span(error.span()),
+                    label: None,
+                    notes: Vec::new(),
+                })
+                .collect();
         }
-        Language::Cpp => {
-            // Perform C++-specific analysis if needed
-            // For example, extract variable types, function signatures, etc.
-            println!("Performing C++ analysis:");
-            analyze_cpp_code(code);
+    };
+
+    let mut items = ItemCollector::default();
+    items.visit_file(&file);
+
+    let mut checker = BindingChecker {
+        items: &items,
+        scopes: Vec::new(),
+        used: HashSet::new(),
+        diagnostics: Vec::new(),
+    };
+    checker.visit_file(&file);
+
+    let mut diagnostics = checker.diagnostics;
+    for (name, range) in &items.private_functions {
+        if !checker.used.contains(name) {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                code: "unused-function",
+                message: format!("function `{}` is never used", name),
+                range: *range,
+                label: None,
+                notes: Vec::new(),
+            });
         }
     }
+
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.range.start_line, diagnostic.range.start_column));
+    diagnostics
 }
 
-fn analyze_rust_code(code: &str) {
-    if let Ok(syntax_tree) = syn::parse_file(code) {
-        for item in syntax_tree.items {
-            match item {
-                Item::Fn(ref func) => {
-                    println!("Found Rust function: {}", func.sig.ident);
-                    analyze_rust_function(func);    
-                }
-                Item::Struct(ref st) => {
-                    println!("Found Rust struct: {}", st.ident);
-                    analyze_rust_struct(st);    
+// Every name declared by an item anywhere in the file
+#[derive(Default)]
+struct ItemCollector {
+    names: HashSet<String>,
+    // Functions that are neither public nor entry points, with the range of their name
+    private_functions: Vec<(String, SourceRange)>,
+    // A `use path::*` can bring in names we cannot see
+    has_glob_import: bool,
+}
+
+impl<'ast> Visit<'ast> for ItemCollector {
+    fn visit_item(&mut self, item: &'ast Item) {
+        let ident = match item {
+            Item::Const(item) => Some(&item.ident),
+            Item::Static(item) => Some(&item.ident),
+            Item::Struct(item) => Some(&item.ident),
+            Item::Enum(item) => Some(&item.ident),
+            Item::Union(item) => Some(&item.ident),
+            Item::Mod(item) => Some(&item.ident),
+            Item::ExternCrate(item) => Some(item.rename.as_ref().map_or(&item.ident, |(_, rename)| rename)),
+            Item::Fn(item) => {
+                let entry_point = item.sig.ident == "main"
+                    || item.attrs.iter().any(|attr| {
+                        ["test", "bench", "no_mangle", "export_name"].iter().any(|name| attr.path.is_ident(name))
+                    });
+                if matches!(item.vis, syn::Visibility::Inherited) && !entry_point {
+                    self.private_functions
+                        .push((item.sig.ident.to_string(), SourceRange::from_span(item.sig.ident.span())));
                 }
-                _ => {}
-            }   
+                Some(&item.sig.ident)
+            }
+            _ => None,
+        };
+        if let Some(ident) = ident {
+            self.names.insert(ident.to_string());
         }
-    } else {
-        eprintln!("Failed to parse Rust code");
-    }   
+        visit::visit_item(self, item);
+    }
+
+    fn visit_use_tree(&mut self, tree: &'ast syn::UseTree) {
+        match tree {
+            syn::UseTree::Name(name) => {
+                self.names.insert(name.ident.to_string());
+            }
+            syn::UseTree::Rename(rename) => {
+                self.names.insert(rename.rename.to_string());
+            }
+            syn::UseTree::Glob(_) => self.has_glob_import = true,
+            _ => {}
+        }
+        visit::visit_use_tree(self, tree);
+    }
+
+    fn visit_foreign_item_fn(&mut self, item: &'ast syn::ForeignItemFn) {
+        self.names.insert(item.sig.ident.to_string());
+    }
 }
 
-fn analyze_rust_function(func: &ItemFn) {
-    println!("Found Rust function: {}", func.sig.ident);
+// Walks function bodies keeping a stack of scopes with the local bindings in each
+struct BindingChecker<'a> {
+    items: &'a ItemCollector,
+    // Bindings of the function being checked, innermost scope last
+    scopes: Vec<Vec<(String, SourceRange)>>,
+    // Every name referred to by a path, method call or macro argument
+    used: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
 
-    // Display function visibility
-    if let Some(visibility) = &func.vis {
-        match visibility {
-            Visibility::Public(_) => println!("Function is public."),
-            Visibility::Crate(_) => println!("Function has crate visibility."),
-            Visibility::Restricted(_) => println!("Function has restricted visibility."),
-            Visibility::Inherited => {} // Do nothing for inherited visibility    
-        }   
+impl<'a> BindingChecker<'a> {
+    fn lookup(&self, name: &str) -> Option<&SourceRange> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(binding, _)| binding == name)
+            .map(|(_, range)| range)
     }
 
-    // Display function parameters
-    for input in &func.sig.inputs {
-        if let Type::Path(TypePath { path, .. }) = &input.ty {
-            let param_name = &input.pat;
-            let param_type = &path.segments.last().unwrap().ident;
-            println!("Parameter {}: {:?}", quote! {#param_name}, param_type);    
+    // Adds the names bound by a pattern to the innermost scope
+    fn bind_pattern(&mut self, pattern: &syn::Pat) {
+        let mut idents = Vec::new();
+        pattern_bindings(pattern, &mut idents);
+
+        for ident in idents {
+            let name = ident.to_string();
+            let range = SourceRange::from_span(ident.span());
+            if !name.starts_with('_') {
+                if let Some(previous) = self.lookup(&name) {
+                    let note = format!(
+                        "previous binding of `{}` at line {}, column {}",
+                        name, previous.start_line, previous.start_column
+                    );
+                    self.diagnostics.push(Diagnostic {
+                        severity: Severity::Warning,
+                        code: "shadowed-name",
+                        message: format!("`{}` shadows an earlier binding", name),
+                        range,
+                        label: Some("shadows the earlier binding".to_owned()),
+                        notes: vec![note],
+                    });
+                }
+            }
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.push((name, range));
+            }
         }
     }
 
-    // Display return type
-    if let Some(return_type) = &func.sig.output {
-        match return_type {
-            syn::ReturnType::Default => println!("Function has no return type."),
-            syn::ReturnType::Type(_, ty) => {
-                if let Type::Path(TypePath { path, .. }) = &**ty {
-                    let return_type = &path.segments.last().unwrap().ident;
-                    println!("Return Type: {:?}", return_type);
+    // Checks a function body with only its parameters in scope
+    fn check_function(&mut self, inputs: &syn::punctuated::Punctuated<syn::FnArg, syn::Token![,]>, block: &syn::Block) {
+        let outer_scopes = std::mem::replace(&mut self.scopes, vec![Vec::new()]);
+        for input in inputs {
+            match input {
+                syn::FnArg::Receiver(receiver) => {
+                    let range = SourceRange::from_span(receiver.self_token.span);
+                    self.scopes[0].push(("self".to_owned(), range));
                 }
-            }    
+                syn::FnArg::Typed(typed) => self.bind_pattern(&typed.pat),
+            }
         }
+        self.visit_block(block);
+        self.scopes = outer_scopes;
     }
-}
 
-fn analyze_rust_struct(struct_item: &ItemStruct) {
-    println!("Found Rust struct: {}", struct_item.ident);
-
-    // Display struct visibility
-    if let Some(visibility) = &struct_item.vis {
-        match visibility {
-            Visibility::Public(_) => println!("Struct is public."),
-            Visibility::Crate(_) => println!("Struct has crate visibility."),
-            Visibility::Restricted(_) => println!("Struct has restricted visibility."),
-            Visibility::Inherited => {} // Do nothing for inherited visibility    
-        }   
+    fn in_scope(&mut self, pattern: Option<&syn::Pat>, body: impl FnOnce(&mut Self)) {
+        self.scopes.push(Vec::new());
+        if let Some(pattern) = pattern {
+            self.bind_pattern(pattern);
+        }
+        body(self);
+        self.scopes.pop();
     }
+}
 
-    // Display struct fields and their types
-    for field in &struct_item.fields {
-        if let Some(ident) = &field.ident {
-            let field_type = &field.ty;
-            println!("Struct field {}: {:?}", ident, quote! {#field_type});
+// Identifiers bound by a pattern; capitalised single names are unit structs or variants like `None`
+fn pattern_bindings<'p>(pattern: &'p syn::Pat, idents: &mut Vec<&'p proc_macro2::Ident>) {
+    match pattern {
+        syn::Pat::Ident(pat) => {
+            if !pat.ident.to_string().starts_with(char::is_uppercase) {
+                idents.push(&pat.ident);
+            }
+            if let Some((_, subpattern)) = &pat.subpat {
+                pattern_bindings(subpattern, idents);
+            }
+        }
+        syn::Pat::Tuple(pat) => pat.elems.iter().for_each(|elem| pattern_bindings(elem, idents)),
+        syn::Pat::TupleStruct(pat) => pat.pat.elems.iter().for_each(|elem| pattern_bindings(elem, idents)),
+        syn::Pat::Struct(pat) => pat.fields.iter().for_each(|field| pattern_bindings(&field.pat, idents)),
+        syn::Pat::Slice(pat) => pat.elems.iter().for_each(|elem| pattern_bindings(elem, idents)),
+        syn::Pat::Reference(pat) => pattern_bindings(&pat.pat, idents),
+        syn::Pat::Box(pat) => pattern_bindings(&pat.pat, idents),
+        syn::Pat::Type(pat) => pattern_bindings(&pat.pat, idents),
+        // Every alternative binds the same names
+        syn::Pat::Or(pat) => {
+            if let Some(first) = pat.cases.first() {
+                pattern_bindings(first, idents);
+            }
         }
+        _ => {}
     }
-
-    // Generate code snippet for struct initialization
-    let struct_name = &struct_item.ident;
-    let struct_fields: Vec<Ident> = struct_item.fields.iter().filter_map(|f| f.ident.clone()).collect();
-    let init_code = quote! {
-        let instance = #struct_name {
-            #(#struct_fields: Default::default()),*
-        };
-    };
-
-    println!("Code snippet for struct initialization:\n{}", init_code);
 }
 
-fn analyze_cpp_code(code: &str) {
-    let function_declaration_regex = regex::Regex::new(r"\b(?:\w+\s+){0,2}(\w+)\s*\([^)]*\)\s*{").unwrap();  
+impl<'a, 'ast> Visit<'ast> for BindingChecker<'a> {
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        self.check_function(&item.sig.inputs, &item.block);
+    }
 
-    for capture in function_declaration_regex.captures_iter(code) {
-        if let (Some(func_name), Some(template_params), Some(params_str), const_modifier) =
-            (capture.get(1), capture.get(2), capture.get(3), capture.get(4)) {
-            let template_params = template_params.as_str();
-            let params: Vec<&str> = params_str.as_str().split(',').map(|p| p.trim()).collect();
+    fn visit_impl_item_method(&mut self, method: &'ast syn::ImplItemMethod) {
+        self.check_function(&method.sig.inputs, &method.block);
+    }
 
-            println!("Found C++ function: {}", func_name.as_str());
+    fn visit_trait_item_method(&mut self, method: &'ast syn::TraitItemMethod) {
+        if let Some(block) = &method.default {
+            self.check_function(&method.sig.inputs, block);
+        }
+    }
 
-            // Display template parameters
-            if !template_params.is_empty() {
-                println!("Template Parameters: {}", template_params);
-            }
+    // Constants and statics outside functions have no local scope to check against
+    fn visit_item_const(&mut self, _item: &'ast syn::ItemConst) {}
 
-            // Display function parameters
-            for param in &params {
-                let param_parts: Vec<&str> = param.split_whitespace().collect();
-                if param_parts.len() == 2 {
-                    let param_type = param_parts[0];
-                    let param_name = param_parts[1];
-                    println!("Parameter {}: {:?}", param_name, param_type);
-                }
-            }
+    fn visit_item_static(&mut self, _item: &'ast syn::ItemStatic) {}
 
-            // Display const modifier
-            if const_modifier.is_some() {
-                println!("Const Modifier: const");
-            }
+    fn visit_block(&mut self, block: &'ast syn::Block) {
+        self.in_scope(None, |checker| visit::visit_block(checker, block));
+    }
 
-            // Extract and display return type
-            let return_type = extract_return_type(code, capture.start());
-            println!("Return Type: {:?}", return_type);
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        // The initializer cannot see the names it is about to bind
+        if let Some((_, init)) = &local.init {
+            self.visit_expr(init);
+        }
+        self.bind_pattern(&local.pat);
+    }
 
-            // Generate C++ function signature code snippet
-            let func_signature = quote! {
-                #func_name#template_params(#(#parse_quote! { #params }),*) #const_modifier
-            };
+    fn visit_expr_closure(&mut self, closure: &'ast syn::ExprClosure) {
+        self.in_scope(None, |checker| {
+            for input in &closure.inputs {
+                checker.bind_pattern(input);
+            }
+            checker.visit_expr(&closure.body);
+        });
+    }
 
-            println!("C++ Function Signature Code Snippet:\n{}", func_signature);
-        }    
+    fn visit_expr_for_loop(&mut self, for_loop: &'ast syn::ExprForLoop) {
+        self.visit_expr(&for_loop.expr);
+        self.in_scope(Some(&for_loop.pat), |checker| checker.visit_block(&for_loop.body));
     }
-}
 
-fn extract_return_type(code: &str, start: usize) -> Option<String> {
-    let mut depth = 0;
-    let mut found_colon = false;
+    fn visit_expr_if(&mut self, if_expr: &'ast syn::ExprIf) {
+        match &*if_expr.cond {
+            syn::Expr::Let(condition) => {
+                self.visit_expr(&condition.expr);
+                self.in_scope(Some(&condition.pat), |checker| checker.visit_block(&if_expr.then_branch));
+            }
+            condition => {
+                self.visit_expr(condition);
+                self.visit_block(&if_expr.then_branch);
+            }
+        }
+        if let Some((_, else_branch)) = &if_expr.else_branch {
+            self.visit_expr(else_branch);
+        }
+    }
 
-    for (i, c) in code[start..].chars().enumerate() {
-        match c {
-            '<' => depth += 1,
-            '>' => depth -= 1,
-            ';' if depth == 0 && !found_colon => {
-                found_colon = true;
+    fn visit_expr_while(&mut self, while_loop: &'ast syn::ExprWhile) {
+        match &*while_loop.cond {
+            syn::Expr::Let(condition) => {
+                self.visit_expr(&condition.expr);
+                self.in_scope(Some(&condition.pat), |checker| checker.visit_block(&while_loop.body));
             }
-            ':' if found_colon => {
-                return Some(code[start + i + 1..start + i + 2].to_owned().trim().to_string());
+            condition => {
+                self.visit_expr(condition);
+                self.visit_block(&while_loop.body);
             }
-            _ => {}
         }
     }
 
-    None
-}
+    fn visit_expr_match(&mut self, match_expr: &'ast syn::ExprMatch) {
+        self.visit_expr(&match_expr.expr);
+        for arm in &match_expr.arms {
+            self.in_scope(Some(&arm.pat), |checker| {
+                if let Some((_, guard)) = &arm.guard {
+                    checker.visit_expr(guard);
+                }
+                checker.visit_expr(&arm.body);
+            });
+        }
+    }
 
-fn display_completions(language: Language, completions: Vec<String>) {
-    match language {
-        Language::Rust => {
-            println!("Rust Completions:");
+    fn visit_expr_path(&mut self, path: &'ast syn::ExprPath) {
+        if let Some(last) = path.path.segments.last() {
+            self.used.insert(last.ident.to_string());
         }
-        Language::Cpp => {
-            println!("C++ Completions:");
+
+        // Only plain lowercase names can be checked; `Type::item` and capitalised names may come from anywhere
+        if path.qself.is_none() && path.path.leading_colon.is_none() && path.path.segments.len() == 1 {
+            let ident = &path.path.segments[0].ident;
+            let name = ident.to_string();
+            let defined = self.lookup(&name).is_some()
+                || self.items.names.contains(&name)
+                || RUST_PRELUDE_NAMES.contains(&name.as_str())
+                || name.starts_with(char::is_uppercase);
+            if !defined && !self.items.has_glob_import && !self.scopes.is_empty() {
+                self.diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    code: "undefined-identifier",
+                    message: format!("cannot find `{}` in this file", name),
+                    range: SourceRange::from_span(ident.span()),
+                    label: Some("not found in this scope".to_owned()),
+                    notes: Vec::new(),
+                });
+            }
         }
+        visit::visit_expr_path(self, path);
     }
 
-    for (index, completion) in completions.iter().enumerate() {
-        println!("{}. {}", index + 1, completion);
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        self.used.insert(call.method.to_string());
+        visit::visit_expr_method_call(self, call);
     }
-}
 
-fn show_additional_info(language: Language, index: usize, completions: Vec<String>) {
-    match language {
-        Language::Rust => {
-            // Display additional information for Rust completions if available
-            if let Ok(match_) = get_rust_completions("temp_file", 5, 17) {
-                if index < match_.len() {
-                    println!("Additional Info for Rust Completion {}: {:?}", index + 1, match_[index]);
-                }
-            }
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        // Paths in types, patterns and struct literals can name functions too, e.g. `Self::helper` in a const
+        if let Some(last) = path.segments.last() {
+            self.used.insert(last.ident.to_string());
         }
-        Language::Cpp => {
-            // Display additional information for C++ completions if available
-            if let Ok(completion) = get_cpp_completions("temp_file", 5, 17) {
-                if index < completion.len() {
-                    println!("Additional Info for C++ Completion {}: {:?}", index + 1, completion[index]);
+        visit::visit_path(self, path);
+    }
+
+    // Macro arguments are not parsed, so every identifier in them counts as used
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let mut pending = vec![mac.tokens.clone()];
+        while let Some(tokens) = pending.pop() {
+            for token in tokens {
+                match token {
+                    proc_macro2::TokenTree::Ident(ident) => {
+                        self.used.insert(ident.to_string());
+                    }
+                    proc_macro2::TokenTree::Group(group) => pending.push(group.stream()),
+                    _ => {}
                 }
             }
         }
+        visit::visit_macro(self, mac);
     }
 }
 
-fn perform_syntax_highlighting(code: &str) {
-    let theme = ThemeSet::load_defaults().themes["base16-ocean.dark"];
-    let syntax_set = SyntaxSet::load_defaults_nonewlines();    
+// rustc-style text: header, location, the source line and carets under the range
+fn render_diagnostic(diagnostic: &Diagnostic, file_name: &str, code: &str) -> String {
+    let range = &diagnostic.range;
+    let line_text = code.lines().nth(range.start_line.saturating_sub(1)).unwrap_or_default();
+    let gutter = " ".repeat(range.start_line.to_string().len());
 
-    let syntax = syntax_set.find_syntax_by_extension("rs").unwrap(); // Assume Rust syntax for now
-    let mut highlighter = HighlightLines::new(syntax, &theme);
+    // Ranges spanning several lines are underlined to the end of their first line
+    let end_column = if range.end_line == range.start_line {
+        range.end_column
+    } else {
+        line_text.chars().count() + 1
+    };
+    let start_column = range.start_column.max(1);
+    let caret_count = end_column.saturating_sub(start_column).max(1);
+    let indent: String = line_text
+        .chars()
+        .take(start_column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
 
-    for line in code.lines() {
-        let ranges = highlighter.highlight(line);
-        let escaped = syntect::util::as_24_bit_terminal_escaped(&ranges[..], false);
-        println!("{}", escaped);
+    let mut output = format!(
+        "{}[{}]: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+        diagnostic.severity.name(),
+        diagnostic.code,
+        diagnostic.message,
+        gutter,
+        file_name,
+        range.start_line,
+        start_column,
+        gutter,
+        range.start_line,
+        line_text,
+        gutter,
+        indent,
+        "^".repeat(caret_count)
+    );
+    if let Some(label) = &diagnostic.label {
+        output.push(' ');
+        output.push_str(label);
     }
+    output.push('\n');
+    for note in &diagnostic.notes {
+        output.push_str(&format!("{} |\n{} = note: {}\n", gutter, gutter, note));
+    }
+    output
 }
 
-fn format_code(code: &str) -> String {
-    match rustfmt::run_rustfmt(rustfmt::Config::default(), Some("stdin".to_owned()), code.into()) {
-        Ok(result) => result.1,
-        Err(err) => {
-            eprintln!("Error formatting code: {}", err);
-            code.to_owned()
-        }
-    }    
+fn diagnostic_to_json(diagnostic: &Diagnostic, file_name: &str) -> Value {
+    json!({
+        "file": file_name,
+        "severity": diagnostic.severity.name(),
+        "code": diagnostic.code,
+        "message": diagnostic.message,
+        "line": diagnostic.range.start_line,
+        "column": diagnostic.range.start_column,
+        "end_line": diagnostic.range.end_line,
+        "end_column": diagnostic.range.end_column,
+        "label": diagnostic.label,
+        "notes": diagnostic.notes,
+    })
 }
 
-fn get_user_preferences() -> UserPreferences {
-    // Allow the user to interactively choose preferences
-    println!("Do you want to perform code analysis? (y/n):");
-    let perform_analysis = read_yes_no_input();  
+fn lsp_diagnostic(text: &str, diagnostic: &Diagnostic) -> Value {
+    let severity = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    };
+    json!({
+        "range": lsp_range(text, &diagnostic.range),
+        "severity": severity,
+        "code": diagnostic.code,
+        "source": "intelligent-code-completion",
+        "message": diagnostic.message,
+    })
+}
 
-    println!("Do you want to enable syntax highlighting? (y/n):");
-    let syntax_highlighting = read_yes_no_input();
+// `check <file> [--format text|json]`; fails when the file has errors
+fn run_check_command(args: &[String]) -> Result<(), String> {
+    let mut file = None;
+    let mut format = OutputFormat::Text;
 
-    println!("Do you want to perform code formatting? (y/n):");
-    let perform_formatting = read_yes_no_input();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().map(|value| value.as_str()) {
+                    Some("text") => OutputFormat::Text,
+                    Some("json") => OutputFormat::Json,
+                    Some(other) => return Err(format!("Unknown format '{}', expected text or json", other)),
+                    None => return Err("Missing value for --format".to_owned()),
+                }
+            }
+            other if other.starts_with("--") => return Err(format!("Unknown argument '{}'", other)),
+            other => file = Some(other.to_owned()),
+        }
+    }
+
+    let file = file.ok_or("Missing file to check")?;
+    let code = fs::read_to_string(&file).map_err(|e| format!("Failed to read {}: {}", file, e))?;
+    let diagnostics = collect_rust_diagnostics(&code);
+
+    match format {
+        OutputFormat::Json => {
+            let diagnostics: Vec<Value> = diagnostics.iter().map(|diagnostic| diagnostic_to_json(diagnostic, &file)).collect();
+            println!("{}", serde_json::to_string_pretty(&diagnostics).map_err(|e| e.to_string())?);
+        }
+        OutputFormat::Text => {
+            for diagnostic in &diagnostics {
+                println!("{}", render_diagnostic(diagnostic, &file, &code));
+            }
+            let count = |severity| diagnostics.iter().filter(|diagnostic| diagnostic.severity == severity).count();
+            println!("{}: {} error(s), {} warning(s)", file, count(Severity::Error), count(Severity::Warning));
+        }
+    }
 
-    UserPreferences {
-        perform_analysis,
-        syntax_highlighting,
-        perform_formatting,
+    if diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error) {
+        return Err(format!("{} has errors", file));
     }
+    Ok(())
 }
 
-fn read_yes_no_input() -> bool {
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).expect("Failed to read line");
-    input.trim().to_lowercase() == "y"        
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
 }
 
-#[derive(Debug)]
-enum SyntaxTheme {
-    Default,
-    Ocean,
-    Custom(String),
+struct CompleteOptions {
+    file: String,
+    line: usize,
+    column: usize,
+    format: OutputFormat,
+    show_scores: bool,
 }
 
-fn choose_syntax_theme() -> SyntaxTheme {
-    println!("Choose a syntax highlighting theme:");
-    println!("1. Default");
-    println!("2. Ocean");
-    println!("3. Custom");
-
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).expect("Failed to read line");
+fn parse_complete_args(args: &[String]) -> Result<CompleteOptions, String> {
+    let mut file = None;
+    let mut line = None;
+    let mut column = None;
+    let mut format = OutputFormat::Text;
+    let mut show_scores = false;
 
-    match input.trim().parse::<u32>() {
-        Ok(choice) => match choice {
-            1 => SyntaxTheme::Default,
-            2 => SyntaxTheme::Ocean,
-            3 => {
-                println!("Enter the custom theme name:");
-                let mut theme_name = String::new();
-                io::stdin().read_line(&mut theme_name).expect("Failed to read line");
-                SyntaxTheme::Custom(theme_name.trim().to_owned())
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--file" => file = Some(value()?.clone()),
+            "--line" => line = Some(parse_position(arg, value()?)?),
+            "--col" | "--column" => column = Some(parse_position(arg, value()?)?),
+            "--scores" => show_scores = true,
+            "--format" => {
+                format = match value()?.as_str() {
+                    "text" => OutputFormat::Text,
+                    "json" => OutputFormat::Json,
+                    other => return Err(format!("Unknown format '{}', expected text or json", other)),
+                }
             }
-            _ => SyntaxTheme::Default, // Default theme if an invalid choice is made
-        },
-        Err(_) => SyntaxTheme::Default, // Default theme if parsing fails
+            other => return Err(format!("Unknown argument '{}'", other)),
+        }
     }
-}
 
-fn get_syntax_theme(theme_name: &str) -> Option<Style> {
-    let theme_set = ThemeSet::load_defaults();
-    theme_set.themes.get(theme_name).map(|theme| theme.settings.selection)    
+    Ok(CompleteOptions {
+        file: file.ok_or("Missing --file")?,
+        line: line.ok_or("Missing --line")?,
+        column: column.ok_or("Missing --col")?,
+        format,
+        show_scores,
+    })
 }
 
-fn perform_syntax_highlighting(code: &str, theme: SyntaxTheme) {
-    match theme {
-        SyntaxTheme::Default => perform_default_syntax_highlighting(code),
-        SyntaxTheme::Ocean => perform_ocean_syntax_highlighting(code),
-        SyntaxTheme::Custom(theme_name) => {
-            if let Some(style) = get_syntax_theme(&theme_name) {
-                perform_custom_syntax_highlighting(code, style);
-            } else {
-                eprintln!("Custom theme not found. Performing default syntax highlighting.");
-                perform_default_syntax_highlighting(code);
-            }
-        }    
+// Lines and columns are 1-based
+fn parse_position(flag: &str, value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(position) if position > 0 => Ok(position),
+        _ => Err(format!("{} expects a positive number, got '{}'", flag, value)),
     }
 }
 
-fn perform_default_syntax_highlighting(code: &str) {
-    // Default syntax highlighting logic
-    println!("Performing Default Syntax Highlighting:");
-    for line in code.lines() {
-        let ranges = highlighter.highlight(line, &syntax_set);
-        let escaped = syntect::util::as_24_bit_terminal_escaped(&ranges[..], false);
-        println!("{}", escaped);
-    }
+fn completion_to_json(ranked: &RankedCompletion) -> Value {
+    let completion = &ranked.completion;
+    json!({
+        "label": completion.label,
+        "kind": completion.kind,
+        "detail": completion.detail,
+        "documentation": completion.documentation,
+        "insert_text": completion.insert_text,
+        "locality": format!("{:?}", ranked.locality).to_lowercase(),
+        "score": ranked.score(),
+        "score_breakdown": {
+            "fuzzy": ranked.breakdown.fuzzy,
+            "locality": ranked.breakdown.locality,
+            "expected_type": ranked.breakdown.expected_type,
+            "recency": ranked.breakdown.recency,
+        },
+    })
 }
 
-fn perform_ocean_syntax_highlighting(code: &str) {
-    // Ocean theme syntax highlighting logic
-    println!("Performing Ocean Theme Syntax Highlighting:");
-    let theme = ThemeSet::load_defaults().themes["oceanic-next"];
-    let syntax_set = SyntaxSet::load_defaults_newlines();
-    let syntax = syntax_set.find_syntax_by_extension("rs").unwrap();
-    let mut highlighter = HighlightLines::new(syntax, &theme);
+// The crate directory holding a file: the nearest ancestor with a Cargo.toml
+fn crate_root(file_path: &Path) -> Option<PathBuf> {
+    let directory = fs::canonicalize(file_path).ok()?;
+    directory.ancestors().skip(1).find(|ancestor| ancestor.join("Cargo.toml").is_file()).map(Path::to_path_buf)
+}
 
-    for line in code.lines() {
-        let ranges = highlighter.highlight(line);
-        let escaped = syntect::util::as_24_bit_terminal_escaped(&ranges[..], false);
-        println!("{}", escaped);
+// Completions from the file's own symbols and, for a Rust file in a crate, those of the other
+// files of the crate; used when no language server answers. Ranking filters them by prefix
+fn local_completions(language: &dyn Language, file_path: &str, code: &str, line: usize, column: usize) -> Vec<Completion> {
+    // Code being typed rarely parses; retry without the line under the cursor
+    let mut symbols = language
+        .extract_symbols(code)
+        .or_else(|_| language.extract_symbols(&without_line(code, line)))
+        .unwrap_or_default();
+    if language.id() == "rust" {
+        if let Some(root) = crate_root(Path::new(file_path)) {
+            let file = fs::canonicalize(file_path).ok();
+            let index = SymbolIndex::build(&root);
+            symbols.extend(
+                index
+                    .symbols()
+                    .filter(|(path, _)| file.as_ref() != Some(*path))
+                    .map(|(_, symbol)| symbol.clone()),
+            );
+        }
     }
+
+    let (_, after_dot) = prefix_at(code, line.saturating_sub(1), column.saturating_sub(1));
+    symbols
+        .into_iter()
+        // The same choice as the language server mode: members after a `.`, everything else elsewhere
+        .filter(|symbol| match symbol.kind {
+            SymbolKind::Field | SymbolKind::Method => after_dot,
+            SymbolKind::Impl => false,
+            _ => !after_dot,
+        })
+        .map(|symbol| Completion {
+            label: symbol.name,
+            kind: Some(completion_kind_name(lsp_completion_kind(symbol.kind)).to_owned()),
+            detail: Some(symbol.detail),
+            documentation: None,
+            insert_text: None,
+        })
+        .collect()
 }
 
-fn perform_custom_syntax_highlighting(code: &str, style: Style) {
-    // Custom theme syntax highlighting logic
-    println!("Performing Custom Theme Syntax Highlighting:");
-    let syntax_set = SyntaxSet::load_defaults_newlines();
-    let syntax = syntax_set.find_syntax_by_extension("rs").unwrap();
-    let mut highlighter = HighlightLines::new(syntax, &Style);
+// `complete --file x.rs --line L --col C [--format text|json] [--scores]`; without a language server the
+// candidates come from the local analysis
+fn run_complete_command(args: &[String], preferences: &UserPreferences) -> Result<(), String> {
+    let options = parse_complete_args(args)?;
+    let code = fs::read_to_string(&options.file).map_err(|e| format!("Failed to read {}: {}", options.file, e))?;
+    let language = detect_language_or(&options.file, &code, preferences.fallback_language());
+    let server = LanguageServerConfig::for_language(language);
+    let mut completions = match get_completions(language, &server, &options.file, options.line, options.column) {
+        Ok(completions) => completions,
+        // E.g. no server installed: complete from our own analysis instead
+        Err(err) => {
+            eprintln!("Warning: {}; completing from the local analysis", err);
+            local_completions(language, &options.file, &code, options.line, options.column)
+        }
+    };
+    let context = RankingContext::from_code(&code, options.line, options.column);
+    completions.extend(snippet_completions(&load_snippets(language), &context.prefix));
+    let mut completions = rank_completions(completions, &context);
+    completions.truncate(preferences.max_completions);
 
-    for line in code.lines() {
-        let ranges = highlighter.highlight(line);
-        let escaped = syntect::util::as_24_bit_terminal_escaped(&ranges[..], false);
-        println!("{}", escaped);
+    match options.format {
+        OutputFormat::Json => {
+            let completions: Vec<Value> = completions.iter().map(completion_to_json).collect();
+            let output = json!({
+                "file": options.file,
+                "line": options.line,
+                "column": options.column,
+                "language": language.id(),
+                "completions": completions,
+            });
+            println!("{}", serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?);
+        }
+        OutputFormat::Text => {
+            for (index, ranked) in completions.iter().enumerate() {
+                let completion = &ranked.completion;
+                let kind = completion.kind.as_deref().unwrap_or("unknown");
+                match &completion.detail {
+                    Some(detail) => println!("{}. {} ({}) {}", index + 1, completion.label, kind, detail),
+                    None => println!("{}. {} ({})", index + 1, completion.label, kind),
+                }
+                if options.show_scores {
+                    println!("   {}", format_score(ranked));
+                }
+            }
+        }
     }
+    Ok(())
 }
 
 fn main() {
+    // Act as a language server for editors when started with `--lsp`
+    if env::args().any(|arg| arg == "--lsp") {
+        if let Err(err) = run_language_server() {
+            eprintln!("Error: {}", err);
+        }
+        return;
+    }
+
+    let mut args: Vec<String> = env::args().collect();
+
+    // Config files, then `--set key=value` flags, e.g. `--set max_completions=5`
+    let flags = take_preference_flags(&mut args).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    });
+    let preferences = load_preferences(&flags).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    });
+
+    // View and edit the settings: `config set theme ocean`, `config show`
+    if args.get(1).map(|arg| arg.as_str()) == Some("config") {
+        if let Err(err) = run_config_command(&args[2..], &flags) {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Scripted mode for editor plugins and CI: `complete --file x.rs --line L --col C --format json`
+    if args.get(1).map(|arg| arg.as_str()) == Some("complete") {
+        if let Err(err) = run_complete_command(&args[2..], &preferences) {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Export highlighted code: `highlight x.rs --output html --theme Solarized.tmTheme --out x.html`
+    if args.get(1).map(|arg| arg.as_str()) == Some("highlight") {
+        if let Err(err) = run_highlight_command(&args[2..], &preferences) {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Show the signature of the call at a position: `signature --file x.rs --line L --col C`
+    if args.get(1).map(|arg| arg.as_str()) == Some("signature") {
+        if let Err(err) = run_signature_command(&args[2..]) {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Report parse errors and warnings for a Rust file: `check x.rs --format json`
+    if args.get(1).map(|arg| arg.as_str()) == Some("check") {
+        if let Err(err) = run_check_command(&args[2..]) {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Print the symbols of a whole crate with `--index <dir>`
+    if let Some(position) = args.iter().position(|arg| arg == "--index") {
+        let root = args.get(position + 1).map_or(".", |root| root.as_str());
+        print_symbol_index(Path::new(root));
+        return;
+    }
+
     let mut code_snippet = String::new();
 
     // Allow the user to input code interactively
@@ -414,38 +4459,49 @@ fn main() {
         code_snippet.push_str(&line);
     }
 
-    // Determine the language based on the code
-    let language = if code_snippet.contains("#include") {
-        Language::Cpp
-    } else {
-        Language::Rust
-    };
-
     // Temporary file path to store the code snippet
     let file_path = "temp_file";
 
+    // Determine the language based on the code
+    let language = detect_language_or(file_path, &code_snippet, preferences.fallback_language());
+
     // Write the code snippet to a temporary file
-    write_code_to_file(language.clone(), &code_snippet, file_path);
+    write_code_to_file(&code_snippet, file_path);
 
-    // Get user preferences
-    let preferences = get_user_preferences();
+    // Ask for preferences on the first run only and remember them
+    let preferences = match user_config_path() {
+        Some(path) if !path.exists() && project_config_path().is_none() => {
+            let answers = get_user_preferences();
+            match answers.save(&path) {
+                Ok(()) => println!("Saved your preferences to {}; change them with `config set`.", path.display()),
+                Err(err) => eprintln!("Warning: {}", err),
+            }
+            let mut preferences = preferences;
+            answers.apply(&mut preferences);
+            flags.apply(&mut preferences);
+            preferences
+        }
+        _ => preferences,
+    };
 
     // Perform analysis based on user preferences
     if preferences.perform_analysis {
-        analyze_code(language.clone(), &code_snippet);
+        analyze_code(language, &code_snippet);
     }
 
-    // Get completions based on the language
-    let completions = match language {
-        Language::Rust => get_rust_completions(file_path, 5, 17), // Adjust line and column based on your cursor position
-        Language::Cpp => get_cpp_completions(file_path, 5, 17),   // Adjust line and column based on your cursor position
-    };
+    // Complete at the end of the entered code
+    let (line, column) = end_of_code_position(&code_snippet);
+    let completions = get_completions(language, &LanguageServerConfig::for_language(language), file_path, line, column);
 
     // Display completions
     match completions {
-        Ok(completions) => {
-            let completion_strings: Vec<String> = completions.iter().map(|c| c.name.clone()).collect();
-            display_completions(language.clone(), completion_strings);
+        Ok(mut completions) => {
+            let context = RankingContext::from_code(&code_snippet, line, column);
+            completions.extend(snippet_completions(&load_snippets(language), &context.prefix));
+            let mut completions = rank_completions(completions, &context);
+            completions.truncate(preferences.max_completions);
+            let completion_strings: Vec<String> = completions.iter().map(|c| c.completion.label.clone()).collect();
+            display_completions(language, completion_strings);
 
             // Allow the user to choose a completion
             println!("Enter the number of the completion you want to select:");
@@ -454,7 +4510,7 @@ fn main() {
 
             if let Ok(index) = input.trim().parse::<usize>() {
                 if index > 0 && index <= completions.len() {
-                    show_additional_info(language, index - 1, completion_strings);
+                    show_additional_info(language, &completions[index - 1].completion, file_path, line, column);
                 } else {
                     eprintln!("Invalid selection index.");
                 }
@@ -465,15 +4521,9 @@ fn main() {
         Err(err) => eprintln!("Error: {}", err),
     }
 
-    // Get user preferences
-    let preferences = get_user_preferences();
-
-    // Choose syntax highlighting theme
-    let syntax_theme = choose_syntax_theme();
-
     // Perform syntax highlighting based on user preferences
     if preferences.syntax_highlighting {
-        perform_syntax_highlighting(&code_snippet, syntax_theme);
+        perform_syntax_highlighting(&code_snippet, SyntaxTheme::from_name(&preferences.theme), language);
     }
     
     // Perform code formatting based on user preferences
@@ -485,3 +4535,196 @@ fn main() {
     // Clean up the temporary file
     fs::remove_file(file_path).expect("Failed to remove the temporary file");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Temporary directory with a space in its name, like the programs in this repository;
+    // removed again when the test ends
+    struct TestDirectory(PathBuf);
+
+    impl TestDirectory {
+        fn new(test: &str) -> Self {
+            let directory = env::temp_dir().join(format!("completion tests {} {}", process::id(), test));
+            fs::create_dir_all(&directory).unwrap();
+            TestDirectory(directory)
+        }
+
+        fn sample_file(&self) -> String {
+            let path = self.0.join("sample file.rs");
+            fs::write(&path, "fn main() {\n    let v = vec![1];\n    v.\n}\n").unwrap();
+            path.to_string_lossy().into_owned()
+        }
+    }
+
+    impl Drop for TestDirectory {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // Scripted stand-in for a language server, like "Fake Language Server.rs" but needing only
+    // `sh` and `dd`: it answers requests with canned results and, like a real server, exits with
+    // status 1 when told to exit before a shutdown request
+    const FAKE_SERVER: &str = r#"
+        shut_down=0
+        while IFS= read -r header; do
+            header=$(printf '%s' "$header" | tr -d '\r')
+            case "$header" in
+                Content-Length:*) length=$(printf '%s' "$header" | tr -cd 0-9); continue ;;
+                ?*) continue ;;
+            esac
+            body=$(dd bs=1 count="$length" 2>/dev/null)
+            id=$(printf '%s' "$body" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+            case "$body" in
+                *'"method":"initialize"'*) result='{"capabilities":{}}' ;;
+                *'"method":"textDocument/completion"'*) result='{"isIncomplete":false,"items":[{"label":"push","kind":2,"detail":"fn push(&mut self, value: T)"},{"label":"pop","kind":2,"documentation":{"kind":"markdown","value":"Removes the last element"}}]}' ;;
+                *'"method":"textDocument/hover"'*) result='{"contents":{"kind":"plaintext","value":"Vec<i32>"}}' ;;
+                *'"method":"shutdown"'*) shut_down=1; result=null ;;
+                *'"method":"exit"'*) [ "$shut_down" = 1 ] && exit 0; exit 1 ;;
+                *) result=null ;;
+            esac
+            [ -z "$id" ] && continue
+            response="{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":$result}"
+            printf 'Content-Length: %d\r\n\r\n%s' "${#response}" "$response"
+        done
+    "#;
+
+    fn fake_server() -> LanguageServerConfig {
+        LanguageServerConfig {
+            command: "sh".to_owned(),
+            args: vec!["-c".to_owned(), FAKE_SERVER.to_owned()],
+        }
+    }
+
+    #[test]
+    fn completes_through_the_server() {
+        let directory = TestDirectory::new("completion");
+        let completions = get_completions(&RustLanguage, &fake_server(), &directory.sample_file(), 3, 7).unwrap();
+
+        let labels: Vec<&str> = completions.iter().map(|completion| completion.label.as_str()).collect();
+        assert_eq!(labels, ["push", "pop"]);
+        assert_eq!(completions[0].kind.as_deref(), Some("method"));
+        assert_eq!(completions[0].detail.as_deref(), Some("fn push(&mut self, value: T)"));
+        assert_eq!(completions[1].documentation.as_deref(), Some("Removes the last element"));
+    }
+
+    #[test]
+    fn hovers_through_the_server() {
+        let directory = TestDirectory::new("hover");
+        let hover = get_hover(&RustLanguage, &fake_server(), &directory.sample_file(), 2, 9).unwrap();
+        assert_eq!(hover.as_deref(), Some("Vec<i32>"));
+    }
+
+    #[test]
+    fn shuts_the_server_down() {
+        let mut client = LspClient::start(&fake_server()).unwrap();
+        client.initialize("file:///").unwrap();
+        assert_eq!(client.shutdown(), Ok(()));
+
+        // Exiting without the shutdown request is an error
+        let mut client = LspClient::start(&fake_server()).unwrap();
+        client.initialize("file:///").unwrap();
+        client.notify("exit", Value::Null).unwrap();
+        assert_eq!(client.server.wait().unwrap().code(), Some(1));
+    }
+
+    #[test]
+    fn kills_the_server_after_an_error() {
+        let directory = TestDirectory::new("error");
+        // Keeps running after the client closes its stdin
+        let mut server = fake_server();
+        server.args[1].push_str("exec sleep 60\n");
+        let mut pid = 0;
+        let result = with_language_server(&RustLanguage, &server, &directory.sample_file(), |client, _| -> Result<(), String> {
+            pid = client.server.id();
+            Err("query failed".to_owned())
+        });
+
+        assert_eq!(result, Err("query failed".to_owned()));
+        let alive = Command::new("kill").args(["-0", &pid.to_string()]).stderr(Stdio::null()).status().unwrap();
+        assert!(!alive.success());
+    }
+
+    #[test]
+    fn completes_locally_from_the_crate() {
+        let directory = TestDirectory::new("local");
+        fs::write(directory.0.join("Cargo.toml"), "[package]\nname = \"shapes\"\n").unwrap();
+        fs::create_dir_all(directory.0.join("src")).unwrap();
+        fs::write(directory.0.join("src").join("shapes.rs"), "pub struct Circle { pub radius: f64 }\n").unwrap();
+        let main = directory.0.join("src").join("main.rs");
+        let code = "mod shapes;\nfn circumference() -> f64 { 0.0 }\nfn main() {\n    let c = Ci\n}\n";
+        fs::write(&main, code).unwrap();
+
+        let completions = local_completions(&RustLanguage, &main.to_string_lossy(), code, 4, 15);
+        let labels: Vec<&str> = completions.iter().map(|completion| completion.label.as_str()).collect();
+        assert!(labels.contains(&"circumference"), "{:?}", labels);
+        assert!(labels.contains(&"Circle"), "{:?}", labels);
+        assert!(!labels.contains(&"radius"), "{:?}", labels);
+
+        let member = "mod shapes;\nfn main() {\n    c.\n}\n";
+        let completions = local_completions(&RustLanguage, &main.to_string_lossy(), member, 3, 7);
+        let labels: Vec<&str> = completions.iter().map(|completion| completion.label.as_str()).collect();
+        assert_eq!(labels, ["radius"]);
+    }
+
+    #[test]
+    fn parses_truncated_cpp() {
+        let code = "#include <x>\ntemplate <typename T> class Box : public Base<T> {\n  T value;\npublic:\n  \
+                    T get() const { return value; }\n};\nenum class Color : int { Red, Green };\nnamespace n { int f(int a); }\n";
+        assert!(collect_cpp_symbols(code).iter().any(|symbol| symbol.path == "Box::get"));
+        // Every prefix, such as `#include <x>\ntemplate`, ends a declaration early
+        for (end, _) in code.char_indices() {
+            collect_cpp_symbols(&code[..end]);
+        }
+    }
+
+    #[test]
+    fn nests_document_symbols_by_item() {
+        let code = "struct Point { x: i32 }\nimpl Point {\n    fn norm(&self) -> i32 { self.x }\n}\nmod shapes {\n    struct Circle { radius: f64 }\n}\n";
+        let symbols = collect_rust_symbols(code).unwrap();
+        let tree = document_symbols(code, &symbols);
+        let names = |value: &Value| -> Vec<String> {
+            value.as_array().unwrap().iter().map(|symbol| symbol["name"].as_str().unwrap().to_owned()).collect()
+        };
+        assert_eq!(names(&tree), ["Point", "Point", "shapes"]);
+        assert_eq!(names(&tree[0]["children"]), ["x"]);
+        assert_eq!(names(&tree[1]["children"]), ["norm"]);
+        assert_eq!(names(&tree[2]["children"]), ["Circle"]);
+        assert_eq!(names(&tree[2]["children"][0]["children"]), ["radius"]);
+        assert!(hover_text(&symbols, "Point", "rust").unwrap().starts_with("```rust\nstruct Point"));
+        assert!(hover_text(&collect_cpp_symbols("struct Box {};"), "Box", "cpp").unwrap().starts_with("```cpp\n"));
+    }
+
+    #[test]
+    fn keeps_the_last_good_symbols_of_a_broken_buffer() {
+        let root = env::temp_dir().join(format!("icc-index-{}", process::id()));
+        let mut index = SymbolIndex::build(&root);
+        let path = root.join("src").join("lib.rs");
+        index.update_file_text(&path, "fn area() -> f64 { 1.0 }\n", None);
+        index.update_file_text(&path, "fn area() -> f64 { 1.0 }\nfn per(\n", None);
+        let names: Vec<&str> = index.symbols().map(|(_, symbol)| symbol.name.as_str()).collect();
+        assert_eq!(names, ["area"]);
+        assert_eq!(index.errors().count(), 1);
+        index.update_file_text(&path, "fn perimeter() {}\n", None);
+        assert_eq!(index.errors().count(), 0);
+    }
+
+    #[test]
+    fn counts_lsp_columns_in_utf16() {
+        // The emoji is one character but two UTF-16 code units
+        let text = "let s = \"\u{1F600}\"; s\n";
+        assert_eq!(lsp_position(text, 1, 14), json!({ "line": 0, "character": 14 }));
+        assert_eq!(character_column(text, 0, 14), 13);
+    }
+
+    #[test]
+    fn encodes_spaces_in_file_uris() {
+        let directory = TestDirectory::new("uri");
+        let uri = file_uri(&directory.sample_file()).unwrap();
+        assert!(uri.starts_with("file:///"));
+        assert!(uri.contains("/completion%20tests%20"), "{}", uri);
+        assert!(uri.ends_with("/sample%20file.rs"), "{}", uri);
+    }
+}
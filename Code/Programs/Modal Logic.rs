@@ -1,4 +1,14 @@
 use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+
+use serde::Deserialize;
+
+// Add the following dependencies to your Cargo.toml:
+// serde = { version = "1.0", features = ["derive"] }
+// serde_json = "1.0"
+
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 enum Proposition {
@@ -26,7 +36,9 @@ enum TemporalOperator {
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 enum DeonticOperator {
-    Obligation, // O
+    Obligation,  // O
+    Permission,  // P
+    Prohibition, // F
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -72,12 +84,61 @@ enum DeonticFormula {
     And(Box<DeonticFormula>, Box<DeonticFormula>),
     Or(Box<DeonticFormula>, Box<DeonticFormula>),
     Modal(DeonticOperator, Box<DeonticFormula>),
+    Conditional(Box<DeonticFormula>, Box<DeonticFormula>), // O(φ|ψ)
 }
 
 #[derive(Debug)]
 struct KripkeFrame {
     states: HashSet<String>,
     accessibility: HashMap<String, HashSet<String>>,
+    // Ideality rank of each state for deontic evaluation (lower is better, unranked states count as 0)
+    ideality: HashMap<String, usize>,
+    // Atomic propositions true at each state
+    valuation: HashMap<String, HashSet<String>>,
+    // Accessibility relations of individual agents; agents without one use the shared relation
+    agent_accessibility: HashMap<Agent, HashMap<String, HashSet<String>>>,
+}
+
+impl KripkeFrame {
+    fn new() -> Self {
+        KripkeFrame {
+            states: HashSet::new(),
+            accessibility: HashMap::new(),
+            ideality: HashMap::new(),
+            valuation: HashMap::new(),
+            agent_accessibility: HashMap::new(),
+        }
+    }
+
+    fn satisfies(&self, state: &str, prop: &Proposition) -> bool {
+        let Proposition::Atom(name) = prop;
+        self.valuation
+            .get(state)
+            .is_some_and(|atoms| atoms.contains(name))
+    }
+
+    fn agent_successors(&self, state: &str, agent: &Agent) -> Option<&HashSet<String>> {
+        match self.agent_accessibility.get(agent) {
+            Some(relation) => relation.get(state),
+            None => self.accessibility.get(state),
+        }
+    }
+
+    fn rank(&self, state: &str) -> usize {
+        self.ideality.get(state).copied().unwrap_or(0)
+    }
+
+    // The most ideal states among the given candidates
+    fn best_states<'a>(&self, candidates: impl Iterator<Item = &'a String>) -> Vec<&'a String> {
+        let candidates: Vec<&String> = candidates.collect();
+        match candidates.iter().map(|state| self.rank(state)).min() {
+            Some(best_rank) => candidates
+                .into_iter()
+                .filter(|state| self.rank(state) == best_rank)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -92,7 +153,7 @@ impl KripkeModel {
 
      fn evaluate_alethic_formula_at_state(&self, frame: &KripkeFrame, state: &str, formula: &AlethicFormula) -> bool {
          match formula {
-             AlethicFormula::Atom(prop) => frame.states.contains(&prop.to_string()),
+             AlethicFormula::Atom(prop) => frame.satisfies(state, prop),
              AlethicFormula::Not(sub_formula) => !self.evaluate_alethic_formula_at_state(frame, state, sub_formula),
              AlethicFormula::And(sub_formula1, sub_formula2) => {
                  self.evaluate_alethic_formula_at_state(frame, state, sub_formula1)
@@ -109,7 +170,8 @@ impl KripkeModel {
                             .iter()
                             .all(|next_state| self.evaluate_alethic_formula_at_state(frame, next_state, sub_formula))
                     } else {
-                        false
+                        // □ holds vacuously at a state without successors
+                        true
                     }
                 }
 
@@ -145,7 +207,7 @@ impl KripkeModel {
         agent: &Agent,
     ) -> bool {
         match formula {
-            EpistemicFormula::Atom(prop) => frame.states.contains(&prop.to_string()),
+            EpistemicFormula::Atom(prop) => frame.satisfies(state, prop),
             EpistemicFormula::Not(sub_formula) => {
                  !self.evaluate_epistemic_formula_at_state(frame, state, sub_formula, agent)    
             }
@@ -161,7 +223,7 @@ impl KripkeModel {
                 if formula_agent == agent {
                     match operator {
                         ModalOperator::Box => {
-                            if let Some(accessible_states) = frame.accessibility.get(state) {
+                            if let Some(accessible_states) = frame.agent_successors(state, formula_agent) {
                                 accessible_states.iter().all(|next_state| {
                                     self.evaluate_epistemic_formula_at_state(
                                         frame,
@@ -171,11 +233,12 @@ impl KripkeModel {
                                     )
                                 })
                             } else {
-                                false
+                                // □ holds vacuously at a state without successors
+                                true
                             }
                         }
                         ModalOperator::Diamond => {
-                            if let Some(accessible_states) = frame.accessibility.get(state) {
+                            if let Some(accessible_states) = frame.agent_successors(state, formula_agent) {
                                 accessible_states.iter().any(|next_state| {
                                     self.evaluate_epistemic_formula_at_state(
                                         frame,
@@ -216,7 +279,7 @@ impl KripkeModel {
         agent: &Agent,
     ) -> bool {
         match formula {
-            DoxasticFormula::Atom(prop) => frame.states.contains(&prop.to_string()),
+            DoxasticFormula::Atom(prop) => frame.satisfies(state, prop),
             DoxasticFormula::Not(sub_formula) => {
                 !self.evaluate_doxastic_formula_at_state(frame, state, sub_formula, agent)    
             }
@@ -232,7 +295,7 @@ impl KripkeModel {
                  if formula_agent == agent {
                     match operator {
                         ModalOperator::Box => {
-                            if let Some(accessible_states) = frame.accessibility.get(state) {
+                            if let Some(accessible_states) = frame.agent_successors(state, formula_agent) {
                                 accessible_states.iter().all(|next_state| {
                                     self.evaluate_doxastic_formula_at_state(
                                         frame,
@@ -242,11 +305,12 @@ impl KripkeModel {
                                     )
                                 })    
                             } else {
-                                false
+                                // □ holds vacuously at a state without successors
+                                true
                             }    
                         }
                         ModalOperator::Diamond => {
-                            if let Some(accessible_states) = frame.accessibility.get(state) {
+                            if let Some(accessible_states) = frame.agent_successors(state, formula_agent) {
                                 accessible_states.iter().any(|next_state| {
                                     self.evaluate_doxastic_formula_at_state(
                                         frame,
@@ -286,7 +350,7 @@ impl KripkeModel {
         formula: &TemporalFormula,
     ) -> bool {
         match formula {
-            TemporalFormula::Atom(prop) => frame.states.contains(&prop.to_string()), 
+            TemporalFormula::Atom(prop) => frame.satisfies(state, prop), 
             TemporalFormula::Not(sub_formula) => {
                  !self.evaluate_temporal_formula_at_state(frame, state, sub_formula)    
             }
@@ -308,15 +372,12 @@ impl KripkeModel {
                         false
                     }    
                 }
-                TemporalOperator::Past => {
-                    if let Some(accessible_states) = frame.accessibility.get(state) {
-                        accessible_states
-                            .iter()
-                            .any(|next_state| self.evaluate_temporal_formula_at_state(frame, next_state, sub_formula))    
-                    } else {
-                        false
-                    }        
-                }
+                // The past of a state is the states it is accessible from
+                TemporalOperator::Past => frame
+                    .accessibility
+                    .iter()
+                    .filter(|(_, next_states)| next_states.contains(state))
+                    .any(|(previous_state, _)| self.evaluate_temporal_formula_at_state(frame, previous_state, sub_formula)),
             },
         }
     }
@@ -339,7 +400,7 @@ impl KripkeModel {
         formula: &DeonticFormula,
     ) -> bool {
         match formula {
-            DeonticFormula::Atom(prop) => frame.states.contains(&prop.to_string()),
+            DeonticFormula::Atom(prop) => frame.satisfies(state, prop),
             DeonticFormula::Not(sub_formula) => {
                 !self.evaluate_deontic_formula_at_state(frame, state, sub_formula)    
             }
@@ -351,20 +412,44 @@ impl KripkeModel {
                 self.evaluate_deontic_formula_at_state(frame, state, sub_formula1)
                     || self.evaluate_deontic_formula_at_state(frame, state, sub_formula2)    
             }
-            DeonticFormula::Modal(operator, sub_formula) => match operator {
-                DeonticOperator::O => {
-                    if let Some(accessible_states) = frame.accessibility.get(state) {
-                        accessible_states
-                            .iter()
-                            .any(|next_state| self.evaluate_deontic_formula_at_state(frame, next_state, sub_formula))    
-                    } else {
-                        false
-                    }   
+            // At a state without ideal alternatives O and F hold vacuously and P fails,
+            // so Pφ ≡ ¬Fφ ≡ ¬O¬φ everywhere; check_deontic_seriality reports such states
+            DeonticFormula::Modal(operator, sub_formula) => {
+                let ideal_states = self.ideal_deontic_states(frame, state);
+                match operator {
+                    DeonticOperator::Obligation => ideal_states
+                        .iter()
+                        .all(|next_state| self.evaluate_deontic_formula_at_state(frame, next_state, sub_formula)),
+                    DeonticOperator::Permission => ideal_states
+                        .iter()
+                        .any(|next_state| self.evaluate_deontic_formula_at_state(frame, next_state, sub_formula)),
+                    DeonticOperator::Prohibition => !ideal_states
+                        .iter()
+                        .any(|next_state| self.evaluate_deontic_formula_at_state(frame, next_state, sub_formula)),
                 }
-            },
+            }
+            DeonticFormula::Conditional(sub_formula, condition) => {
+                // O(φ|ψ) holds when the best accessible ψ-states all satisfy φ, vacuously like O,
+                // so O(φ|⊤) ≡ Oφ
+                let accessible_states = frame.accessibility.get(state).into_iter().flatten();
+                let condition_states = accessible_states
+                    .filter(|next_state| self.evaluate_deontic_formula_at_state(frame, next_state, condition));
+                frame
+                    .best_states(condition_states)
+                    .iter()
+                    .all(|next_state| self.evaluate_deontic_formula_at_state(frame, next_state, sub_formula))
+            }
         }    
     }
 
+    // The deontically ideal alternatives of a state: the best-ranked states accessible from it
+    fn ideal_deontic_states<'a>(&self, frame: &'a KripkeFrame, state: &str) -> Vec<&'a String> {
+        match frame.accessibility.get(state) {
+            Some(accessible_states) => frame.best_states(accessible_states.iter()),
+            None => Vec::new(),
+        }
+    }
+
     fn evaluate_deontic_formula(&self, formula: &DeonticFormula) -> bool {
         for frame in &self.frames {
             for state in &frame.states {
@@ -374,12 +459,680 @@ impl KripkeModel {
             }
         }
         true
-    }       
+    }
+
+    // Deontic frames should be serial (axiom D: Op → Pp); returns every state without an ideal alternative
+    fn check_deontic_seriality(&self) -> Result<(), Vec<String>> {
+        let mut dead_ends: Vec<String> = Vec::new();
+        for frame in &self.frames {
+            for state in &frame.states {
+                if self.ideal_deontic_states(frame, state).is_empty() {
+                    dead_ends.push(state.clone());
+                }
+            }
+        }
+
+        if dead_ends.is_empty() {
+            Ok(())
+        } else {
+            dead_ends.sort();
+            Err(dead_ends)
+        }
+    }
+
+    // Returns the states at which the given norms cannot all be met
+    fn find_norm_conflicts(&self, norms: &[DeonticFormula]) -> Vec<String> {
+        let mut conflicts: Vec<String> = Vec::new();
+        for frame in &self.frames {
+            for state in &frame.states {
+                if !self.norms_compatible_at_state(frame, state, norms) {
+                    conflicts.push(state.clone());
+                }
+            }
+        }
+        conflicts.sort();
+        conflicts
+    }
+
+    // The norms can be met at a state when an ideal alternative satisfies the obligations and
+    // prohibitions, one does so together with each permitted formula, and for each condition ψ of
+    // a conditional obligation one of the best ψ-states satisfies every obligation given ψ. Those
+    // best ψ-states must also satisfy the unconditional norms when they are ideal alternatives.
+    fn norms_compatible_at_state(&self, frame: &KripkeFrame, state: &str, norms: &[DeonticFormula]) -> bool {
+        let ideal_states = self.ideal_deontic_states(frame, state);
+        let complies = |next_state: &str| norms.iter().all(|norm| self.satisfies_norm_at_state(frame, next_state, norm));
+        if !ideal_states.iter().any(|next_state| complies(next_state)) {
+            return false;
+        }
+
+        norms.iter().all(|norm| match norm {
+            DeonticFormula::Modal(DeonticOperator::Permission, sub_formula) => ideal_states.iter().any(|next_state| {
+                complies(next_state) && self.evaluate_deontic_formula_at_state(frame, next_state, sub_formula)
+            }),
+            DeonticFormula::Conditional(_, condition) => {
+                let accessible_states = frame.accessibility.get(state).into_iter().flatten();
+                let condition_states = accessible_states
+                    .filter(|next_state| self.evaluate_deontic_formula_at_state(frame, next_state, condition));
+                let best_states = frame.best_states(condition_states);
+                let ideal = best_states.first().is_some_and(|next_state| ideal_states.contains(next_state));
+                // Without ψ-states the obligations given ψ hold vacuously
+                best_states.is_empty()
+                    || best_states.iter().any(|next_state| {
+                        norms.iter().all(|other| match other {
+                            DeonticFormula::Conditional(sub_formula, other_condition) if other_condition == condition => {
+                                self.evaluate_deontic_formula_at_state(frame, next_state, sub_formula)
+                            }
+                            _ => !ideal || self.satisfies_norm_at_state(frame, next_state, other),
+                        })
+                    })
+            }
+            _ => true,
+        })
+    }
+
+    // Whether the ideal alternative `next_state` complies with an obligation or prohibition
+    fn satisfies_norm_at_state(&self, frame: &KripkeFrame, next_state: &str, norm: &DeonticFormula) -> bool {
+        match norm {
+            DeonticFormula::Modal(DeonticOperator::Obligation, sub_formula) => {
+                self.evaluate_deontic_formula_at_state(frame, next_state, sub_formula)
+            }
+            DeonticFormula::Modal(DeonticOperator::Prohibition, sub_formula) => {
+                !self.evaluate_deontic_formula_at_state(frame, next_state, sub_formula)
+            }
+            // Permissions and conditional obligations are checked in norms_compatible_at_state,
+            // and non-normative formulas do not constrain the ideal alternatives
+            _ => true,
+        }
+    }
+}
+
+impl Agent {
+    fn from_name(name: &str) -> Option<Agent> {
+        match name.to_lowercase().as_str() {
+            "agent1" | "a1" | "1" => Some(Agent::Agent1),
+            "agent2" | "a2" | "2" => Some(Agent::Agent2),
+            _ => None,
+        }
+    }
+}
+
+// Formula syntax accepted by the REPL, before it is turned into an Alethic, Epistemic or Deontic formula
+#[derive(Debug, Clone)]
+enum FormulaSyntax {
+    Atom(String),
+    Not(Box<FormulaSyntax>),
+    And(Box<FormulaSyntax>, Box<FormulaSyntax>),
+    Or(Box<FormulaSyntax>, Box<FormulaSyntax>),
+    Implies(Box<FormulaSyntax>, Box<FormulaSyntax>),
+    Modal(ModalOperator, Option<Agent>, Box<FormulaSyntax>),
+    Deontic(DeonticOperator, Box<FormulaSyntax>),
+    Conditional(Box<FormulaSyntax>, Box<FormulaSyntax>), // O(φ / ψ)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Atom(String),
+    Not,
+    And,
+    Or,
+    Implies,
+    Box(Option<String>),
+    Diamond(Option<String>),
+    LeftParen,
+    RightParen,
+    Slash,
+}
+
+fn tokenize_formula(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(Token::LeftParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RightParen);
+                i += 1;
+            }
+            '/' => {
+                tokens.push(Token::Slash);
+                i += 1;
+            }
+            '!' | '~' | '¬' => {
+                tokens.push(Token::Not);
+                i += 1;
+            }
+            '&' | '∧' => {
+                tokens.push(Token::And);
+                i += 1;
+            }
+            '|' | '∨' => {
+                tokens.push(Token::Or);
+                i += 1;
+            }
+            '→' => {
+                tokens.push(Token::Implies);
+                i += 1;
+            }
+            '□' => {
+                tokens.push(Token::Box(None));
+                i += 1;
+            }
+            '◇' => {
+                tokens.push(Token::Diamond(None));
+                i += 1;
+            }
+            '-' if chars.get(i + 1) == Some(&'>') => {
+                tokens.push(Token::Implies);
+                i += 2;
+            }
+            '[' | '<' => {
+                let closing = if c == '[' { ']' } else { '>' };
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == closing)
+                    .map(|offset| i + 1 + offset)
+                    .ok_or_else(|| format!("Missing '{}' for '{}' at position {}", closing, c, i))?;
+                let label: String = chars[i + 1..end].iter().collect::<String>().trim().to_string();
+                let agent = if label.is_empty() { None } else { Some(label) };
+                tokens.push(if c == '[' { Token::Box(agent) } else { Token::Diamond(agent) });
+                i = end + 1;
+            }
+            _ if c.is_alphanumeric() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Atom(chars[start..i].iter().collect()));
+            }
+            _ => return Err(format!("Unexpected character '{}' at position {}", c, i)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+// Recursive descent parser; precedence from loosest to tightest is ->, |, &, then the unary operators
+struct FormulaParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl FormulaParser {
+    fn parse(input: &str) -> Result<FormulaSyntax, String> {
+        let mut parser = FormulaParser {
+            tokens: tokenize_formula(input)?,
+            position: 0,
+        };
+        let formula = parser.parse_implication()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(formula),
+            Some(token) => Err(format!("Unexpected {:?} after the end of the formula", token)),
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn parse_implication(&mut self) -> Result<FormulaSyntax, String> {
+        let left = self.parse_disjunction()?;
+        if self.peek() == Some(&Token::Implies) {
+            self.position += 1;
+            let right = self.parse_implication()?;
+            return Ok(FormulaSyntax::Implies(Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn parse_disjunction(&mut self) -> Result<FormulaSyntax, String> {
+        let mut left = self.parse_conjunction()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            let right = self.parse_conjunction()?;
+            left = FormulaSyntax::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_conjunction(&mut self) -> Result<FormulaSyntax, String> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            let right = self.parse_unary()?;
+            left = FormulaSyntax::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<FormulaSyntax, String> {
+        match self.next_token() {
+            Some(Token::Not) => Ok(FormulaSyntax::Not(Box::new(self.parse_unary()?))),
+            Some(Token::Box(label)) => {
+                let agent = parse_agent_label(label)?;
+                Ok(FormulaSyntax::Modal(ModalOperator::Box, agent, Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Diamond(label)) => {
+                let agent = parse_agent_label(label)?;
+                Ok(FormulaSyntax::Modal(ModalOperator::Diamond, agent, Box::new(self.parse_unary()?)))
+            }
+            // O, P and F followed by a parenthesised formula are the deontic operators, anywhere
+            // else they are atoms
+            Some(Token::Atom(name)) if self.peek() == Some(&Token::LeftParen) && ["O", "P", "F"].contains(&name.as_str()) => {
+                self.position += 1;
+                let sub_formula = Box::new(self.parse_implication()?);
+                let formula = match name.as_str() {
+                    "O" if self.peek() == Some(&Token::Slash) => {
+                        self.position += 1;
+                        FormulaSyntax::Conditional(sub_formula, Box::new(self.parse_implication()?))
+                    }
+                    "O" => FormulaSyntax::Deontic(DeonticOperator::Obligation, sub_formula),
+                    "P" => FormulaSyntax::Deontic(DeonticOperator::Permission, sub_formula),
+                    _ => FormulaSyntax::Deontic(DeonticOperator::Prohibition, sub_formula),
+                };
+                match self.next_token() {
+                    Some(Token::RightParen) => Ok(formula),
+                    _ => Err(format!("Missing ')' after {}(", name)),
+                }
+            }
+            Some(Token::Atom(name)) => Ok(FormulaSyntax::Atom(name)),
+            Some(Token::LeftParen) => {
+                let formula = self.parse_implication()?;
+                match self.next_token() {
+                    Some(Token::RightParen) => Ok(formula),
+                    _ => Err("Missing ')'".to_string()),
+                }
+            }
+            Some(token) => Err(format!("Unexpected {:?}", token)),
+            None => Err("Unexpected end of formula".to_string()),
+        }
+    }
+}
+
+fn parse_agent_label(label: Option<String>) -> Result<Option<Agent>, String> {
+    match label {
+        None => Ok(None),
+        Some(name) => Agent::from_name(&name)
+            .map(Some)
+            .ok_or_else(|| format!("Unknown agent '{}'", name)),
+    }
+}
+
+impl FormulaSyntax {
+    fn agents(&self, agents: &mut HashSet<Agent>) {
+        match self {
+            FormulaSyntax::Atom(_) => {}
+            FormulaSyntax::Not(sub_formula) => sub_formula.agents(agents),
+            FormulaSyntax::And(sub_formula1, sub_formula2)
+            | FormulaSyntax::Or(sub_formula1, sub_formula2)
+            | FormulaSyntax::Implies(sub_formula1, sub_formula2)
+            | FormulaSyntax::Conditional(sub_formula1, sub_formula2) => {
+                sub_formula1.agents(agents);
+                sub_formula2.agents(agents);
+            }
+            FormulaSyntax::Deontic(_, sub_formula) => sub_formula.agents(agents),
+            FormulaSyntax::Modal(_, agent, sub_formula) => {
+                if let Some(agent) = agent {
+                    agents.insert(agent.clone());
+                }
+                sub_formula.agents(agents);
+            }
+        }
+    }
+
+    fn to_alethic(&self) -> Result<AlethicFormula, String> {
+        Ok(match self {
+            FormulaSyntax::Atom(name) => AlethicFormula::Atom(Proposition::Atom(name.clone())),
+            FormulaSyntax::Not(sub_formula) => AlethicFormula::Not(Box::new(sub_formula.to_alethic()?)),
+            FormulaSyntax::And(sub_formula1, sub_formula2) => AlethicFormula::And(
+                Box::new(sub_formula1.to_alethic()?),
+                Box::new(sub_formula2.to_alethic()?),
+            ),
+            FormulaSyntax::Or(sub_formula1, sub_formula2) => AlethicFormula::Or(
+                Box::new(sub_formula1.to_alethic()?),
+                Box::new(sub_formula2.to_alethic()?),
+            ),
+            // φ → ψ is rewritten as ¬φ ∨ ψ
+            FormulaSyntax::Implies(sub_formula1, sub_formula2) => AlethicFormula::Or(
+                Box::new(AlethicFormula::Not(Box::new(sub_formula1.to_alethic()?))),
+                Box::new(sub_formula2.to_alethic()?),
+            ),
+            FormulaSyntax::Modal(operator, _, sub_formula) => {
+                AlethicFormula::Modal(operator.clone(), Box::new(sub_formula.to_alethic()?))
+            }
+            FormulaSyntax::Deontic(..) | FormulaSyntax::Conditional(..) => return Err(MIXED_DEONTIC.to_string()),
+        })
+    }
+
+    // Every modality must name its agent; an unlabelled one would be ambiguous
+    fn to_epistemic(&self) -> Result<EpistemicFormula, String> {
+        Ok(match self {
+            FormulaSyntax::Atom(name) => EpistemicFormula::Atom(Proposition::Atom(name.clone())),
+            FormulaSyntax::Not(sub_formula) => EpistemicFormula::Not(Box::new(sub_formula.to_epistemic()?)),
+            FormulaSyntax::And(sub_formula1, sub_formula2) => EpistemicFormula::And(
+                Box::new(sub_formula1.to_epistemic()?),
+                Box::new(sub_formula2.to_epistemic()?),
+            ),
+            FormulaSyntax::Or(sub_formula1, sub_formula2) => EpistemicFormula::Or(
+                Box::new(sub_formula1.to_epistemic()?),
+                Box::new(sub_formula2.to_epistemic()?),
+            ),
+            FormulaSyntax::Implies(sub_formula1, sub_formula2) => EpistemicFormula::Or(
+                Box::new(EpistemicFormula::Not(Box::new(sub_formula1.to_epistemic()?))),
+                Box::new(sub_formula2.to_epistemic()?),
+            ),
+            FormulaSyntax::Modal(operator, Some(agent), sub_formula) => {
+                EpistemicFormula::Modal(operator.clone(), Box::new(sub_formula.to_epistemic()?), agent.clone())
+            }
+            FormulaSyntax::Modal(..) => {
+                return Err("Label every modality in a formula about an agent, e.g. [agent1]p instead of []p".to_string())
+            }
+            FormulaSyntax::Deontic(..) | FormulaSyntax::Conditional(..) => return Err(MIXED_DEONTIC.to_string()),
+        })
+    }
+
+    fn is_deontic(&self) -> bool {
+        match self {
+            FormulaSyntax::Atom(_) => false,
+            FormulaSyntax::Not(sub_formula) | FormulaSyntax::Modal(_, _, sub_formula) => sub_formula.is_deontic(),
+            FormulaSyntax::And(sub_formula1, sub_formula2)
+            | FormulaSyntax::Or(sub_formula1, sub_formula2)
+            | FormulaSyntax::Implies(sub_formula1, sub_formula2) => sub_formula1.is_deontic() || sub_formula2.is_deontic(),
+            FormulaSyntax::Deontic(..) | FormulaSyntax::Conditional(..) => true,
+        }
+    }
+
+    fn to_deontic(&self) -> Result<DeonticFormula, String> {
+        Ok(match self {
+            FormulaSyntax::Atom(name) => DeonticFormula::Atom(Proposition::Atom(name.clone())),
+            FormulaSyntax::Not(sub_formula) => DeonticFormula::Not(Box::new(sub_formula.to_deontic()?)),
+            FormulaSyntax::And(sub_formula1, sub_formula2) => DeonticFormula::And(
+                Box::new(sub_formula1.to_deontic()?),
+                Box::new(sub_formula2.to_deontic()?),
+            ),
+            FormulaSyntax::Or(sub_formula1, sub_formula2) => DeonticFormula::Or(
+                Box::new(sub_formula1.to_deontic()?),
+                Box::new(sub_formula2.to_deontic()?),
+            ),
+            FormulaSyntax::Implies(sub_formula1, sub_formula2) => DeonticFormula::Or(
+                Box::new(DeonticFormula::Not(Box::new(sub_formula1.to_deontic()?))),
+                Box::new(sub_formula2.to_deontic()?),
+            ),
+            FormulaSyntax::Deontic(operator, sub_formula) => {
+                DeonticFormula::Modal(operator.clone(), Box::new(sub_formula.to_deontic()?))
+            }
+            FormulaSyntax::Conditional(sub_formula, condition) => {
+                DeonticFormula::Conditional(Box::new(sub_formula.to_deontic()?), Box::new(condition.to_deontic()?))
+            }
+            FormulaSyntax::Modal(..) => return Err(MIXED_DEONTIC.to_string()),
+        })
+    }
+}
+
+const MIXED_DEONTIC: &str = "A formula cannot mix O(), P(), F() with [] and <>";
+
+// Model file layout for the `load` command:
+// { "states": { "s1": ["p", "q"], "s2": [] }, "edges": [["s1", "s2"], ["s2", "s1", "agent1"]], "ideality": { "s1": 0 } }
+#[derive(Deserialize)]
+struct ModelFile {
+    states: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    edges: Vec<Vec<String>>,
+    #[serde(default)]
+    ideality: HashMap<String, usize>,
+}
+
+fn load_frame(path: &str) -> Result<KripkeFrame, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path, e))?;
+    let model_file: ModelFile =
+        serde_json::from_str(&content).map_err(|e| format!("Error parsing {}: {}", path, e))?;
+
+    let mut frame = KripkeFrame::new();
+    for (state, atoms) in model_file.states {
+        frame.add_state(&state, &atoms);
+    }
+    for edge in &model_file.edges {
+        match edge.as_slice() {
+            [from, to] => frame.add_edge(from, to, None)?,
+            [from, to, agent] => {
+                let agent = Agent::from_name(agent).ok_or_else(|| format!("Unknown agent '{}'", agent))?;
+                frame.add_edge(from, to, Some(agent))?
+            }
+            _ => return Err(format!("Invalid edge {:?}: expected [from, to] or [from, to, agent]", edge)),
+        }
+    }
+    if let Some(state) = model_file.ideality.keys().find(|state| !frame.states.contains(*state)) {
+        return Err(format!("Unknown state '{}' in ideality", state));
+    }
+    frame.ideality = model_file.ideality;
+    Ok(frame)
+}
+
+impl KripkeFrame {
+    fn add_state(&mut self, state: &str, atoms: &[String]) {
+        self.states.insert(state.to_string());
+        self.valuation
+            .entry(state.to_string())
+            .or_default()
+            .extend(atoms.iter().cloned());
+    }
+
+    fn add_edge(&mut self, from: &str, to: &str, agent: Option<Agent>) -> Result<(), String> {
+        for state in [from, to] {
+            if !self.states.contains(state) {
+                return Err(format!("Unknown state '{}'", state));
+            }
+        }
+
+        let relation = match agent {
+            Some(agent) => self.agent_accessibility.entry(agent).or_default(),
+            None => &mut self.accessibility,
+        };
+        relation.entry(from.to_string()).or_default().insert(to.to_string());
+        Ok(())
+    }
+
+    fn show(&self) {
+        let mut states: Vec<&String> = self.states.iter().collect();
+        states.sort();
+        println!("States:");
+        for state in &states {
+            let mut atoms: Vec<&String> = self.valuation.get(*state).map_or(Vec::new(), |atoms| atoms.iter().collect());
+            atoms.sort();
+            let atoms: Vec<&str> = atoms.iter().map(|atom| atom.as_str()).collect();
+            println!("  {} {{{}}} rank {}", state, atoms.join(", "), self.rank(state));
+        }
+
+        println!("Edges:");
+        print_relation(&self.accessibility, "");
+        for (agent, relation) in &self.agent_accessibility {
+            print_relation(relation, &format!(" [{:?}]", agent));
+        }
+    }
+}
+
+fn print_relation(relation: &HashMap<String, HashSet<String>>, label: &str) {
+    let mut edges: Vec<(&String, &String)> = relation
+        .iter()
+        .flat_map(|(from, targets)| targets.iter().map(move |to| (from, to)))
+        .collect();
+    edges.sort();
+    for (from, to) in edges {
+        println!("  {} -> {}{}", from, to, label);
+    }
+}
+
+impl KripkeModel {
+    // Evaluates a REPL formula at one state, or at every state when `state` is None
+    fn check_formula(&self, state: Option<&str>, formula: &FormulaSyntax) -> Result<bool, String> {
+        let frame = self.frames.first().ok_or("The model has no states yet")?;
+        if let Some(state) = state {
+            if !frame.states.contains(state) {
+                return Err(format!("Unknown state '{}'", state));
+            }
+        }
+
+        let mut agents = HashSet::new();
+        formula.agents(&mut agents);
+        let holds_at = |state: &str| match agents.len() {
+            _ if formula.is_deontic() => Ok(self.evaluate_deontic_formula_at_state(frame, state, &formula.to_deontic()?)),
+            0 => Ok(self.evaluate_alethic_formula_at_state(frame, state, &formula.to_alethic()?)),
+            1 => {
+                let agent = agents.iter().next().unwrap();
+                Ok(self.evaluate_epistemic_formula_at_state(frame, state, &formula.to_epistemic()?, agent))
+            }
+            _ => Err("Formulas may refer to at most one agent".to_string()),
+        };
+
+        match state {
+            Some(state) => holds_at(state),
+            None => {
+                for state in &frame.states {
+                    if !holds_at(state)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+}
+
+fn print_repl_help() {
+    println!("Commands:");
+    println!("  state <name> [atoms...]      add a state and the atoms true at it");
+    println!("  edge <from> <to> [agent]     add an accessibility edge, optionally for one agent");
+    println!("  rank <state> <n>             set the ideality rank of a state (lower is better)");
+    println!("  check <state> <formula>      evaluate a formula at a state");
+    println!("  valid <formula>              check whether a formula holds at every state");
+    println!("  load <file.json>             replace the model with one read from a JSON file");
+    println!("  show                         print the current model");
+    println!("  clear                        remove every state and edge");
+    println!("  help                         show this message");
+    println!("  quit                         leave the REPL");
+    println!("Formulas use atoms, !, &, |, ->, parentheses, []φ and <>φ, or [agent1]φ and <agent1>φ.");
+    println!("A formula about an agent must label all of its modalities.");
+    println!("Deontic formulas use O(φ), P(φ), F(φ) and O(φ / ψ) for φ being obligatory given ψ,");
+    println!("judged against the states of best rank.");
+}
+
+fn run_repl_command(model: &mut KripkeModel, line: &str) -> Result<(), String> {
+    let mut words = line.split_whitespace();
+    let command = match words.next() {
+        Some(command) => command,
+        None => return Ok(()),
+    };
+    let arguments: Vec<&str> = words.collect();
+
+    if model.frames.is_empty() {
+        model.frames.push(KripkeFrame::new());
+    }
+    let frame = &mut model.frames[0];
+
+    match command {
+        "state" => {
+            let (state, atoms) = arguments.split_first().ok_or("Usage: state <name> [atoms...]")?;
+            let atoms: Vec<String> = atoms.iter().map(|atom| atom.to_string()).collect();
+            frame.add_state(state, &atoms);
+        }
+        "edge" => match arguments.as_slice() {
+            [from, to] => frame.add_edge(from, to, None)?,
+            [from, to, agent] => {
+                let agent = Agent::from_name(agent).ok_or_else(|| format!("Unknown agent '{}'", agent))?;
+                frame.add_edge(from, to, Some(agent))?
+            }
+            _ => return Err("Usage: edge <from> <to> [agent]".to_string()),
+        },
+        "rank" => match arguments.as_slice() {
+            [state, rank] => {
+                let rank = rank.parse::<usize>().map_err(|_| format!("Invalid rank '{}'", rank))?;
+                if !frame.states.contains(*state) {
+                    return Err(format!("Unknown state '{}'", state));
+                }
+                frame.ideality.insert(state.to_string(), rank);
+            }
+            _ => return Err("Usage: rank <state> <n>".to_string()),
+        },
+        "check" => {
+            let (state, formula) = arguments.split_first().ok_or("Usage: check <state> <formula>")?;
+            let formula = FormulaParser::parse(&formula.join(" "))?;
+            println!("{}", model.check_formula(Some(*state), &formula)?);
+        }
+        "valid" => {
+            let formula = FormulaParser::parse(&arguments.join(" "))?;
+            println!("{}", model.check_formula(None, &formula)?);
+        }
+        "load" => {
+            let path = arguments.first().ok_or("Usage: load <file.json>")?;
+            *frame = load_frame(path)?;
+            println!("Loaded {} states from {}", frame.states.len(), path);
+        }
+        "show" => frame.show(),
+        "clear" => *frame = KripkeFrame::new(),
+        "help" => print_repl_help(),
+        _ => return Err(format!("Unknown command '{}'. Type 'help' for a list of commands.", command)),
+    }
+    Ok(())
+}
+
+fn run_repl(model: &mut KripkeModel) {
+    println!("Modal logic REPL. Type 'help' for a list of commands.");
+
+    loop {
+        print!("> ");
+        io::stdout().flush().expect("Failed to flush stdout");
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).expect("Failed to read line") == 0 {
+            break;
+        }
+
+        let line = line.trim();
+        if line == "quit" || line == "exit" {
+            break;
+        }
+
+        if let Err(err) = run_repl_command(model, line) {
+            eprintln!("Error: {}", err);
+        }
+    }
 }
 
 fn main() {
-    // Example usage
-    let mut model = KripkeModel::new(); 
+    let mut model = KripkeModel::new();
+
+    // Run the hard-coded example with `--demo`, otherwise start the REPL
+    if env::args().any(|arg| arg == "--demo") {
+        run_demo(&mut model);
+    } else {
+        run_repl(&mut model);
+    }
+}
+
+// φ ↔ ψ, written as (φ ∧ ψ) ∨ (¬φ ∧ ¬ψ)
+fn deontic_equivalence(formula1: DeonticFormula, formula2: DeonticFormula) -> DeonticFormula {
+    DeonticFormula::Or(
+        Box::new(DeonticFormula::And(Box::new(formula1.clone()), Box::new(formula2.clone()))),
+        Box::new(DeonticFormula::And(
+            Box::new(DeonticFormula::Not(Box::new(formula1))),
+            Box::new(DeonticFormula::Not(Box::new(formula2))),
+        )),
+    )
+}
+
+fn run_demo(model: &mut KripkeModel) {
 
     // Define states and accessibility relations for a Kripke frame
     let frame1_states: HashSet<String> = ["s1".to_string(), "s2".to_string()].iter().cloned().collect();
@@ -390,6 +1143,9 @@ fn main() {
     model.frames.push(KripkeFrame {
         states: frame1_states,
         accessibility: frame1_accessibility,
+        ideality: HashMap::new(),
+        valuation: HashMap::new(),
+        agent_accessibility: HashMap::new(),
     });
 
     // Define an Alethic logic formula: ◇(p ∧ q) → (◇p ∧ ◇q)
@@ -439,6 +1195,20 @@ fn main() {
     let result_doxastic = model.evaluate_doxastic_formula(&doxastic_formula, &Agent::Agent1);
     println!("Does the Doxastic logic formula hold in the Kripke model? {}", result_doxastic);
 
+    // Belief distributes over conjunction: B1(p ∧ q) → (B1p ∧ B1q)
+    let belief = |formula: DoxasticFormula| DoxasticFormula::Modal(ModalOperator::Box, Box::new(formula), Agent::Agent1);
+    let p = DoxasticFormula::Atom(Proposition::Atom("p".to_string()));
+    let q = DoxasticFormula::Atom(Proposition::Atom("q".to_string()));
+    let distribution = DoxasticFormula::Or(
+        Box::new(DoxasticFormula::Not(Box::new(belief(DoxasticFormula::And(
+            Box::new(p.clone()),
+            Box::new(q.clone()),
+        ))))),
+        Box::new(DoxasticFormula::And(Box::new(belief(p)), Box::new(belief(q)))),
+    );
+    let result_distribution = model.evaluate_doxastic_formula(&distribution, &Agent::Agent1);
+    println!("Does belief distribute over conjunction? {}", result_distribution);
+
     // Define a Temporal logic formula: ◇Fp
     let temporal_formula = TemporalFormula::Modal(
         TemporalOperator::Future,
@@ -449,13 +1219,75 @@ fn main() {
     let result_temporal = model.evaluate_temporal_formula(&temporal_formula);
     println!("Does the Temporal logic formula hold in the Kripke model? {}", result_temporal);
 
+    // What will happen has the present in its past: (p ∧ Fq) → F(q ∧ Pp)
+    let p = TemporalFormula::Atom(Proposition::Atom("p".to_string()));
+    let q = TemporalFormula::Atom(Proposition::Atom("q".to_string()));
+    let future = |formula: TemporalFormula| TemporalFormula::Modal(TemporalOperator::Future, Box::new(formula));
+    let past = |formula: TemporalFormula| TemporalFormula::Modal(TemporalOperator::Past, Box::new(formula));
+    let interaction = TemporalFormula::Or(
+        Box::new(TemporalFormula::Not(Box::new(TemporalFormula::And(
+            Box::new(p.clone()),
+            Box::new(future(q.clone())),
+        )))),
+        Box::new(future(TemporalFormula::And(Box::new(q), Box::new(past(p))))),
+    );
+    let result_interaction = model.evaluate_temporal_formula(&interaction);
+    println!("Does the future keep the present in its past? {}", result_interaction);
+
     // Define a Deontic logic formula: [O]p → O[p]
     let deontic_formula = DeonticFormula::Modal(
-        DeonticOperator::O,
+        DeonticOperator::Obligation,
         Box::new(DeonticFormula::Atom(Proposition::Atom("p".to_string()))),
     );
 
     // Evaluate the Deontic formula in the Kripke model
     let result_deontic = model.evaluate_deontic_formula(&deontic_formula);
     println!("Does the Deontic logic formula hold in the Kripke model? {}", result_deontic);
+
+    // Define a permission and a prohibition: Pp, Fq
+    let permission_formula = DeonticFormula::Modal(
+        DeonticOperator::Permission,
+        Box::new(DeonticFormula::Atom(Proposition::Atom("p".to_string()))),
+    );
+    let prohibition_formula = DeonticFormula::Modal(
+        DeonticOperator::Prohibition,
+        Box::new(DeonticFormula::Atom(Proposition::Atom("q".to_string()))),
+    );
+    println!("Is p permitted everywhere? {}", model.evaluate_deontic_formula(&permission_formula));
+    println!("Is q prohibited everywhere? {}", model.evaluate_deontic_formula(&prohibition_formula));
+
+    // Define a conditional obligation: O(p|q)
+    let conditional_formula = DeonticFormula::Conditional(
+        Box::new(DeonticFormula::Atom(Proposition::Atom("p".to_string()))),
+        Box::new(DeonticFormula::Atom(Proposition::Atom("q".to_string()))),
+    );
+    println!("Is p obligatory given q? {}", model.evaluate_deontic_formula(&conditional_formula));
+
+    // The operators agree at s2, which has no ideal alternative: Pp ↔ ¬Fp and O(p|q ∨ ¬q) ↔ Op
+    let p = DeonticFormula::Atom(Proposition::Atom("p".to_string()));
+    let q = DeonticFormula::Atom(Proposition::Atom("q".to_string()));
+    let duality = deontic_equivalence(
+        DeonticFormula::Modal(DeonticOperator::Permission, Box::new(p.clone())),
+        DeonticFormula::Not(Box::new(DeonticFormula::Modal(DeonticOperator::Prohibition, Box::new(p.clone())))),
+    );
+    let tautology = DeonticFormula::Or(Box::new(q.clone()), Box::new(DeonticFormula::Not(Box::new(q))));
+    let unconditional = deontic_equivalence(
+        DeonticFormula::Conditional(Box::new(p.clone()), Box::new(tautology)),
+        DeonticFormula::Modal(DeonticOperator::Obligation, Box::new(p)),
+    );
+    println!("Is Pp equivalent to ¬Fp everywhere? {}", model.evaluate_deontic_formula(&duality));
+    println!("Is O(p|q ∨ ¬q) equivalent to Op everywhere? {}", model.evaluate_deontic_formula(&unconditional));
+
+    // Check that the norms are consistent with each other
+    match model.check_deontic_seriality() {
+        Ok(()) => println!("The deontic frames are serial."),
+        Err(states) => println!("No ideal alternative exists at: {}", states.join(", ")),
+    }
+
+    let conflicts = model.find_norm_conflicts(&[deontic_formula, prohibition_formula, conditional_formula]);
+    if conflicts.is_empty() {
+        println!("The norms are consistent.");
+    } else {
+        println!("The norms contradict each other at: {}", conflicts.join(", "));
+    }
 }
@@ -1,191 +1,5873 @@
-use std::fmt::Write;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
 
-#[derive(Debug)]
+use quote::ToTokens;
+use serde::Deserialize;
+use syn::spanned::Spanned;
+
+// Add the following dependencies to your Cargo.toml:
+// syn = { version = "1.0", features = ["full", "extra-traits"] }
+// quote = "1.0"
+// proc-macro2 = { version = "1.0", features = ["span-locations"] }
+// serde = { version = "1.0", features = ["derive"] }
+// toml = "0.8"
+
+#[derive(Debug, Clone)]
+struct RustParameter {
+    pattern: syn::Pat,
+    ty: syn::Type,
+}
+
+// How a method takes `self`; associated functions and free functions have none
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Receiver {
+    Shared,
+    Mutable,
+    Owned,
+}
+
+impl Receiver {
+    fn index(self) -> usize {
+        match self {
+            Receiver::Shared => 0,
+            Receiver::Mutable => 1,
+            Receiver::Owned => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 struct RustFunction {
     name: String,
-    parameters: Vec<(String, String)>,
-    return_type: String,
-    body: String,
+    receiver: Option<Receiver>,
+    parameters: Vec<RustParameter>,
+    // `()` for functions without a declared return type
+    return_type: syn::Type,
+    body: syn::Block,
 }
 
 impl RustFunction {
-    fn new(name: &str, parameters: Vec<(&str, &str)>, return_type: &str, body: &str) -> Self {
-        let parameters = parameters
-            .into_iter()
-            .map(|(n, t)| (n.to_string(), t.to_string()))
-            .collect(); 
-        RustFunction {
+    fn from_item(item: &syn::ItemFn) -> Result<Self, Unsupported> {
+        RustFunction::from_signature(&item.sig, &item.block, false)
+    }
+
+    fn from_method(method: &syn::ImplItemMethod) -> Result<Self, Unsupported> {
+        RustFunction::from_signature(&method.sig, &method.block, true)
+    }
+
+    // Everything the converters cannot express yet is reported instead of being dropped
+    fn from_signature(sig: &syn::Signature, block: &syn::Block, in_impl: bool) -> Result<Self, Unsupported> {
+        let name = &sig.ident;
+
+        if let Some(constness) = &sig.constness {
+            return Err(Unsupported::at(constness, format!("`const fn {}` is not supported", name)));
+        }
+        if let Some(asyncness) = &sig.asyncness {
+            return Err(Unsupported::at(asyncness, format!("`async fn {}` is not supported", name)));
+        }
+        if let Some(unsafety) = &sig.unsafety {
+            return Err(Unsupported::at(unsafety, format!("`unsafe fn {}` is not supported", name)));
+        }
+        if let Some(abi) = &sig.abi {
+            return Err(Unsupported::at(abi, format!("`extern` function {} is not supported", name)));
+        }
+        if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+            return Err(Unsupported::at(&sig.generics, format!("generic function {} is not supported", name)));
+        }
+        if let Some(variadic) = &sig.variadic {
+            return Err(Unsupported::at(variadic, format!("variadic function {} is not supported", name)));
+        }
+
+        let mut receiver = None;
+        let mut parameters = Vec::new();
+        for input in &sig.inputs {
+            match input {
+                syn::FnArg::Typed(typed) => {
+                    match &*typed.pat {
+                        syn::Pat::Ident(pattern) if pattern.by_ref.is_none() && pattern.subpat.is_none() => {}
+                        pattern => {
+                            return Err(Unsupported::at(
+                                pattern,
+                                format!("parameter pattern `{}` of {} is not supported, use a plain name", tokens_to_string(pattern), name),
+                            ))
+                        }
+                    }
+                    parameters.push(RustParameter {
+                        pattern: (*typed.pat).clone(),
+                        ty: (*typed.ty).clone(),
+                    });
+                }
+                syn::FnArg::Receiver(self_parameter) if in_impl => {
+                    receiver = Some(match (&self_parameter.reference, &self_parameter.mutability) {
+                        (None, _) => Receiver::Owned,
+                        (Some(_), Some(_)) => Receiver::Mutable,
+                        (Some(_), None) => Receiver::Shared,
+                    });
+                }
+                syn::FnArg::Receiver(self_parameter) => {
+                    return Err(Unsupported::at(self_parameter, format!("`self` parameter of {} outside an impl block", name)))
+                }
+            }
+        }
+
+        let return_type = match &sig.output {
+            syn::ReturnType::Default => syn::parse_quote!(()),
+            syn::ReturnType::Type(_, ty) => (**ty).clone(),
+        };
+
+        Ok(RustFunction {
             name: name.to_string(),
+            receiver,
+            parameters,
+            return_type,
+            body: block.clone(),
+        })
+    }
+
+    fn parameter_name(parameter: &RustParameter) -> String {
+        match &parameter.pattern {
+            syn::Pat::Ident(pattern) => pattern.ident.to_string(),
+            pattern => tokens_to_string(pattern),
+        }
+    }
+
+    fn is_mutable(parameter: &RustParameter) -> bool {
+        matches!(&parameter.pattern, syn::Pat::Ident(pattern) if pattern.mutability.is_some())
+    }
+
+    // Converts the signature types and the body into the representation the converters print
+    fn lower(&self) -> Result<IrFunction, Unsupported> {
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|parameter| {
+                let ty = RustType::from_syn(&parameter.ty).map_err(|e| Unsupported::at(&parameter.ty, e))?;
+                Ok((RustFunction::parameter_name(parameter), ty))
+            })
+            .collect::<Result<Vec<(String, RustType)>, Unsupported>>()?;
+        let return_type = RustType::from_syn(&self.return_type).map_err(|e| Unsupported::at(&self.return_type, e))?;
+        let mut body = lower_block(&self.body, return_type.name != "()")?;
+        // Parameters are immutable in some targets, so `mut` ones get a local copy
+        let copies = self.parameters.iter().filter(|parameter| RustFunction::is_mutable(parameter)).map(|parameter| {
+            let name = RustFunction::parameter_name(parameter);
+            Stmt::Let {
+                name: name.clone(),
+                mutable: true,
+                ty: None,
+                value: Expr::Variable(name),
+            }
+        });
+        body.splice(0..0, copies);
+
+        Ok(IrFunction {
+            name: self.name.clone(),
+            receiver: self.receiver,
             parameters,
-            return_type: return_type.to_string(),
-            body: body.to_string(),
+            return_type,
+            body,
+        })
+    }
+}
+
+fn tokens_to_string(tokens: &impl ToTokens) -> String {
+    tokens.to_token_stream().to_string()
+}
+
+// A construct that could not be imported or lowered, with its 1-based position
+#[derive(Debug)]
+struct Unsupported {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl Unsupported {
+    fn at(node: &impl Spanned, message: String) -> Self {
+        Unsupported::at_span(node.span(), message)
+    }
+
+    // Needs proc-macro2's "span-locations" feature for real line numbers
+    fn at_span(span: proc_macro2::Span, message: String) -> Self {
+        let start = span.start();
+        Unsupported {
+            line: start.line,
+            column: start.column + 1,
+            message,
         }
     }
 }
 
-trait LanguageConverter {
-    fn convert(&self, rust_function: &RustFunction) -> Result<String, String>;
-    fn details(&self) -> &'static LanguageDetails;    
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
 }
 
-trait LanguageDetails {
-    fn name(&self) -> &'static str;
-    fn file_extension(&self) -> &'static str;    
+// Fields of structs and enum variants; positional fields are named `value0`, `value1`, ...
+fn import_fields(fields: &syn::Fields) -> Result<Vec<(String, RustType)>, Unsupported> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let name = field.ident.as_ref().map_or_else(|| format!("value{}", index), |ident| ident.to_string());
+            let ty = RustType::from_syn(&field.ty).map_err(|e| Unsupported::at(&field.ty, e))?;
+            Ok((name, ty))
+        })
+        .collect()
 }
 
-struct CodeConverter<'a> {
-    language_converters: Vec<Box<dyn LanguageConverter + 'a>>,    
+#[derive(Debug, Clone)]
+struct RustStruct {
+    name: String,
+    fields: Vec<(String, RustType)>,
+    // Methods and associated functions of the struct's impl blocks
+    methods: Vec<IrFunction>,
 }
 
-impl<'a> CodeConverter<'a> {
-    fn new() -> Self {
-        CodeConverter {
-            language_converters: vec![
-                Box::new(JavaConverter {}),
-                Box::new(CppConverter {}),
-                Box::new(KotlinConverter {}),
-                Box::new(PythonConverter {}),
-                Box::new(RustConverter {}),
-                Box::new(GoConverter {}),
-                Box::new(RubyConverter {}),
-                Box::new(SwiftConverter {}),
-                Box::new(CSharpConverter {}),
-                // Add other converters here
-            ],    
+impl RustStruct {
+    fn from_item(item: &syn::ItemStruct) -> Result<Self, Unsupported> {
+        if !item.generics.params.is_empty() {
+            return Err(Unsupported::at(&item.generics, format!("generic struct {} is not supported", item.ident)));
+        }
+        Ok(RustStruct {
+            name: item.ident.to_string(),
+            fields: import_fields(&item.fields)?,
+            methods: Vec::new(),
+        })
+    }
+
+    // Targets without mutable records declare the struct as a class instead
+    fn is_mutable(&self) -> bool {
+        self.methods.iter().any(|method| method.receiver == Some(Receiver::Mutable))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RustVariant {
+    name: String,
+    fields: Vec<(String, RustType)>,
+}
+
+#[derive(Debug, Clone)]
+struct RustEnum {
+    name: String,
+    variants: Vec<RustVariant>,
+    methods: Vec<IrFunction>,
+}
+
+impl RustEnum {
+    fn from_item(item: &syn::ItemEnum) -> Result<Self, Unsupported> {
+        if !item.generics.params.is_empty() {
+            return Err(Unsupported::at(&item.generics, format!("generic enum {} is not supported", item.ident)));
+        }
+        let variants = item
+            .variants
+            .iter()
+            .map(|variant| {
+                if let Some((_, discriminant)) = &variant.discriminant {
+                    return Err(Unsupported::at(
+                        discriminant,
+                        format!("explicit discriminant of {}::{} is not supported", item.ident, variant.ident),
+                    ));
+                }
+                Ok(RustVariant {
+                    name: variant.ident.to_string(),
+                    fields: import_fields(&variant.fields)?,
+                })
+            })
+            .collect::<Result<Vec<RustVariant>, Unsupported>>()?;
+        Ok(RustEnum {
+            name: item.ident.to_string(),
+            variants,
+            methods: Vec::new(),
+        })
+    }
+
+    // Enums without data become the target's plain enum type where it has one
+    fn is_plain(&self) -> bool {
+        self.variants.iter().all(|variant| variant.fields.is_empty())
+    }
+}
+
+// Everything translated from one source file
+#[derive(Debug, Default)]
+struct Module {
+    structs: Vec<RustStruct>,
+    enums: Vec<RustEnum>,
+    functions: Vec<IrFunction>,
+}
+
+impl Module {
+    fn find_struct(&self, name: &str) -> Option<&RustStruct> {
+        self.structs.iter().find(|item| item.name == name)
+    }
+
+    fn find_enum(&self, name: &str) -> Option<&RustEnum> {
+        self.enums.iter().find(|item| item.name == name)
+    }
+
+    fn type_names(&self) -> HashSet<String> {
+        self.structs
+            .iter()
+            .map(|item| item.name.clone())
+            .chain(self.enums.iter().map(|item| item.name.clone()))
+            .collect()
+    }
+}
+
+// The items of a Rust source file and the constructs that were left out
+struct ImportedFile {
+    module: Module,
+    unsupported: Vec<Unsupported>,
+}
+
+fn import_rust_file(path: &Path) -> Result<ImportedFile, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    import_rust_source(&source).map_err(|e| format!("{}:{}", path.display(), e))
+}
+
+fn import_rust_source(source: &str) -> Result<ImportedFile, Unsupported> {
+    let file = syn::parse_file(source).map_err(|e| Unsupported::at_span(e.span(), e.to_string()))?;
+
+    let mut imported = ImportedFile {
+        module: Module::default(),
+        unsupported: Vec::new(),
+    };
+    let mut impl_blocks = Vec::new();
+    for item in &file.items {
+        let kind = match item {
+            syn::Item::Fn(function) => {
+                match RustFunction::from_item(function).and_then(|function| function.lower()) {
+                    Ok(function) => imported.module.functions.push(function),
+                    Err(unsupported) => imported.unsupported.push(unsupported),
+                }
+                continue;
+            }
+            syn::Item::Struct(item) => {
+                match RustStruct::from_item(item) {
+                    Ok(item) => imported.module.structs.push(item),
+                    Err(unsupported) => imported.unsupported.push(unsupported),
+                }
+                continue;
+            }
+            syn::Item::Enum(item) => {
+                match RustEnum::from_item(item) {
+                    Ok(item) => imported.module.enums.push(item),
+                    Err(unsupported) => imported.unsupported.push(unsupported),
+                }
+                continue;
+            }
+            // Methods are attached once all types are known
+            syn::Item::Impl(item) => {
+                impl_blocks.push(item);
+                continue;
+            }
+            syn::Item::Use(_) => "`use` declaration",
+            syn::Item::Trait(_) => "trait",
+            syn::Item::Const(_) => "constant",
+            syn::Item::Static(_) => "static",
+            syn::Item::Mod(_) => "module",
+            syn::Item::Type(_) => "type alias",
+            syn::Item::Macro(_) => "macro invocation",
+            _ => "item",
+        };
+        imported
+            .unsupported
+            .push(Unsupported::at(item, format!("{} is not supported and was skipped", kind)));
+    }
+
+    for block in impl_blocks {
+        if let Err(unsupported) = import_impl_block(block, &mut imported) {
+            imported.unsupported.push(unsupported);
+        }
+    }
+    Ok(imported)
+}
+
+fn import_impl_block(block: &syn::ItemImpl, imported: &mut ImportedFile) -> Result<(), Unsupported> {
+    if let Some((_, path, _)) = &block.trait_ {
+        return Err(Unsupported::at(
+            block,
+            format!("implementation of trait `{}` is not supported and was skipped", tokens_to_string(path)),
+        ));
+    }
+    if !block.generics.params.is_empty() {
+        return Err(Unsupported::at(&block.generics, "generic impl block is not supported".to_owned()));
+    }
+    let type_name = match &*block.self_ty {
+        syn::Type::Path(path) if path.qself.is_none() && path.path.get_ident().is_some() => tokens_to_string(&path.path),
+        other => return Err(Unsupported::at(other, format!("impl block for `{}` is not supported", tokens_to_string(other)))),
+    };
+
+    let mut methods = Vec::new();
+    for item in &block.items {
+        let method = match item {
+            syn::ImplItem::Method(method) => method,
+            syn::ImplItem::Const(_) => {
+                imported.unsupported.push(Unsupported::at(item, "associated constant is not supported and was skipped".to_owned()));
+                continue;
+            }
+            _ => {
+                imported.unsupported.push(Unsupported::at(item, "impl item is not supported and was skipped".to_owned()));
+                continue;
+            }
+        };
+        match RustFunction::from_method(method).and_then(|method| method.lower()) {
+            Ok(method) => methods.push(method),
+            Err(unsupported) => imported.unsupported.push(unsupported),
+        }
+    }
+
+    let module = &mut imported.module;
+    if let Some(item) = module.structs.iter_mut().find(|item| item.name == type_name) {
+        item.methods.extend(methods);
+    } else if let Some(item) = module.enums.iter_mut().find(|item| item.name == type_name) {
+        item.methods.extend(methods);
+    } else {
+        return Err(Unsupported::at(&block.self_ty, format!("impl block for unknown type {}", type_name)));
+    }
+    Ok(())
+}
+
+// Function bodies in a small language-neutral form that every converter prints from.
+// Rust code outside of it is reported as Unsupported when lowering.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+        }
+    }
+
+    // Higher binds tighter; the order is the same in all targets
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 10,
+            BinaryOp::And => 20,
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 30,
+            BinaryOp::Add | BinaryOp::Sub => 40,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 50,
+        }
+    }
+
+    // Comparisons do not chain in Rust, and chain differently in Python
+    fn is_comparison(self) -> bool {
+        self.precedence() == 30
+    }
+
+    fn is_arithmetic(self) -> bool {
+        self.precedence() > 30
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    // Literals keep their decimal digits, without suffixes such as `u8`
+    Int(String),
+    Float(String),
+    Bool(bool),
+    Str(String),
+    Variable(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Cast(Box<Expr>, RustType),
+    Call(String, Vec<Expr>),
+    SelfValue,
+    Field(Box<Expr>, String),
+    MethodCall(Box<Expr>, String, Vec<Expr>),
+    // `Type::name(...)`: an associated function, or a tuple variant of an enum
+    StaticCall(String, String, Vec<Expr>),
+    // `Type::Name`: a unit variant of an enum
+    Associated(String, String),
+    // `Type { field: value }`, or `Enum::Variant { ... }` with a variant
+    Construct {
+        type_name: String,
+        variant: Option<String>,
+        fields: Vec<(String, Expr)>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Stmt {
+    // `ty` is only set when the source spells it out
+    Let {
+        name: String,
+        mutable: bool,
+        ty: Option<RustType>,
+        value: Expr,
+    },
+    // `target` is a Variable or a Field; `op` is set for compound assignments such as `x += 1`
+    Assign {
+        target: Expr,
+        op: Option<BinaryOp>,
+        value: Expr,
+    },
+    Expr(Expr),
+    // `else if` chains are an If as the only statement of `else_body`
+    If {
+        condition: Expr,
+        then_body: Vec<Stmt>,
+        else_body: Vec<Stmt>,
+    },
+    While {
+        condition: Expr,
+        body: Vec<Stmt>,
+    },
+    ForRange {
+        variable: String,
+        start: Expr,
+        end: Expr,
+        inclusive: bool,
+        body: Vec<Stmt>,
+    },
+    Break,
+    Continue,
+    Return(Option<Expr>),
+}
+
+#[derive(Debug, Clone)]
+struct IrFunction {
+    name: String,
+    receiver: Option<Receiver>,
+    parameters: Vec<(String, RustType)>,
+    return_type: RustType,
+    body: Vec<Stmt>,
+}
+
+// With `returns_value` the final expression of the block becomes a return statement
+fn lower_block(block: &syn::Block, returns_value: bool) -> Result<Vec<Stmt>, Unsupported> {
+    let mut statements = Vec::new();
+    for (index, stmt) in block.stmts.iter().enumerate() {
+        match stmt {
+            syn::Stmt::Local(local) => statements.push(lower_local(local)?),
+            syn::Stmt::Item(item) => {
+                return Err(Unsupported::at(item, "items inside function bodies are not supported".to_owned()))
+            }
+            syn::Stmt::Semi(expr, _) => lower_statement(expr, false, &mut statements)?,
+            syn::Stmt::Expr(expr) => {
+                let is_result = returns_value && index + 1 == block.stmts.len();
+                lower_statement(expr, is_result, &mut statements)?
+            }
+        }
+    }
+    Ok(statements)
+}
+
+fn lower_local(local: &syn::Local) -> Result<Stmt, Unsupported> {
+    let (pattern, ty) = match &local.pat {
+        syn::Pat::Type(typed) => {
+            let ty = RustType::from_syn(&typed.ty).map_err(|e| Unsupported::at(&typed.ty, e))?;
+            (&*typed.pat, Some(ty))
+        }
+        pattern => (pattern, None),
+    };
+    let (name, mutable) = match pattern {
+        syn::Pat::Ident(ident) if ident.by_ref.is_none() && ident.subpat.is_none() => {
+            (ident.ident.to_string(), ident.mutability.is_some())
+        }
+        pattern => {
+            return Err(Unsupported::at(
+                pattern,
+                format!("`let` pattern `{}` is not supported, use a plain name", tokens_to_string(pattern)),
+            ))
+        }
+    };
+    let value = match &local.init {
+        Some((_, init)) => lower_expr(init)?,
+        None => return Err(Unsupported::at(local, format!("`let {}` without a value is not supported", name))),
+    };
+    Ok(Stmt::Let {
+        name,
+        mutable,
+        ty,
+        value,
+    })
+}
+
+fn lower_statement(expr: &syn::Expr, is_result: bool, statements: &mut Vec<Stmt>) -> Result<(), Unsupported> {
+    let statement = match expr {
+        syn::Expr::If(expr_if) => lower_if(expr_if, is_result)?,
+        syn::Expr::Block(block) if block.label.is_none() => {
+            statements.extend(lower_block(&block.block, is_result)?);
+            return Ok(());
+        }
+        syn::Expr::While(expr_while) if expr_while.label.is_none() => Stmt::While {
+            condition: lower_expr(&expr_while.cond)?,
+            body: lower_block(&expr_while.body, false)?,
+        },
+        syn::Expr::Loop(expr_loop) if expr_loop.label.is_none() => Stmt::While {
+            condition: Expr::Bool(true),
+            body: lower_block(&expr_loop.body, false)?,
+        },
+        syn::Expr::ForLoop(for_loop) if for_loop.label.is_none() => lower_for(for_loop)?,
+        syn::Expr::Assign(assign) => Stmt::Assign {
+            target: lower_place(&assign.left)?,
+            op: None,
+            value: lower_expr(&assign.right)?,
+        },
+        syn::Expr::AssignOp(assign) => {
+            let op = match assign.op {
+                syn::BinOp::AddEq(_) => BinaryOp::Add,
+                syn::BinOp::SubEq(_) => BinaryOp::Sub,
+                syn::BinOp::MulEq(_) => BinaryOp::Mul,
+                syn::BinOp::DivEq(_) => BinaryOp::Div,
+                syn::BinOp::RemEq(_) => BinaryOp::Rem,
+                op => {
+                    return Err(Unsupported::at(
+                        &op,
+                        format!("operator `{}` is not supported", tokens_to_string(&op)),
+                    ))
+                }
+            };
+            Stmt::Assign {
+                target: lower_place(&assign.left)?,
+                op: Some(op),
+                value: lower_expr(&assign.right)?,
+            }
+        }
+        syn::Expr::Return(expr_return) => {
+            Stmt::Return(expr_return.expr.as_deref().map(lower_expr).transpose()?)
+        }
+        syn::Expr::Break(expr_break) if expr_break.label.is_none() && expr_break.expr.is_none() => Stmt::Break,
+        syn::Expr::Continue(expr_continue) if expr_continue.label.is_none() => Stmt::Continue,
+        syn::Expr::While(_) | syn::Expr::Loop(_) | syn::Expr::ForLoop(_) | syn::Expr::Block(_)
+        | syn::Expr::Break(_) | syn::Expr::Continue(_) => {
+            return Err(Unsupported::at(expr, "labels and `break` with a value are not supported".to_owned()))
+        }
+        expr if is_result => Stmt::Return(Some(lower_expr(expr)?)),
+        expr => Stmt::Expr(lower_expr(expr)?),
+    };
+    statements.push(statement);
+    Ok(())
+}
+
+fn lower_if(expr_if: &syn::ExprIf, is_result: bool) -> Result<Stmt, Unsupported> {
+    let else_body = match &expr_if.else_branch {
+        None => Vec::new(),
+        Some((_, else_branch)) => match &**else_branch {
+            syn::Expr::If(nested) => vec![lower_if(nested, is_result)?],
+            syn::Expr::Block(block) => lower_block(&block.block, is_result)?,
+            other => return Err(Unsupported::at(other, "unexpected `else` branch".to_owned())),
+        },
+    };
+    Ok(Stmt::If {
+        condition: lower_expr(&expr_if.cond)?,
+        then_body: lower_block(&expr_if.then_branch, is_result)?,
+        else_body,
+    })
+}
+
+fn lower_for(for_loop: &syn::ExprForLoop) -> Result<Stmt, Unsupported> {
+    let variable = match &for_loop.pat {
+        syn::Pat::Ident(ident) if ident.by_ref.is_none() && ident.subpat.is_none() => ident.ident.to_string(),
+        pattern => {
+            return Err(Unsupported::at(
+                pattern,
+                format!("loop pattern `{}` is not supported, use a plain name", tokens_to_string(pattern)),
+            ))
+        }
+    };
+    let mut iterated = &*for_loop.expr;
+    while let syn::Expr::Paren(paren) = iterated {
+        iterated = &paren.expr;
+    }
+    let (start, end, inclusive) = match iterated {
+        syn::Expr::Range(syn::ExprRange {
+            from: Some(from),
+            to: Some(to),
+            limits,
+            ..
+        }) => (from, to, matches!(limits, syn::RangeLimits::Closed(_))),
+        other => {
+            return Err(Unsupported::at(
+                other,
+                "only `for` loops over bounded ranges such as `0..n` are supported".to_owned(),
+            ))
         }
+    };
+    Ok(Stmt::ForRange {
+        variable,
+        start: lower_expr(start)?,
+        end: lower_expr(end)?,
+        inclusive,
+        body: lower_block(&for_loop.body, false)?,
+    })
+}
+
+fn lower_place(target: &syn::Expr) -> Result<Expr, Unsupported> {
+    match lower_expr(target)? {
+        place @ (Expr::Variable(_) | Expr::Field(_, _)) => Ok(place),
+        _ => Err(Unsupported::at(
+            target,
+            format!("assignment to `{}` is not supported, only to variables and fields", tokens_to_string(target)),
+        )),
+    }
+}
+
+// `Type::name` paths as type and item name; `Self` is resolved by the emitter
+fn associated_path(path: &syn::ExprPath) -> Option<(String, String)> {
+    let segments = &path.path.segments;
+    if path.qself.is_some() || path.path.leading_colon.is_some() || segments.len() != 2 {
+        return None;
+    }
+    if segments.iter().any(|segment| !segment.arguments.is_empty()) {
+        return None;
     }
+    Some((segments[0].ident.to_string(), segments[1].ident.to_string()))
+}
 
-    fn convert_to_languages(&self, rust_functions: &[RustFunction]) {
-        for function in rust_functions {
-            for converter in &self.language_converters {
-                match converter.convert(function) {
-                    Ok(result) => println!("Converted {}:\n{}", converter.details().name(), result),
-                    Err(err) => eprintln!(
-                        "Error converting {} to {}: {}",
-                        function.name,
-                        converter.details().name(),
-                        err
-                    ),
+fn lower_expr(expr: &syn::Expr) -> Result<Expr, Unsupported> {
+    match expr {
+        syn::Expr::Lit(literal) => match &literal.lit {
+            syn::Lit::Int(int) => Ok(Expr::Int(int.base10_digits().to_owned())),
+            syn::Lit::Float(float) => Ok(Expr::Float(float.base10_digits().to_owned())),
+            syn::Lit::Bool(boolean) => Ok(Expr::Bool(boolean.value)),
+            syn::Lit::Str(string) => Ok(Expr::Str(string.value())),
+            other => Err(Unsupported::at(other, format!("literal `{}` is not supported", tokens_to_string(other)))),
+        },
+        syn::Expr::Path(path) if path.path.is_ident("self") => Ok(Expr::SelfValue),
+        syn::Expr::Path(path) if path.qself.is_none() && path.path.get_ident().is_some() => {
+            Ok(Expr::Variable(tokens_to_string(&path.path)))
+        }
+        syn::Expr::Path(path) => match associated_path(path) {
+            Some((type_name, name)) => Ok(Expr::Associated(type_name, name)),
+            None => Err(Unsupported::at(path, format!("path `{}` is not supported", tokens_to_string(path)))),
+        },
+        syn::Expr::Field(field) => {
+            let name = match &field.member {
+                syn::Member::Named(ident) => ident.to_string(),
+                syn::Member::Unnamed(index) => format!("value{}", index.index),
+            };
+            Ok(Expr::Field(Box::new(lower_expr(&field.base)?), name))
+        }
+        syn::Expr::Paren(paren) => lower_expr(&paren.expr),
+        syn::Expr::Group(group) => lower_expr(&group.expr),
+        // Borrowing has no counterpart in the targets
+        syn::Expr::Reference(reference) if reference.mutability.is_some() => Err(Unsupported::at(
+            reference,
+            format!("mutable borrow `{}` is not supported", tokens_to_string(reference)),
+        )),
+        syn::Expr::Reference(reference) => lower_expr(&reference.expr),
+        syn::Expr::Unary(unary) => match unary.op {
+            syn::UnOp::Neg(_) => Ok(Expr::Unary(UnaryOp::Neg, Box::new(lower_expr(&unary.expr)?))),
+            syn::UnOp::Not(_) => Ok(Expr::Unary(UnaryOp::Not, Box::new(lower_expr(&unary.expr)?))),
+            syn::UnOp::Deref(_) => Err(Unsupported::at(
+                unary,
+                format!("dereference `{}` is not supported", tokens_to_string(unary)),
+            )),
+        },
+        syn::Expr::Binary(binary) => {
+            let op = match binary.op {
+                syn::BinOp::Add(_) => BinaryOp::Add,
+                syn::BinOp::Sub(_) => BinaryOp::Sub,
+                syn::BinOp::Mul(_) => BinaryOp::Mul,
+                syn::BinOp::Div(_) => BinaryOp::Div,
+                syn::BinOp::Rem(_) => BinaryOp::Rem,
+                syn::BinOp::Eq(_) => BinaryOp::Eq,
+                syn::BinOp::Ne(_) => BinaryOp::Ne,
+                syn::BinOp::Lt(_) => BinaryOp::Lt,
+                syn::BinOp::Le(_) => BinaryOp::Le,
+                syn::BinOp::Gt(_) => BinaryOp::Gt,
+                syn::BinOp::Ge(_) => BinaryOp::Ge,
+                syn::BinOp::And(_) => BinaryOp::And,
+                syn::BinOp::Or(_) => BinaryOp::Or,
+                op => {
+                    return Err(Unsupported::at(
+                        &op,
+                        format!("operator `{}` is not supported", tokens_to_string(&op)),
+                    ))
+                }
+            };
+            Ok(Expr::Binary(op, Box::new(lower_expr(&binary.left)?), Box::new(lower_expr(&binary.right)?)))
+        }
+        syn::Expr::Cast(cast) => {
+            let ty = RustType::from_syn(&cast.ty).map_err(|e| Unsupported::at(&cast.ty, e))?;
+            Ok(Expr::Cast(Box::new(lower_expr(&cast.expr)?), ty))
+        }
+        syn::Expr::Call(call) => {
+            let arguments = call.args.iter().map(lower_expr).collect::<Result<Vec<Expr>, Unsupported>>()?;
+            let callee = match &*call.func {
+                syn::Expr::Path(path) if path.qself.is_none() && path.path.get_ident().is_some() => {
+                    return Ok(Expr::Call(tokens_to_string(&path.path), arguments))
                 }
+                syn::Expr::Path(path) => associated_path(path),
+                _ => None,
+            };
+            match callee {
+                Some((type_name, name)) => Ok(Expr::StaticCall(type_name, name, arguments)),
+                None => Err(Unsupported::at(
+                    &call.func,
+                    format!("call of `{}` is not supported, only of functions by name", tokens_to_string(&call.func)),
+                )),
+            }
+        }
+        syn::Expr::MethodCall(call) => {
+            if let Some(turbofish) = &call.turbofish {
+                return Err(Unsupported::at(turbofish, format!("type arguments of `.{}()` are not supported", call.method)));
             }
-        }    
+            let arguments = call.args.iter().map(lower_expr).collect::<Result<Vec<Expr>, Unsupported>>()?;
+            Ok(Expr::MethodCall(Box::new(lower_expr(&call.receiver)?), call.method.to_string(), arguments))
+        }
+        syn::Expr::Struct(literal) => {
+            if let Some(rest) = &literal.rest {
+                return Err(Unsupported::at(rest, "struct update syntax `..` is not supported".to_owned()));
+            }
+            let segments: Vec<String> = literal.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+            let (type_name, variant) = match segments.as_slice() {
+                [type_name] => (type_name.clone(), None),
+                [type_name, variant] => (type_name.clone(), Some(variant.clone())),
+                _ => {
+                    return Err(Unsupported::at(
+                        &literal.path,
+                        format!("struct path `{}` is not supported", tokens_to_string(&literal.path)),
+                    ))
+                }
+            };
+            let fields = literal
+                .fields
+                .iter()
+                .map(|field| {
+                    let name = match &field.member {
+                        syn::Member::Named(ident) => ident.to_string(),
+                        syn::Member::Unnamed(index) => format!("value{}", index.index),
+                    };
+                    Ok((name, lower_expr(&field.expr)?))
+                })
+                .collect::<Result<Vec<(String, Expr)>, Unsupported>>()?;
+            Ok(Expr::Construct {
+                type_name,
+                variant,
+                fields,
+            })
+        }
+        syn::Expr::Macro(invocation) => Err(Unsupported::at(
+            invocation,
+            format!("macro `{}!` is not supported", tokens_to_string(&invocation.mac.path)),
+        )),
+        syn::Expr::If(_) | syn::Expr::Block(_) | syn::Expr::Match(_) | syn::Expr::Loop(_) => Err(Unsupported::at(
+            expr,
+            "`if`, `match` and block expressions are only supported as statements or as the function result".to_owned(),
+        )),
+        other => Err(Unsupported::at(other, format!("expression `{}` is not supported", tokens_to_string(other)))),
     }
 }
 
-macro_rules! language_converter {
-    ($struct_name:ident, $lang_name:expr, $ext:expr, $format_code:expr) => {
-        struct $struct_name;
+// Python front end: typed `def` functions of a Python subset, lowered into the same IR so
+// that every converter, RustConverter included, can print them
+
+#[derive(Debug, Clone, PartialEq)]
+enum PyTokenKind {
+    Name(String),
+    Int(String),
+    Float(String),
+    Str(String),
+    // Operators and punctuation such as `+=`, `->`, `(` and `:`
+    Symbol(&'static str),
+    Newline,
+    Indent,
+    Dedent,
+    End,
+}
+
+#[derive(Debug, Clone)]
+struct PyToken {
+    kind: PyTokenKind,
+    line: usize,
+    column: usize,
+}
+
+// Longest first, so `//=` is not read as `//` and `=`
+const PYTHON_SYMBOLS: &[&str] = &[
+    "**=", "//=", "->", "**", "//", "==", "!=", "<=", ">=", "+=", "-=", "*=", "/=", "%=", "+", "-", "*", "/", "%", "<", ">",
+    "=", "(", ")", "[", "]", "{", "}", ",", ":", ".", "|", "@", ";",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+    "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal", "not", "or",
+    "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+fn tokenize_python(source: &str) -> Result<Vec<PyToken>, Unsupported> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens: Vec<PyToken> = Vec::new();
+    let mut indents = vec![0];
+    // Newlines and indentation inside brackets do not count
+    let mut brackets = 0;
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut at_line_start = true;
+    let mut i = 0;
+
+    let error = |line: usize, column: usize, message: String| Unsupported { line, column, message };
+    let token = |kind: PyTokenKind, line: usize, column: usize| PyToken { kind, line, column };
 
-        impl LanguageDetails for $struct_name {
-            fn name(&self) -> &'static str {
-                $lang_name
+    while i < chars.len() {
+        let column = i - line_start + 1;
+        if at_line_start {
+            at_line_start = false;
+            if brackets == 0 {
+                let mut width = 0;
+                let mut end = i;
+                while end < chars.len() && matches!(chars[end], ' ' | '\t') {
+                    width = if chars[end] == '\t' { width / 8 * 8 + 8 } else { width + 1 };
+                    end += 1;
+                }
+                // Blank and comment lines do not change the indentation
+                let blank = end >= chars.len() || matches!(chars[end], '\n' | '\r' | '#');
+                if !blank {
+                    let column = end - line_start + 1;
+                    if width > *indents.last().unwrap_or(&0) {
+                        indents.push(width);
+                        tokens.push(token(PyTokenKind::Indent, line, column));
+                    }
+                    while width < *indents.last().unwrap_or(&0) {
+                        indents.pop();
+                        tokens.push(token(PyTokenKind::Dedent, line, column));
+                    }
+                    if width != *indents.last().unwrap_or(&0) {
+                        return Err(error(line, column, "indentation does not match any outer block".to_owned()));
+                    }
+                }
+                i = end;
+                continue;
             }
+        }
 
-            fn file_extension(&self) -> &'static str {
-                $ext
+        let c = chars[i];
+        match c {
+            '\n' => {
+                if brackets == 0 && !matches!(tokens.last().map(|t| &t.kind), None | Some(PyTokenKind::Newline)) {
+                    tokens.push(token(PyTokenKind::Newline, line, column));
+                }
+                i += 1;
+                line += 1;
+                line_start = i;
+                at_line_start = true;
+            }
+            ' ' | '\t' | '\r' => i += 1,
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '\\' if chars.get(i + 1) == Some(&'\n') => {
+                i += 2;
+                line += 1;
+                line_start = i;
+            }
+            '"' | '\'' => {
+                let triple = chars.get(i + 1) == Some(&c) && chars.get(i + 2) == Some(&c);
+                let (start_line, start_column) = (line, column);
+                i += if triple { 3 } else { 1 };
+                let mut text = String::new();
+                loop {
+                    let Some(&next) = chars.get(i) else {
+                        return Err(error(start_line, start_column, "unterminated string literal".to_owned()));
+                    };
+                    if next == c && (!triple || (chars.get(i + 1) == Some(&c) && chars.get(i + 2) == Some(&c))) {
+                        i += if triple { 3 } else { 1 };
+                        break;
+                    }
+                    match next {
+                        '\\' => {
+                            let escaped = match chars.get(i + 1) {
+                                Some('n') => '\n',
+                                Some('t') => '\t',
+                                Some('r') => '\r',
+                                Some('0') => '\0',
+                                Some(&other @ ('\\' | '\'' | '"')) => other,
+                                _ => {
+                                    return Err(error(line, i - line_start + 1, "unsupported escape sequence in string".to_owned()))
+                                }
+                            };
+                            text.push(escaped);
+                            i += 2;
+                        }
+                        '\n' if !triple => {
+                            return Err(error(start_line, start_column, "unterminated string literal".to_owned()));
+                        }
+                        '\n' => {
+                            text.push('\n');
+                            i += 1;
+                            line += 1;
+                            line_start = i;
+                        }
+                        other => {
+                            text.push(other);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(token(PyTokenKind::Str(text), start_line, start_column));
+            }
+            c if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|next| next.is_ascii_digit())) => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                    // Exponents may carry a sign
+                    if matches!(chars[i], 'e' | 'E') && matches!(chars.get(i + 1), Some('+' | '-')) && !chars[start..i].contains(&'x') {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().filter(|&&digit| digit != '_').collect();
+                let lower = text.to_ascii_lowercase();
+                let radix = match lower.get(..2) {
+                    Some("0x") => Some(16),
+                    Some("0o") => Some(8),
+                    Some("0b") => Some(2),
+                    _ => None,
+                };
+                let kind = if let Some(radix) = radix {
+                    let value = i64::from_str_radix(&lower[2..], radix)
+                        .map_err(|_| error(line, column, format!("invalid number `{}`", text)))?;
+                    PyTokenKind::Int(value.to_string())
+                } else if lower.ends_with('j') {
+                    return Err(error(line, column, format!("complex number `{}` is not supported", text)));
+                } else if lower.contains(['.', 'e']) {
+                    lower.parse::<f64>().map_err(|_| error(line, column, format!("invalid number `{}`", text)))?;
+                    // `1.` and `.5` are not literals in every target
+                    let mut digits = lower.replace(".e", ".0e");
+                    if digits.starts_with('.') {
+                        digits.insert(0, '0');
+                    }
+                    if digits.ends_with('.') {
+                        digits.push('0');
+                    }
+                    PyTokenKind::Float(digits)
+                } else {
+                    let value = lower
+                        .parse::<i64>()
+                        .map_err(|_| error(line, column, format!("integer `{}` does not fit in 64 bits", text)))?;
+                    PyTokenKind::Int(value.to_string())
+                };
+                tokens.push(token(kind, line, column));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let name: String = chars[start..i].iter().collect();
+                if matches!(chars.get(i), Some('"' | '\'')) {
+                    return Err(error(line, column, format!("string prefix `{}` is not supported", name)));
+                }
+                tokens.push(token(PyTokenKind::Name(name), line, column));
+            }
+            _ => {
+                let symbol = PYTHON_SYMBOLS
+                    .iter()
+                    .find(|symbol| symbol.chars().enumerate().all(|(offset, expected)| chars.get(i + offset) == Some(&expected)))
+                    .ok_or_else(|| error(line, column, format!("unexpected character `{}`", c)))?;
+                match *symbol {
+                    "(" | "[" | "{" => brackets += 1,
+                    ")" | "]" | "}" => brackets -= 1,
+                    _ => {}
+                }
+                i += symbol.len();
+                tokens.push(token(PyTokenKind::Symbol(symbol), line, column));
             }
         }
+    }
 
-        impl LanguageConverter for $struct_name {
-            fn convert(&self, rust_function: &RustFunction) -> Result<String, String> {
-                let mut code = String::new();
-                write!(
-                    code,
-                    $format_code,
-                    rust_function.return_type,
-                    rust_function.name,
-                    format_parameters(rust_function),
-                    rust_function.body
-                )
-                .map_err(|e| format!("Error formatting {} code: {}", $lang_name, e))?;
-                Ok(code)    
-            }
-        }
-
-        fn details(&self) -> &'static LanguageDetails {
-                self
-        }    
-    };
-}
-
-language_converter!(
-    JavaConverter,
-    "Java",
-    "java",
-    "public class Main {{\n  public static {} {}({}) {{\n    return {};\n  }}\n}}"
-);
-
-language_converter!(
-    CppConverter,
-    "C++",
-    "cpp",
-    "{} {}({}) {{\n  return {};\n}}"
-);
-
-language_converter!(
-    KotlinConverter,
-    "Kotlin",
-    "kt",
-    "fun {}({}): {} {{\n  return {};\n}}"
-);
-
-language_converter!(
-    PythonConverter,
-    "Python",
-    "py",
-    "def {}({}) -> {}:\n  return {}"
-);
-
-language_converter!(
-    RustConverter,
-    "Rust",
-    "rs",
-    "fn {}({}) -> {} {{\n  {}\n}}"
-);
-
-language_converter!(
-    GoConverter,
-    "Go",
-    "go",
-    "package main\n\nimport \"fmt\"\n\nfunc {}({}) {} {{\n  return {}\n}}"
-);
-
-language_converter!(
-    RubyConverter,
-    "Ruby",
-    "rb",
-    "def {}({})\n  {}\nend"
-);
-
-language_converter!(
-    SwiftConverter,
-    "Swift",
-    "swift",
-    "func {}({}) -> {} {{\n  return {}\n}}"
-);
-
-language_converter!(
-    CSharpConverter,
-    "C#",
-    "cs",
-    "using System;\n\npublic class Program {{\n  public static {} {}({}) {{\n    return {};\n}}}}"
-);
-
-fn format_parameters(rust_function: &RustFunction) -> String {
-    rust_function
-        .parameters
-        .iter()
-        .map(|(name, ty)| format!("{} {}", ty, name))
-        .collect::<Vec<String>>()
-        .join(", ")    
+    let column = i - line_start + 1;
+    if !matches!(tokens.last().map(|t| &t.kind), None | Some(PyTokenKind::Newline)) {
+        tokens.push(token(PyTokenKind::Newline, line, column));
+    }
+    for _ in 1..indents.len() {
+        tokens.push(token(PyTokenKind::Dedent, line, column));
+    }
+    tokens.push(token(PyTokenKind::End, line, column));
+    Ok(tokens)
 }
 
-fn main() {
-    let rust_functions = vec![
-        RustFunction::new("add", vec![("a", "i32"), ("b", "i32")], "i32", "a + b"),
-        RustFunction::new("multiply", vec![("a", "i32"), ("b", "i32")], "i32", "a * b"),
-    ];  
+// Python statements without a counterpart in the function IR
+const PYTHON_UNSUPPORTED_STATEMENTS: &[&str] = &[
+    "assert", "async", "class", "def", "del", "global", "import", "from", "lambda", "nonlocal", "raise", "try", "with", "yield",
+];
+
+fn import_python_file(path: &Path) -> Result<ImportedFile, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    import_python_source(&source).map_err(|e| format!("{}:{}", path.display(), e))
+}
+
+// Top-level functions are translated; anything else, and any function using more than the
+// subset, is reported and skipped as a whole
+fn import_python_source(source: &str) -> Result<ImportedFile, Unsupported> {
+    let mut parser = PythonParser::new(tokenize_python(source)?);
+    let mut imported = ImportedFile {
+        module: Module::default(),
+        unsupported: Vec::new(),
+    };
+    // Where each function starts, for what PythonArithmetic reports
+    let mut starts = HashMap::new();
+    while parser.peek().kind != PyTokenKind::End {
+        let start = parser.peek().clone();
+        let result = match &start.kind {
+            PyTokenKind::Name(name) if name == "def" => parser.function().map(Some),
+            // Module docstrings
+            PyTokenKind::Str(_) => parser.skip_statement().map(|_| None),
+            PyTokenKind::Symbol("@") => {
+                parser.skip_statement()?;
+                if parser.is_keyword("def") {
+                    parser.skip_statement()?;
+                }
+                Err(PythonParser::error_at(&start, "decorated function is not supported and was skipped".to_owned()))
+            }
+            PyTokenKind::Name(name) if name == "import" || name == "from" => {
+                parser.skip_statement()?;
+                Err(PythonParser::error_at(&start, "import is not supported and was skipped".to_owned()))
+            }
+            PyTokenKind::Name(name) if name == "class" => {
+                parser.skip_statement()?;
+                Err(PythonParser::error_at(&start, "class is not supported and was skipped".to_owned()))
+            }
+            _ => {
+                parser.skip_statement()?;
+                Err(PythonParser::error_at(&start, "top-level statement is not supported and was skipped".to_owned()))
+            }
+        };
+        match result {
+            Ok(Some(function)) => {
+                starts.insert(function.name.clone(), start);
+                imported.module.functions.push(function);
+            }
+            Ok(None) => {}
+            Err(unsupported) => {
+                imported.unsupported.push(unsupported);
+                parser.skip_to_top_level();
+            }
+        }
+    }
+    for (name, message) in PythonArithmetic::apply(&mut imported.module) {
+        if let Some(start) = starts.get(&name) {
+            let message = format!("function {} was skipped: it {}", name, message);
+            imported.unsupported.push(PythonParser::error_at(start, message));
+        }
+    }
+    imported.unsupported.sort_by_key(|unsupported| (unsupported.line, unsupported.column));
+    Ok(imported)
+}
+
+// Recursive descent over the token stream, lowering while parsing. Python variables live
+// for the whole function, the IR's only for their block, so a variable first assigned in
+// a block and read after it is reported rather than silently hoisted.
+struct PythonParser {
+    tokens: Vec<PyToken>,
+    // Indentation depth at each token, for skipping to the next top-level statement
+    depths: Vec<usize>,
+    position: usize,
+    scopes: Vec<HashSet<String>>,
+    ended: HashSet<String>,
+    // Variables assigned after their declaration, which makes them mutable
+    reassigned: HashSet<String>,
+}
+
+impl PythonParser {
+    fn new(tokens: Vec<PyToken>) -> Self {
+        let mut depth = 0;
+        let depths = tokens
+            .iter()
+            .map(|token| {
+                let current = depth;
+                match token.kind {
+                    PyTokenKind::Indent => depth += 1,
+                    PyTokenKind::Dedent => depth -= 1,
+                    _ => {}
+                }
+                current
+            })
+            .collect();
+        PythonParser {
+            tokens,
+            depths,
+            position: 0,
+            scopes: Vec::new(),
+            ended: HashSet::new(),
+            reassigned: HashSet::new(),
+        }
+    }
+
+    fn peek(&self) -> &PyToken {
+        &self.tokens[self.position.min(self.tokens.len() - 1)]
+    }
+
+    fn peek_at(&self, offset: usize) -> &PyTokenKind {
+        &self.tokens[(self.position + offset).min(self.tokens.len() - 1)].kind
+    }
+
+    fn advance(&mut self) -> PyToken {
+        let token = self.peek().clone();
+        if token.kind != PyTokenKind::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error_at(token: &PyToken, message: String) -> Unsupported {
+        Unsupported {
+            line: token.line,
+            column: token.column,
+            message,
+        }
+    }
+
+    fn error(&self, message: String) -> Unsupported {
+        PythonParser::error_at(self.peek(), message)
+    }
+
+    fn describe(kind: &PyTokenKind) -> String {
+        match kind {
+            PyTokenKind::Name(name) => format!("`{}`", name),
+            PyTokenKind::Int(digits) | PyTokenKind::Float(digits) => format!("`{}`", digits),
+            PyTokenKind::Str(_) => "a string".to_owned(),
+            PyTokenKind::Symbol(symbol) => format!("`{}`", symbol),
+            PyTokenKind::Newline => "the end of the line".to_owned(),
+            PyTokenKind::Indent => "an indented block".to_owned(),
+            PyTokenKind::Dedent => "the end of the block".to_owned(),
+            PyTokenKind::End => "the end of the file".to_owned(),
+        }
+    }
 
-    let code_converter = CodeConverter::new();
-    code_converter.convert_to_languages(&rust_functions);
+    fn unexpected(&self, expected: &str) -> Unsupported {
+        self.error(format!("expected {}, found {}", expected, PythonParser::describe(&self.peek().kind)))
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek().kind, PyTokenKind::Symbol(found) if found == symbol)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.peek().kind, PyTokenKind::Name(name) if name == keyword)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = self.is_symbol(symbol);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), Unsupported> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", symbol)))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), Unsupported> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", keyword)))
+        }
+    }
+
+    fn expect_name(&mut self, what: &str) -> Result<String, Unsupported> {
+        match &self.peek().kind {
+            PyTokenKind::Name(name) if !PYTHON_KEYWORDS.contains(&name.as_str()) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            _ => Err(self.unexpected(what)),
+        }
+    }
+
+    fn end_of_statement(&mut self) -> Result<(), Unsupported> {
+        if self.is_symbol(";") {
+            return Err(self.error("several statements on one line are not supported".to_owned()));
+        }
+        match self.peek().kind {
+            PyTokenKind::Newline => {
+                self.advance();
+                Ok(())
+            }
+            PyTokenKind::End | PyTokenKind::Dedent => Ok(()),
+            _ => Err(self.unexpected("the end of the statement")),
+        }
+    }
+
+    // Skips one top-level statement, including its indented block
+    fn skip_statement(&mut self) -> Result<(), Unsupported> {
+        self.advance();
+        self.skip_to_top_level();
+        Ok(())
+    }
+
+    fn skip_to_top_level(&mut self) {
+        while self.peek().kind != PyTokenKind::End {
+            let starts_statement = self.depths[self.position] == 0
+                && !matches!(self.peek().kind, PyTokenKind::Newline | PyTokenKind::Indent | PyTokenKind::Dedent)
+                && matches!(self.tokens[self.position - 1].kind, PyTokenKind::Newline | PyTokenKind::Dedent);
+            if starts_statement {
+                break;
+            }
+            self.advance();
+        }
+    }
+
+    fn is_declared(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(name))
+    }
+
+    fn declare(&mut self, name: &str) {
+        self.ended.remove(name);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned());
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashSet::new());
+    }
+
+    fn pop_scope(&mut self) {
+        if let Some(scope) = self.scopes.pop() {
+            for name in scope {
+                if !self.is_declared(&name) {
+                    self.ended.insert(name);
+                }
+            }
+        }
+    }
+
+    // `def name(parameter: type, ...) -> type:` and its body
+    fn function(&mut self) -> Result<IrFunction, Unsupported> {
+        self.expect_keyword("def")?;
+        let name = self.expect_name("a function name")?;
+        if FLOOR_FUNCTIONS.contains(&name.as_str()) {
+            return Err(self.error(format!("the name {} is taken by the translation of `//` and `%`", name)));
+        }
+        self.expect_symbol("(")?;
+        let mut parameters = Vec::new();
+        while !self.is_symbol(")") {
+            if self.is_symbol("*") || self.is_symbol("**") || self.is_symbol("/") {
+                return Err(self.error(format!("variadic and positional-only parameters of {} are not supported", name)));
+            }
+            let parameter = self.expect_name("a parameter name")?;
+            if !self.eat_symbol(":") {
+                return Err(self.error(format!("parameter {} of {} needs a type annotation", parameter, name)));
+            }
+            let ty = self.annotation()?;
+            if self.is_symbol("=") {
+                return Err(self.error(format!("default value of parameter {} is not supported", parameter)));
+            }
+            parameters.push((parameter, ty));
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        self.expect_symbol(")")?;
+        if !self.eat_symbol("->") {
+            return Err(self.error(format!("function {} needs a return annotation such as `-> None`", name)));
+        }
+        let return_type = self.annotation()?;
+
+        self.scopes = vec![parameters.iter().map(|(parameter, _)| parameter.clone()).collect()];
+        self.ended.clear();
+        self.reassigned.clear();
+        let mut body = self.block()?;
+        // A leading docstring documents the function and is not code
+        if let Some(Stmt::Expr(Expr::Str(_))) = body.first() {
+            body.remove(0);
+        }
+        // Parameters are immutable in some targets, so reassigned ones get a local copy
+        let copies = parameters.iter().filter(|(parameter, _)| self.reassigned.contains(parameter)).map(|(parameter, _)| Stmt::Let {
+            name: parameter.clone(),
+            mutable: true,
+            ty: None,
+            value: Expr::Variable(parameter.clone()),
+        });
+        body.splice(0..0, copies);
+        mark_mutable(&mut body, &self.reassigned);
+
+        Ok(IrFunction {
+            name,
+            receiver: None,
+            parameters,
+            return_type,
+            body,
+        })
+    }
+
+    // Python annotations as Rust types: `int` is a 64-bit integer, `float` a double
+    fn annotation(&mut self) -> Result<RustType, Unsupported> {
+        let first = self.annotation_term()?;
+        if !self.eat_symbol("|") {
+            return Ok(first);
+        }
+        let second = self.annotation_term()?;
+        match (first, second) {
+            (ty, none) | (none, ty) if none.name == "()" && ty.name != "()" => Ok(RustType {
+                name: "Option".to_owned(),
+                arguments: vec![ty],
+            }),
+            _ => Err(self.error("union types other than `T | None` are not supported".to_owned())),
+        }
+    }
+
+    fn annotation_term(&mut self) -> Result<RustType, Unsupported> {
+        let token = self.peek().clone();
+        let name = match &token.kind {
+            PyTokenKind::Name(name) => name.clone(),
+            _ => return Err(self.unexpected("a type")),
+        };
+        self.advance();
+        let mut arguments = Vec::new();
+        if self.eat_symbol("[") {
+            while !self.is_symbol("]") {
+                arguments.push(self.annotation()?);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+            self.expect_symbol("]")?;
+        }
+        let (rust_name, arity) = match name.as_str() {
+            "int" => ("i64", Some(0)),
+            "float" => ("f64", Some(0)),
+            "bool" => ("bool", Some(0)),
+            "str" => ("String", Some(0)),
+            "None" => ("()", Some(0)),
+            "list" | "List" => ("Vec", Some(1)),
+            "dict" | "Dict" => ("HashMap", Some(2)),
+            "Optional" => ("Option", Some(1)),
+            "tuple" | "Tuple" => ("tuple", None),
+            _ => return Err(PythonParser::error_at(&token, format!("type `{}` is not supported", name))),
+        };
+        if arity.map_or(arguments.is_empty(), |arity| arguments.len() != arity) {
+            return Err(PythonParser::error_at(&token, format!("wrong number of type arguments for `{}`", name)));
+        }
+        Ok(RustType {
+            name: rust_name.to_owned(),
+            arguments,
+        })
+    }
+
+    // `:` and an indented block, or simple statements on the same line
+    fn block(&mut self) -> Result<Vec<Stmt>, Unsupported> {
+        self.expect_symbol(":")?;
+        self.push_scope();
+        let result = self.block_statements();
+        self.pop_scope();
+        result
+    }
+
+    fn block_statements(&mut self) -> Result<Vec<Stmt>, Unsupported> {
+        let mut statements = Vec::new();
+        if self.peek().kind != PyTokenKind::Newline {
+            self.simple_statement(&mut statements)?;
+            return Ok(statements);
+        }
+        self.advance();
+        if self.peek().kind != PyTokenKind::Indent {
+            return Err(self.unexpected("an indented block"));
+        }
+        self.advance();
+        while !matches!(self.peek().kind, PyTokenKind::Dedent | PyTokenKind::End) {
+            self.statement(&mut statements)?;
+        }
+        self.advance();
+        Ok(statements)
+    }
+
+    fn statement(&mut self, statements: &mut Vec<Stmt>) -> Result<(), Unsupported> {
+        let keyword = match &self.peek().kind {
+            PyTokenKind::Name(name) => name.clone(),
+            _ => String::new(),
+        };
+        match keyword.as_str() {
+            "if" => {
+                self.advance();
+                statements.push(self.if_statement()?);
+            }
+            "while" => {
+                self.advance();
+                let condition = self.expression()?;
+                let body = self.block()?;
+                self.reject_loop_else()?;
+                statements.push(Stmt::While { condition, body });
+            }
+            "for" => {
+                self.advance();
+                statements.push(self.for_statement()?);
+            }
+            _ => self.simple_statement(statements)?,
+        }
+        Ok(())
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, Unsupported> {
+        let condition = self.expression()?;
+        let then_body = self.block()?;
+        let else_body = if self.eat_keyword("elif") {
+            vec![self.if_statement()?]
+        } else if self.eat_keyword("else") {
+            self.block()?
+        } else {
+            Vec::new()
+        };
+        Ok(Stmt::If {
+            condition,
+            then_body,
+            else_body,
+        })
+    }
+
+    // Only `for name in range(...)` with a step of 1
+    fn for_statement(&mut self) -> Result<Stmt, Unsupported> {
+        let variable = self.expect_name("a loop variable")?;
+        self.expect_keyword("in")?;
+        if !self.eat_keyword("range") {
+            return Err(self.error("only `for` loops over `range(...)` are supported".to_owned()));
+        }
+        self.expect_symbol("(")?;
+        let mut arguments = Vec::new();
+        while !self.is_symbol(")") {
+            arguments.push(self.expression()?);
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        self.expect_symbol(")")?;
+        let (start, end) = match arguments.len() {
+            1 => (Expr::Int("0".to_owned()), arguments.remove(0)),
+            2 | 3 if arguments.get(2).is_none_or(|step| *step == Expr::Int("1".to_owned())) => {
+                let start = arguments.remove(0);
+                (start, arguments.remove(0))
+            }
+            3 => return Err(self.error("`range` steps other than 1 are not supported".to_owned())),
+            _ => return Err(self.error("`range` takes one to three arguments".to_owned())),
+        };
+
+        self.expect_symbol(":")?;
+        self.push_scope();
+        self.declare(&variable);
+        let body = self.block_statements();
+        self.pop_scope();
+        let body = body?;
+        self.reject_loop_else()?;
+        Ok(Stmt::ForRange {
+            variable,
+            start,
+            end,
+            inclusive: false,
+            body,
+        })
+    }
+
+    fn reject_loop_else(&self) -> Result<(), Unsupported> {
+        if self.is_keyword("else") {
+            return Err(self.error("`else` after a loop is not supported".to_owned()));
+        }
+        Ok(())
+    }
+
+    fn simple_statement(&mut self, statements: &mut Vec<Stmt>) -> Result<(), Unsupported> {
+        let start = self.peek().clone();
+        if let PyTokenKind::Name(keyword) = &start.kind {
+            if PYTHON_UNSUPPORTED_STATEMENTS.contains(&keyword.as_str()) {
+                return Err(self.error(format!("`{}` is not supported", keyword)));
+            }
+            match keyword.as_str() {
+                "pass" => {
+                    self.advance();
+                    return self.end_of_statement();
+                }
+                "break" | "continue" => {
+                    self.advance();
+                    statements.push(if keyword == "break" { Stmt::Break } else { Stmt::Continue });
+                    return self.end_of_statement();
+                }
+                "return" => {
+                    self.advance();
+                    let value = match self.peek().kind {
+                        PyTokenKind::Newline | PyTokenKind::End | PyTokenKind::Dedent => None,
+                        _ => Some(self.expression()?),
+                    };
+                    statements.push(Stmt::Return(value));
+                    return self.end_of_statement();
+                }
+                _ => {}
+            }
+        }
+
+        // Assignments are told apart before the target is read as an expression
+        let target = match (&start.kind, self.peek_at(1)) {
+            (PyTokenKind::Name(name), PyTokenKind::Symbol("=" | ":" | "+=" | "-=" | "*=" | "/=" | "//=" | "%=" | "**="))
+                if !PYTHON_KEYWORDS.contains(&name.as_str()) =>
+            {
+                Some(name.clone())
+            }
+            _ => None,
+        };
+        let Some(name) = target else {
+            let value = self.expression()?;
+            if self.is_symbol("=") {
+                return Err(self.error("assignment is only supported to plain variables".to_owned()));
+            }
+            statements.push(Stmt::Expr(value));
+            return self.end_of_statement();
+        };
+        self.advance();
+
+        let operator = self.advance();
+        // `//=` and `%=` assign the result of a call, see term
+        let (op, floor) = match operator.kind {
+            PyTokenKind::Symbol("=") | PyTokenKind::Symbol(":") => (None, None),
+            PyTokenKind::Symbol("+=") => (Some(BinaryOp::Add), None),
+            PyTokenKind::Symbol("-=") => (Some(BinaryOp::Sub), None),
+            PyTokenKind::Symbol("*=") => (Some(BinaryOp::Mul), None),
+            PyTokenKind::Symbol("/=") => (Some(BinaryOp::Div), None),
+            PyTokenKind::Symbol("//=") => (None, Some(FLOOR_DIVISION)),
+            PyTokenKind::Symbol("%=") => (None, Some(FLOOR_REMAINDER)),
+            _ => return Err(PythonParser::error_at(&operator, "operator `**=` is not supported".to_owned())),
+        };
+        let ty = if operator.kind == PyTokenKind::Symbol(":") {
+            let ty = self.annotation()?;
+            self.expect_symbol("=")?;
+            Some(ty)
+        } else {
+            None
+        };
+        let value = self.expression()?;
+        if self.is_symbol("=") {
+            return Err(self.error("chained assignment is not supported".to_owned()));
+        }
+        let value = match floor {
+            Some(function) => Expr::Call(function.to_owned(), vec![Expr::Variable(name.clone()), value]),
+            None => value,
+        };
+
+        if op.is_some() || floor.is_some() || self.is_declared(&name) {
+            if !self.is_declared(&name) {
+                return Err(PythonParser::error_at(&start, format!("variable {} is updated before it is assigned", name)));
+            }
+            self.reassigned.insert(name.clone());
+            statements.push(Stmt::Assign {
+                target: Expr::Variable(name),
+                op,
+                value,
+            });
+        } else {
+            self.declare(&name);
+            statements.push(Stmt::Let {
+                name,
+                mutable: false,
+                ty,
+                value,
+            });
+        }
+        self.end_of_statement()
+    }
+
+    fn expression(&mut self) -> Result<Expr, Unsupported> {
+        let value = self.or_expression()?;
+        if self.is_keyword("if") {
+            return Err(self.error("conditional expressions are not supported".to_owned()));
+        }
+        Ok(value)
+    }
+
+    fn or_expression(&mut self) -> Result<Expr, Unsupported> {
+        let mut left = self.and_expression()?;
+        while self.eat_keyword("or") {
+            let right = self.and_expression()?;
+            left = Expr::Binary(BinaryOp::Or, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn and_expression(&mut self) -> Result<Expr, Unsupported> {
+        let mut left = self.not_expression()?;
+        while self.eat_keyword("and") {
+            let right = self.not_expression()?;
+            left = Expr::Binary(BinaryOp::And, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn not_expression(&mut self) -> Result<Expr, Unsupported> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(self.not_expression()?)));
+        }
+        self.comparison()
+    }
+
+    // `a < b < c` means `a < b and b < c`, which needs `b` to be safe to repeat
+    fn comparison(&mut self) -> Result<Expr, Unsupported> {
+        let mut operands = vec![self.sum()?];
+        let mut operators = Vec::new();
+        loop {
+            let op = match self.peek().kind {
+                PyTokenKind::Symbol("==") => BinaryOp::Eq,
+                PyTokenKind::Symbol("!=") => BinaryOp::Ne,
+                PyTokenKind::Symbol("<") => BinaryOp::Lt,
+                PyTokenKind::Symbol("<=") => BinaryOp::Le,
+                PyTokenKind::Symbol(">") => BinaryOp::Gt,
+                PyTokenKind::Symbol(">=") => BinaryOp::Ge,
+                _ if self.is_keyword("is") || self.is_keyword("in") || self.is_keyword("not") => {
+                    return Err(self.error("`is` and `in` comparisons are not supported".to_owned()))
+                }
+                _ => break,
+            };
+            self.advance();
+            operators.push(op);
+            operands.push(self.sum()?);
+        }
+
+        let mut comparisons = Vec::new();
+        for (index, op) in operators.into_iter().enumerate() {
+            let left = operands[index].clone();
+            if index > 0 && !matches!(left, Expr::Variable(_) | Expr::Int(_) | Expr::Float(_)) {
+                return Err(self.error("chained comparisons are only supported through variables and numbers".to_owned()));
+            }
+            comparisons.push(Expr::Binary(op, Box::new(left), Box::new(operands[index + 1].clone())));
+        }
+        let mut comparisons = comparisons.into_iter();
+        let Some(first) = comparisons.next() else {
+            return Ok(operands.remove(0));
+        };
+        Ok(comparisons.fold(first, |left, right| Expr::Binary(BinaryOp::And, Box::new(left), Box::new(right))))
+    }
+
+    fn sum(&mut self) -> Result<Expr, Unsupported> {
+        let mut left = self.term()?;
+        loop {
+            let op = match self.peek().kind {
+                PyTokenKind::Symbol("+") => BinaryOp::Add,
+                PyTokenKind::Symbol("-") => BinaryOp::Sub,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.term()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    // `//` and `%` floor, where the targets round toward zero, so they become calls of
+    // FLOOR_FUNCTIONS; `/` always divides as floating point
+    fn term(&mut self) -> Result<Expr, Unsupported> {
+        let mut left = self.factor()?;
+        loop {
+            let op = match self.peek().kind {
+                PyTokenKind::Symbol("*") => BinaryOp::Mul,
+                PyTokenKind::Symbol(symbol @ ("//" | "%")) => {
+                    self.advance();
+                    let right = self.factor()?;
+                    let function = if symbol == "//" { FLOOR_DIVISION } else { FLOOR_REMAINDER };
+                    left = Expr::Call(function.to_owned(), vec![left, right]);
+                    continue;
+                }
+                PyTokenKind::Symbol("/") => {
+                    self.advance();
+                    let right = self.factor()?;
+                    left = Expr::Binary(BinaryOp::Div, Box::new(as_float(left)), Box::new(as_float(right)));
+                    continue;
+                }
+                PyTokenKind::Symbol("@") => return Err(self.error("operator `@` is not supported".to_owned())),
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.factor()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn factor(&mut self) -> Result<Expr, Unsupported> {
+        if self.eat_symbol("-") {
+            return Ok(Expr::Unary(UnaryOp::Neg, Box::new(self.factor()?)));
+        }
+        if self.eat_symbol("+") {
+            return self.factor();
+        }
+        let value = self.primary()?;
+        if self.is_symbol("**") {
+            return Err(self.error("operator `**` is not supported".to_owned()));
+        }
+        Ok(value)
+    }
+
+    fn primary(&mut self) -> Result<Expr, Unsupported> {
+        let token = self.advance();
+        let value = match token.kind.clone() {
+            PyTokenKind::Int(digits) => Expr::Int(digits),
+            PyTokenKind::Float(digits) => Expr::Float(digits),
+            PyTokenKind::Str(text) => Expr::Str(text),
+            PyTokenKind::Symbol("(") => {
+                let value = self.expression()?;
+                if self.is_symbol(",") {
+                    return Err(self.error("tuples are not supported".to_owned()));
+                }
+                self.expect_symbol(")")?;
+                value
+            }
+            PyTokenKind::Name(name) if name == "True" || name == "False" => Expr::Bool(name == "True"),
+            PyTokenKind::Name(name) if self.is_symbol("(") => self.call(&token, name)?,
+            PyTokenKind::Name(name) if !PYTHON_KEYWORDS.contains(&name.as_str()) => {
+                if !self.is_declared(&name) && self.ended.contains(&name) {
+                    return Err(PythonParser::error_at(
+                        &token,
+                        format!("variable {} is read outside the block that assigns it; assign it before the block", name),
+                    ));
+                }
+                Expr::Variable(name)
+            }
+            kind => {
+                return Err(PythonParser::error_at(
+                    &token,
+                    format!("expected an expression, found {}", PythonParser::describe(&kind)),
+                ))
+            }
+        };
+        match self.peek().kind {
+            PyTokenKind::Symbol("[") => Err(self.error("indexing is not supported".to_owned())),
+            PyTokenKind::Symbol(".") => Err(self.error("attribute access is not supported".to_owned())),
+            _ => Ok(value),
+        }
+    }
+
+    // Calls of functions by name; `int(...)` and `float(...)` become casts
+    fn call(&mut self, token: &PyToken, name: String) -> Result<Expr, Unsupported> {
+        self.expect_symbol("(")?;
+        let mut arguments = Vec::new();
+        while !self.is_symbol(")") {
+            if matches!(self.peek_at(1), PyTokenKind::Symbol("=")) {
+                return Err(self.error("keyword arguments are not supported".to_owned()));
+            }
+            arguments.push(self.expression()?);
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        self.expect_symbol(")")?;
+        let cast = match name.as_str() {
+            "int" => Some("i64"),
+            "float" => Some("f64"),
+            _ => None,
+        };
+        match (cast, arguments.len()) {
+            (Some(ty), 1) => Ok(Expr::Cast(Box::new(arguments.remove(0)), RustType::simple(ty))),
+            (Some(_), _) => Err(PythonParser::error_at(token, format!("`{}` takes exactly one argument here", name))),
+            (None, _) => Ok(Expr::Call(name, arguments)),
+        }
+    }
+}
+
+// Python's flooring `//` and `%` on integers, and the same on floats
+const FLOOR_DIVISION: &str = "floor_div";
+const FLOOR_REMAINDER: &str = "floor_mod";
+const FLOOR_FUNCTIONS: &[&str] = &[FLOOR_REMAINDER, FLOOR_DIVISION, "floor_mod_float", "floor_div_float"];
+
+// `floor_mod(a, b)` is `(a % b + b) % b` and `floor_div(a, b)` is `(a - floor_mod(a, b)) / b`,
+// on `i64` or, with the `_float` suffix, on `f64`
+fn floor_function(name: &str) -> IrFunction {
+    let float = name.ends_with("_float");
+    let ty = RustType::simple(if float { "f64" } else { "i64" });
+    let a = || Box::new(Expr::Variable("a".to_owned()));
+    let b = || Box::new(Expr::Variable("b".to_owned()));
+    let remainder = Expr::Binary(BinaryOp::Rem, a(), b());
+    let value = if name.starts_with(FLOOR_REMAINDER) {
+        Expr::Binary(BinaryOp::Rem, Box::new(Expr::Binary(BinaryOp::Add, Box::new(remainder), b())), b())
+    } else {
+        let floor_remainder = Expr::Call(name.replace(FLOOR_DIVISION, FLOOR_REMAINDER), vec![*a(), *b()]);
+        Expr::Binary(BinaryOp::Div, Box::new(Expr::Binary(BinaryOp::Sub, a(), Box::new(floor_remainder))), b())
+    };
+    IrFunction {
+        name: name.to_owned(),
+        receiver: None,
+        parameters: vec![("a".to_owned(), ty.clone()), ("b".to_owned(), ty.clone())],
+        return_type: ty,
+        body: vec![Stmt::Return(Some(value))],
+    }
+}
+
+// Python turns integers into floats wherever the two meet, which Rust leaves to casts. Once
+// every signature is known, the integer operands, arguments, values and returns that meet a
+// float get a cast, and the FLOOR_FUNCTIONS that are called are added to the module. A
+// variable without an annotation is a float when any of its assignments is one, while a
+// float that reaches a declared `int` has no cast and its function is reported instead.
+struct PythonArithmetic {
+    signatures: HashMap<String, (Vec<RustType>, RustType)>,
+    scopes: Vec<HashMap<String, RustType>>,
+    return_type: RustType,
+    used: HashSet<String>,
+    // Variables without an annotation, those known to be floats and those found to be
+    // floats by the current pass over a function
+    untyped: HashSet<String>,
+    floats: HashSet<String>,
+    found: HashSet<String>,
+    error: Option<String>,
+}
+
+impl PythonArithmetic {
+    // Returns the functions that were removed from the module, with the reason
+    fn apply(module: &mut Module) -> Vec<(String, String)> {
+        let mut arithmetic = PythonArithmetic {
+            signatures: HashMap::new(),
+            scopes: Vec::new(),
+            return_type: RustType::simple("()"),
+            used: HashSet::new(),
+            untyped: HashSet::new(),
+            floats: HashSet::new(),
+            found: HashSet::new(),
+            error: None,
+        };
+        let floor_functions: Vec<IrFunction> = FLOOR_FUNCTIONS.iter().map(|name| floor_function(name)).collect();
+        for function in module.functions.iter().chain(&floor_functions) {
+            let parameters = function.parameters.iter().map(|(_, ty)| ty.clone()).collect();
+            arithmetic.signatures.insert(function.name.clone(), (parameters, function.return_type.clone()));
+        }
+        let mut rejected = Vec::new();
+        let mut used = HashSet::new();
+        module.functions.retain_mut(|function| {
+            arithmetic.floats.clear();
+            arithmetic.return_type = function.return_type.clone();
+            // Each pass may find more float variables, which change the types of the next one
+            let body = loop {
+                let mut body = function.body.clone();
+                arithmetic.scopes = vec![function.parameters.iter().cloned().collect()];
+                arithmetic.untyped.clear();
+                arithmetic.found.clear();
+                arithmetic.used.clear();
+                arithmetic.error = None;
+                arithmetic.block(&mut body);
+                if arithmetic.found.is_subset(&arithmetic.floats) {
+                    break body;
+                }
+                let found = std::mem::take(&mut arithmetic.found);
+                arithmetic.floats.extend(found);
+            };
+            match arithmetic.error.take() {
+                Some(message) => {
+                    rejected.push((function.name.clone(), message));
+                    false
+                }
+                None => {
+                    function.body = body;
+                    used.extend(arithmetic.used.drain());
+                    true
+                }
+            }
+        });
+        // `floor_div` calls `floor_mod`, and targets such as C++ need functions before their calls
+        let is_used = |name: &str| used.contains(name) || used.contains(&name.replace(FLOOR_REMAINDER, FLOOR_DIVISION));
+        module.functions.splice(0..0, floor_functions.into_iter().filter(|function| is_used(&function.name)));
+        rejected
+    }
+
+    fn block(&mut self, statements: &mut [Stmt]) {
+        self.scopes.push(HashMap::new());
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.scopes.pop();
+    }
+
+    fn reject(&mut self, message: String) {
+        self.error.get_or_insert(message);
+    }
+
+    fn statement(&mut self, statement: &mut Stmt) {
+        let float = RustType::simple("f64");
+        match statement {
+            Stmt::Let { name, ty, value, .. } => {
+                let value_type = self.expression(value);
+                if ty.is_none() {
+                    self.untyped.insert(name.clone());
+                    if self.floats.contains(name.as_str()) {
+                        *ty = Some(float.clone());
+                    } else if value_type.as_ref() == Some(&float) {
+                        self.found.insert(name.clone());
+                    }
+                }
+                if let Some(ty) = ty {
+                    if ty.is_integer() && value_type.as_ref() == Some(&float) {
+                        self.reject(format!("assigns a float to {} where an int is declared", name));
+                    }
+                    convert(value, value_type.as_ref(), ty);
+                }
+                if let Some(ty) = ty.clone().or(value_type) {
+                    if let Some(scope) = self.scopes.last_mut() {
+                        scope.insert(name.clone(), ty);
+                    }
+                }
+            }
+            Stmt::Assign { target, op, value } => {
+                let target_type = self.expression(target);
+                let value_type = self.expression(value);
+                // `/=` divides as floating point like `/`
+                let is_float = value_type.as_ref() == Some(&float) || *op == Some(BinaryOp::Div);
+                if let (Expr::Variable(name), Some(target_type)) = (&*target, &target_type) {
+                    if is_float && target_type.is_integer() {
+                        if self.untyped.contains(name.as_str()) {
+                            self.found.insert(name.clone());
+                        } else {
+                            self.reject(format!("assigns a float to {} where an int is declared", name));
+                        }
+                    }
+                }
+                if let Some(target_type) = target_type {
+                    convert(value, value_type.as_ref(), &target_type);
+                }
+            }
+            Stmt::Expr(expr) => {
+                self.expression(expr);
+            }
+            Stmt::If {
+                condition,
+                then_body,
+                else_body,
+            } => {
+                self.expression(condition);
+                self.block(then_body);
+                self.block(else_body);
+            }
+            Stmt::While { condition, body } => {
+                self.expression(condition);
+                self.block(body);
+            }
+            Stmt::ForRange {
+                variable, start, end, body, ..
+            } => {
+                self.expression(start);
+                self.expression(end);
+                self.scopes.push(HashMap::from([(variable.clone(), RustType::simple("i64"))]));
+                self.block(body);
+                self.scopes.pop();
+            }
+            Stmt::Return(Some(value)) => {
+                let value_type = self.expression(value);
+                let return_type = self.return_type.clone();
+                if return_type.is_integer() && value_type.as_ref() == Some(&float) {
+                    self.reject("returns a float where an int is declared".to_owned());
+                }
+                convert(value, value_type.as_ref(), &return_type);
+            }
+            Stmt::Return(None) | Stmt::Break | Stmt::Continue => {}
+        }
+    }
+
+    fn expression(&mut self, expr: &mut Expr) -> Option<RustType> {
+        let float = RustType::simple("f64");
+        match expr {
+            Expr::Int(_) => Some(RustType::simple("i64")),
+            Expr::Float(_) => Some(float),
+            Expr::Bool(_) => Some(RustType::simple("bool")),
+            Expr::Str(_) => Some(RustType::simple("String")),
+            Expr::Variable(name) => self.scopes.iter().rev().find_map(|scope| scope.get(name.as_str())).cloned(),
+            Expr::Unary(UnaryOp::Neg, operand) => self.expression(operand),
+            Expr::Unary(UnaryOp::Not, operand) => {
+                self.expression(operand);
+                Some(RustType::simple("bool"))
+            }
+            Expr::Binary(op, left, right) => {
+                let left_type = self.expression(left);
+                let right_type = self.expression(right);
+                let is_float = [&left_type, &right_type].into_iter().any(|ty| ty.as_ref() == Some(&float));
+                if is_float {
+                    convert(left, left_type.as_ref(), &float);
+                    convert(right, right_type.as_ref(), &float);
+                }
+                if op.is_arithmetic() {
+                    if is_float {
+                        Some(float)
+                    } else {
+                        left_type.or(right_type)
+                    }
+                } else {
+                    Some(RustType::simple("bool"))
+                }
+            }
+            Expr::Cast(value, ty) => {
+                let ty = ty.clone();
+                // The casts of `/` are left out for operands that are floats already
+                if self.expression(value).as_ref() == Some(&ty) {
+                    *expr = (**value).clone();
+                }
+                Some(ty)
+            }
+            Expr::Call(name, arguments) => {
+                let argument_types: Vec<Option<RustType>> = arguments.iter_mut().map(|argument| self.expression(argument)).collect();
+                if FLOOR_FUNCTIONS.contains(&name.as_str()) {
+                    if argument_types.iter().any(|ty| ty.as_ref() == Some(&float)) {
+                        *name = format!("{}_float", name);
+                    }
+                    self.used.insert(name.clone());
+                }
+                let (parameters, return_type) = self.signatures.get(name.as_str())?.clone();
+                for ((argument, argument_type), parameter) in arguments.iter_mut().zip(&argument_types).zip(&parameters) {
+                    if parameter.is_integer() && argument_type.as_ref() == Some(&float) {
+                        self.reject(format!("passes a float to {} where an int is declared", name));
+                    }
+                    convert(argument, argument_type.as_ref(), parameter);
+                }
+                Some(return_type)
+            }
+            // Not produced by the Python front end
+            Expr::SelfValue
+            | Expr::Field(..)
+            | Expr::MethodCall(..)
+            | Expr::StaticCall(..)
+            | Expr::Associated(..)
+            | Expr::Construct { .. } => None,
+        }
+    }
+}
+
+// Casts an integer `value` to a float where `expected` is one
+fn convert(value: &mut Expr, ty: Option<&RustType>, expected: &RustType) {
+    if expected.name == "f64" && ty.is_some_and(RustType::is_integer) {
+        *value = as_float(value.clone());
+    }
+}
+
+// Python's `/` divides as floating point whatever its operands
+fn as_float(value: Expr) -> Expr {
+    match value {
+        Expr::Float(_) => value,
+        Expr::Int(digits) => Expr::Float(format!("{}.0", digits)),
+        Expr::Unary(UnaryOp::Neg, operand) => Expr::Unary(UnaryOp::Neg, Box::new(as_float(*operand))),
+        value => Expr::Cast(Box::new(value), RustType::simple("f64")),
+    }
+}
+
+// 2 to the power of the bits of the types Syntax::wrapping_cast is for
+fn wrapping_modulus(ty: &str) -> Option<&'static str> {
+    match ty {
+        "u8" => Some("256"),
+        "u16" => Some("65536"),
+        "u32" => Some("4294967296"),
+        _ => None,
+    }
+}
+
+// The bits of `bool` and the unsigned types, whose values any wider one holds
+fn unsigned_bits(ty: &str) -> Option<u32> {
+    match ty {
+        "bool" => Some(1),
+        "u8" => Some(8),
+        "u16" => Some(16),
+        "u32" => Some(32),
+        "u64" | "usize" => Some(64),
+        "u128" => Some(128),
+        _ => None,
+    }
+}
+
+fn mark_mutable(statements: &mut [Stmt], reassigned: &HashSet<String>) {
+    for statement in statements {
+        match statement {
+            Stmt::Let { name, mutable, .. } => *mutable = reassigned.contains(name.as_str()),
+            Stmt::If {
+                then_body, else_body, ..
+            } => {
+                mark_mutable(then_body, reassigned);
+                mark_mutable(else_body, reassigned);
+            }
+            Stmt::While { body, .. } | Stmt::ForRange { body, .. } => mark_mutable(body, reassigned),
+            _ => {}
+        }
+    }
+}
+
+// A Rust type reduced to what the type maps describe: a name such as `i32`, `String`,
+// `&str`, `()`, `Vec`, `Option`, `HashMap` or `tuple`, and its type arguments
+#[derive(Debug, Clone, PartialEq)]
+struct RustType {
+    name: String,
+    arguments: Vec<RustType>,
+}
+
+impl RustType {
+    fn simple(name: &str) -> Self {
+        RustType {
+            name: name.to_owned(),
+            arguments: Vec::new(),
+        }
+    }
+
+    fn is_integer(&self) -> bool {
+        matches!(
+            self.name.as_str(),
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize"
+        )
+    }
+
+    fn from_syn(ty: &syn::Type) -> Result<Self, String> {
+        match ty {
+            syn::Type::Paren(paren) => RustType::from_syn(&paren.elem),
+            syn::Type::Group(group) => RustType::from_syn(&group.elem),
+            syn::Type::Tuple(tuple) if tuple.elems.is_empty() => Ok(RustType::simple("()")),
+            syn::Type::Tuple(tuple) => Ok(RustType {
+                name: "tuple".to_owned(),
+                arguments: tuple.elems.iter().map(RustType::from_syn).collect::<Result<_, _>>()?,
+            }),
+            // Writing through `&mut` has no counterpart in targets that pass numbers by value
+            syn::Type::Reference(reference) if reference.mutability.is_some() => {
+                Err(format!("mutable reference `{}` is not supported", tokens_to_string(ty)))
+            }
+            // Borrowed values are passed like owned ones, except that `&str` has its own mapping
+            syn::Type::Reference(reference) => match &*reference.elem {
+                syn::Type::Path(path) if path.qself.is_none() && path.path.is_ident("str") => Ok(RustType::simple("&str")),
+                syn::Type::Slice(slice) => Ok(RustType {
+                    name: "Vec".to_owned(),
+                    arguments: vec![RustType::from_syn(&slice.elem)?],
+                }),
+                elem => RustType::from_syn(elem),
+            },
+            syn::Type::Path(path) if path.qself.is_none() => {
+                // `std::collections::HashMap<K, V>` is looked up as `HashMap`
+                let segment = path.path.segments.last().ok_or("empty type path")?;
+                let arguments = match &segment.arguments {
+                    syn::PathArguments::None => Vec::new(),
+                    syn::PathArguments::AngleBracketed(generics) => generics
+                        .args
+                        .iter()
+                        .map(|argument| match argument {
+                            syn::GenericArgument::Type(ty) => RustType::from_syn(ty),
+                            other => Err(format!("unsupported type argument `{}`", tokens_to_string(other))),
+                        })
+                        .collect::<Result<_, _>>()?,
+                    syn::PathArguments::Parenthesized(_) => {
+                        return Err(format!("unsupported type `{}`", tokens_to_string(ty)))
+                    }
+                };
+                Ok(RustType {
+                    name: segment.ident.to_string(),
+                    arguments,
+                })
+            }
+            other => Err(format!("unsupported type `{}`", tokens_to_string(other))),
+        }
+    }
+}
+
+impl fmt::Display for RustType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let arguments: Vec<String> = self.arguments.iter().map(|argument| argument.to_string()).collect();
+        match self.name.as_str() {
+            "tuple" => write!(f, "({})", arguments.join(", ")),
+            name if arguments.is_empty() => write!(f, "{}", name),
+            name => write!(f, "{}<{}>", name, arguments.join(", ")),
+        }
+    }
+}
+
+// Rust type names to target type templates for one language. In a template `{0}`, `{1}`
+// stand for the mapped type arguments and `{elements}` for all tuple elements; `tuple2`
+// takes precedence over `tuple` for pairs, and `boxed.i32` over `i32` inside generics.
+// Structs and enums of the translated module keep their names unless mapped.
+struct TypeMap {
+    language: String,
+    // Section of the type map file for this language
+    section: String,
+    entries: HashMap<String, String>,
+    user_types: HashSet<String>,
+}
+
+impl TypeMap {
+    fn new(
+        language: &str,
+        section: &str,
+        defaults: &HashMap<String, String>,
+        overrides: Option<&HashMap<String, String>>,
+        user_types: HashSet<String>,
+    ) -> Self {
+        let mut entries = defaults.clone();
+        if let Some(overrides) = overrides {
+            entries.extend(overrides.iter().map(|(rust, target)| (rust.clone(), target.clone())));
+        }
+        TypeMap {
+            language: language.to_owned(),
+            section: section.to_owned(),
+            entries,
+            user_types,
+        }
+    }
+
+    fn map(&self, ty: &RustType, in_generic: bool) -> Result<String, String> {
+        let tuple_key = format!("tuple{}", ty.arguments.len());
+        let boxed_key = format!("boxed.{}", ty.name);
+        let keys: Vec<&str> = match ty.name.as_str() {
+            "tuple" => vec![&tuple_key, "tuple"],
+            name if in_generic => vec![&boxed_key, name],
+            name => vec![name],
+        };
+        let user_type = (self.user_types.contains(&ty.name) && ty.arguments.is_empty()).then(|| ty.name.clone());
+        let template = keys.iter().find_map(|key| self.entries.get(*key)).or(user_type.as_ref()).ok_or_else(|| {
+            format!(
+                "Rust type `{}` has no {} mapping; add `\"{}\" = \"...\"` under [{}] in a type map file",
+                ty,
+                self.language,
+                keys.last().unwrap_or(&""),
+                self.section
+            )
+        })?;
+
+        let arguments = ty
+            .arguments
+            .iter()
+            .map(|argument| self.map(argument, true))
+            .collect::<Result<Vec<String>, String>>()?;
+        let mut mapped = template.replace("{elements}", &arguments.join(", "));
+        for (index, argument) in arguments.iter().enumerate() {
+            mapped = mapped.replace(&format!("{{{}}}", index), argument);
+        }
+        Ok(mapped)
+    }
+}
+
+// User mappings keyed by language name or file extension, e.g.
+// [java]
+// i64 = "long"
+// Vec = "java.util.ArrayList<{0}>"
+fn load_type_overrides(path: &Path) -> Result<HashMap<String, HashMap<String, String>>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    toml::from_str(&content).map_err(|e| format!("Invalid type map file {}: {}", path.display(), e))
+}
+
+trait LanguageConverter {
+    fn convert(&self, function: &IrFunction, module: &Module, types: &TypeMap) -> Result<String, String>;
+    fn details(&self) -> &dyn LanguageDetails;
+    // Default Rust-to-target type mappings, see TypeMap
+    fn type_names(&self) -> &HashMap<String, String>;
+    // How function bodies are spelled, see Emitter
+    fn syntax(&self) -> &Syntax;
+    // How structs, enums and methods are declared, see declare_struct
+    fn type_syntax(&self) -> &TypeSyntax;
+    // Files written for the language, see CodeConverter::write_projects
+    fn project(&self) -> &Project;
+    // How translations are run by `--golden`, see run_goldens
+    fn check(&self) -> Option<&CheckProgram>;
+
+    fn declare_struct(&self, item: &RustStruct, module: &Module, types: &TypeMap) -> Result<String, String> {
+        declare_struct(item, &self.target(module, types))
+    }
+
+    fn declare_enum(&self, item: &RustEnum, module: &Module, types: &TypeMap) -> Result<String, String> {
+        declare_enum(item, &self.target(module, types))
+    }
+
+    fn target<'a>(&'a self, module: &'a Module, types: &'a TypeMap) -> Target<'a> {
+        Target {
+            language: self.details().name(),
+            syntax: self.syntax(),
+            type_syntax: self.type_syntax(),
+            types,
+            module,
+        }
+    }
+}
+
+trait LanguageDetails {
+    fn name(&self) -> &str;
+    fn file_extension(&self) -> &str;
+}
+
+struct CodeConverter<'a> {
+    language_converters: Vec<Box<dyn LanguageConverter + 'a>>,    
+    type_overrides: HashMap<String, HashMap<String, String>>,
+}
+
+impl<'a> CodeConverter<'a> {
+    fn new(type_overrides: HashMap<String, HashMap<String, String>>, languages: Vec<LanguageSpec>) -> Self {
+        CodeConverter {
+            type_overrides,
+            language_converters: languages
+                .into_iter()
+                .map(|spec| Box::new(TemplateConverter { spec }) as Box<dyn LanguageConverter>)
+                .collect(),
+        }
+    }
+
+    // Keeps the converters named by language or file extension, e.g. `rust` or `rs`
+    fn select_languages(&mut self, names: &[String]) -> Result<(), String> {
+        for name in names {
+            let known = self.language_converters.iter().any(|converter| {
+                let details = converter.details();
+                details.name().eq_ignore_ascii_case(name) || details.file_extension() == name.as_str()
+            });
+            if !known {
+                return Err(format!("unknown language `{}`", name));
+            }
+        }
+        self.language_converters.retain(|converter| {
+            let details = converter.details();
+            names
+                .iter()
+                .any(|name| details.name().eq_ignore_ascii_case(name) || details.file_extension() == name.as_str())
+        });
+        Ok(())
+    }
+
+    // Built-in mappings of the converter with the user's section for that language on top
+    fn type_map(&self, converter: &dyn LanguageConverter, user_types: &HashSet<String>) -> TypeMap {
+        let details = converter.details();
+        let overrides = self
+            .type_overrides
+            .iter()
+            .find(|(section, _)| section.eq_ignore_ascii_case(details.name()) || section.as_str() == details.file_extension())
+            .map(|(_, overrides)| overrides);
+        TypeMap::new(details.name(), details.file_extension(), converter.type_names(), overrides, user_types.clone())
+    }
+
+    fn convert_to_languages(&self, module: &Module) {
+        let user_types = module.type_names();
+        for item in &module.structs {
+            self.print_conversions(&item.name, &user_types, |converter, types| converter.declare_struct(item, module, types));
+        }
+        for item in &module.enums {
+            self.print_conversions(&item.name, &user_types, |converter, types| converter.declare_enum(item, module, types));
+        }
+        for function in &module.functions {
+            self.print_conversions(&function.name, &user_types, |converter, types| {
+                let code = converter.convert(function, module, types)?;
+                Ok(fill(&converter.project().functions, &[("code", &code)]))
+            });
+        }
+    }
+
+    // Writes one project per language below `options.directory` and returns the number of
+    // files that could not be written. Items that fail to convert are left out and reported.
+    fn write_projects(&self, module: &Module, options: &OutputOptions) -> usize {
+        let mut failures = 0;
+        for converter in &self.language_converters {
+            let converter = converter.as_ref();
+            let language = converter.details().name();
+            let mut report = |name: &str, err: String| eprintln!("Error converting {} to {}: {}", name, language, err);
+            let files = self.project_files(converter, module, &options.package, options.build_files, &mut report);
+
+            let root = options.directory.join(&converter.project().directory);
+            for (path, content) in files {
+                let path = root.join(path);
+                match write_file(&path, &content, options.overwrite) {
+                    Ok(outcome) => println!("{} {}", outcome, path.display()),
+                    Err(err) => {
+                        eprintln!("Error: {}", err);
+                        failures += 1;
+                    }
+                }
+            }
+        }
+        failures
+    }
+
+    // The files of the project of one language. Items that fail to convert are left out and
+    // passed to `report`.
+    fn project_files(
+        &self,
+        converter: &dyn LanguageConverter,
+        module: &Module,
+        package: &str,
+        build_files: bool,
+        report: &mut dyn FnMut(&str, String),
+    ) -> Vec<(String, String)> {
+        let types = self.type_map(converter, &module.type_names());
+        let mut declarations = Vec::new();
+        for item in &module.structs {
+            match converter.declare_struct(item, module, &types) {
+                Ok(code) => declarations.push((item.name.as_str(), code)),
+                Err(err) => report(&item.name, err),
+            }
+        }
+        for item in &module.enums {
+            match converter.declare_enum(item, module, &types) {
+                Ok(code) => declarations.push((item.name.as_str(), code)),
+                Err(err) => report(&item.name, err),
+            }
+        }
+        let mut functions = Vec::new();
+        for function in &module.functions {
+            match converter.convert(function, module, &types) {
+                Ok(code) => functions.push(code),
+                Err(err) => report(&function.name, err),
+            }
+        }
+        converter.project().files(converter.details(), package, build_files, &declarations, &functions)
+    }
+
+    fn print_conversions(
+        &self,
+        name: &str,
+        user_types: &HashSet<String>,
+        convert: impl Fn(&dyn LanguageConverter, &TypeMap) -> Result<String, String>,
+    ) {
+        for converter in &self.language_converters {
+            let types = self.type_map(converter.as_ref(), user_types);
+            match convert(converter.as_ref(), &types) {
+                Ok(result) => println!("Converted {}:\n{}", converter.details().name(), result),
+                Err(err) => eprintln!("Error converting {} to {}: {}", name, converter.details().name(), err),
+            }
+        }
+    }
+}
+
+// What happens to files that already exist in the output directory
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Overwrite {
+    // Only files whose content would not change are accepted
+    Never,
+    Always,
+    // The old file is kept next to the new one with a `.bak` suffix
+    Backup,
+}
+
+struct OutputOptions {
+    directory: PathBuf,
+    // Dotted package such as `com.example.shapes`, used for packages, namespaces and modules
+    package: String,
+    build_files: bool,
+    overwrite: Overwrite,
+}
+
+fn write_file(path: &Path, content: &str, overwrite: Overwrite) -> Result<&'static str, String> {
+    let mut outcome = "Wrote";
+    if path.exists() {
+        let existing = fs::read_to_string(path).ok();
+        if existing.as_deref() == Some(content) {
+            return Ok("Unchanged");
+        }
+        match overwrite {
+            Overwrite::Never => {
+                return Err(format!(
+                    "{} already exists and differs from the translation; use --overwrite always or backup",
+                    path.display()
+                ))
+            }
+            Overwrite::Always => outcome = "Replaced",
+            Overwrite::Backup => {
+                let mut backup = path.as_os_str().to_owned();
+                backup.push(".bak");
+                fs::rename(path, &backup).map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+                outcome = "Replaced (backup kept)";
+            }
+        }
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(outcome)
+}
+
+// Package names become directories and identifiers, so every segment must be an identifier
+fn is_valid_package(package: &str) -> bool {
+    package.split('.').all(|segment| {
+        let mut chars = segment.chars();
+        matches!(chars.next(), Some(first) if first.is_ascii_alphabetic() || first == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+// How the translation of a module is laid out on disk for one language. Templates use
+// `{package}` (dotted), `{package_path}` (with slashes), `{package_scope}` (with `::`),
+// `{namespace}` (capitalized segments), `{package_name}` (the last segment), `{extension}`
+// and `{code}`; build files also `{source_file}`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Project {
+    // Subdirectory of the output directory
+    directory: String,
+    source_file: String,
+    // The main source file around its declarations and functions
+    source: String,
+    // Declarations go to files of their own named after `{type}` where the language
+    // requires it, with the same `source` around them
+    type_file: Option<String>,
+    // All free functions, such as Java's `Main` class
+    functions: String,
+    // Blank lines between top-level items
+    separator: String,
+    // A minimal build file
+    build_file: Option<ProjectFile>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectFile {
+    name: String,
+    content: String,
+}
+
+// The placeholders of Project templates other than `{code}`, `{type}` and `{source_file}`
+fn package_values(package: &str, extension: &str) -> TemplateValues<'static> {
+    let segments: Vec<&str> = package.split('.').collect();
+    let namespace: Vec<String> = segments
+        .iter()
+        .map(|segment| {
+            let mut chars = segment.chars();
+            chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+        })
+        .collect();
+    TemplateValues::default()
+        .text("package", package)
+        .text("package_path", segments.join("/"))
+        .text("package_scope", segments.join("::"))
+        .text("namespace", namespace.join("."))
+        .text("package_name", segments.last().copied().unwrap_or_default())
+        .text("extension", extension)
+}
+
+impl Project {
+    fn files(
+        &self,
+        details: &dyn LanguageDetails,
+        package: &str,
+        build_files: bool,
+        declarations: &[(&str, String)],
+        functions: &[String],
+    ) -> Vec<(String, String)> {
+        let values = package_values(package, details.file_extension());
+        let source_file = render(&self.source_file, &values);
+        let source = |code: &[&str]| -> String {
+            let code: Vec<&str> = code.iter().map(|item| item.trim_end_matches('\n')).collect();
+            render(&self.source, &values.clone().text("code", code.join(&self.separator)))
+        };
+
+        let functions = (!functions.is_empty()).then(|| fill(&self.functions, &[("code", &functions.join("\n\n"))]));
+        let mut files = Vec::new();
+        let mut items: Vec<&str> = Vec::new();
+        for (name, code) in declarations {
+            match &self.type_file {
+                Some(type_file) => {
+                    let path = render(type_file, &values.clone().text("type", *name));
+                    files.push((path, source(&[code])));
+                }
+                None => items.push(code),
+            }
+        }
+        if let Some(functions) = &functions {
+            items.push(functions);
+        }
+        if !items.is_empty() {
+            files.push((source_file.clone(), source(&items)));
+        }
+
+        if let (true, Some(build_file)) = (build_files, &self.build_file) {
+            let content = render(&build_file.content, &values.text("source_file", source_file));
+            files.push((build_file.name.clone(), content));
+        }
+        files
+    }
+}
+
+// A target language as read from its language file. The built-in languages are files of the
+// same format, see BUILTIN_LANGUAGES and `--export-languages`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LanguageSpec {
+    name: String,
+    extension: String,
+    // A free function from `{name}`, `{return_type}`, `{body}` and the `parameters` list
+    // with `{name}` and `{type}` per parameter
+    function: String,
+    // Default Rust-to-target type mappings, see TypeMap
+    types: HashMap<String, String>,
+    syntax: Syntax,
+    declarations: TypeSyntax,
+    project: Project,
+    // How `--golden` runs translations; languages without one are only compared with goldens
+    check: Option<CheckProgram>,
+}
+
+// A program around a translated project that prints the result of its `check()` function.
+// File names, contents and command arguments use the placeholders of Project.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckProgram {
+    // Package of the translation where the usual one cannot be run, such as Go's `main`
+    package: Option<String>,
+    files: Vec<ProjectFile>,
+    // Run in order in the project directory; the last one prints the result
+    commands: Vec<Vec<String>>,
+}
+
+impl LanguageSpec {
+    fn parse(text: &str) -> Result<Self, String> {
+        let language: LanguageSpec = toml::from_str(text).map_err(|e| e.to_string())?;
+        language.check_templates()?;
+        Ok(language)
+    }
+
+    // Every template with the placeholders it is rendered with, so that a mistyped key or an
+    // unclosed section is reported when the file is loaded instead of showing up in the output
+    fn check_templates(&self) -> Result<(), String> {
+        const PACKAGE: &[&str] = &["package", "package_path", "package_scope", "namespace", "package_name", "extension"];
+        const PARAMETERS: &[(&str, &[&str])] = &[("parameters", &["name", "type"])];
+        const FIELDS: &[(&str, &[&str])] = &[("fields", &["name", "type"])];
+        const FIELD_VALUES: &[(&str, &[&str])] = &[("fields", &["name", "value"])];
+        let check = |key: &str, template: &str, keys: &[&str], lists: &[(&str, &[&str])]| {
+            check_template(template, keys, lists).map_err(|e| format!("{}: {}", key, e))
+        };
+        let with_package = |keys: &[&'static str]| -> Vec<&'static str> { PACKAGE.iter().chain(keys).copied().collect() };
+
+        check("function", &self.function, &["name", "return_type", "body"], PARAMETERS)?;
+
+        let syntax = &self.syntax;
+        for (index, declaration) in syntax.declarations.iter().enumerate() {
+            check(&format!("syntax.declarations[{}]", index), declaration, &["name", "type", "value"], &[])?;
+        }
+        check("syntax.if_header", &syntax.if_header, &["condition"], &[])?;
+        check("syntax.else_if_header", &syntax.else_if_header, &["condition"], &[])?;
+        check("syntax.while_header", &syntax.while_header, &["condition"], &[])?;
+        check("syntax.for_header", &syntax.for_header, &["variable", "type", "start", "end"], &[])?;
+        if let Some(header) = &syntax.inclusive_for_header {
+            check("syntax.inclusive_for_header", header, &["variable", "type", "start", "end"], &[])?;
+        }
+        check("syntax.cast", &syntax.cast, &["value", "type"], &[])?;
+        if let Some(cast) = &syntax.wrapping_cast {
+            check("syntax.wrapping_cast", cast, &["value", "type", "modulus"], &[])?;
+        }
+        if let Some(division) = &syntax.truncating_division {
+            check("syntax.truncating_division", division, &["left", "right"], &[])?;
+        }
+        if let Some(remainder) = &syntax.truncating_remainder {
+            check("syntax.truncating_remainder", remainder, &["left", "right"], &[])?;
+        }
+        check("syntax.self_field", &syntax.self_field, &["field"], &[])?;
+        check("syntax.static_call", &syntax.static_call, &["type", "name", "arguments"], &[])?;
+        check("syntax.escape", &syntax.escape, &["name"], &[])?;
+
+        let declarations = &self.declarations;
+        check("declarations.record", &declarations.record, &["name", "members"], FIELDS)?;
+        check("declarations.mutable_record", &declarations.mutable_record, &["name", "members"], FIELDS)?;
+        let enum_keys = &["name", "variants", "variant_names", "members"];
+        if let Some(plain_enum) = &declarations.plain_enum {
+            check("declarations.plain_enum", plain_enum, enum_keys, &[])?;
+        }
+        check("declarations.plain_variant", &declarations.plain_variant, &["enum", "variant", "index"], &[])?;
+        check("declarations.union", &declarations.union, enum_keys, &[])?;
+        check("declarations.variant", &declarations.variant, &["enum", "variant"], FIELDS)?;
+        check("declarations.unit_variant", &declarations.unit_variant, &["enum", "variant"], FIELDS)?;
+        check("declarations.members", &declarations.members, &["name", "methods"], &[])?;
+        let method_keys = &["type", "name", "receiver", "return_type", "body"];
+        check("declarations.method", &declarations.method, method_keys, PARAMETERS)?;
+        check("declarations.mutating_method", &declarations.mutating_method, method_keys, PARAMETERS)?;
+        if let Some(union_method) = &declarations.union_method {
+            check("declarations.union_method", union_method, method_keys, PARAMETERS)?;
+        }
+        check("declarations.static_method", &declarations.static_method, method_keys, PARAMETERS)?;
+        check("declarations.static_block", &declarations.static_block, &["methods"], &[])?;
+        let value_keys = &["type", "enum", "variant"];
+        check("declarations.construct", &declarations.construct, value_keys, FIELD_VALUES)?;
+        check("declarations.plain_variant_value", &declarations.plain_variant_value, value_keys, FIELD_VALUES)?;
+        check("declarations.variant_value", &declarations.variant_value, value_keys, FIELD_VALUES)?;
+        check("declarations.unit_variant_value", &declarations.unit_variant_value, value_keys, FIELD_VALUES)?;
+
+        let project = &self.project;
+        check("project.source_file", &project.source_file, PACKAGE, &[])?;
+        check("project.source", &project.source, &with_package(&["code"]), &[])?;
+        if let Some(type_file) = &project.type_file {
+            check("project.type_file", type_file, &with_package(&["type"]), &[])?;
+        }
+        check("project.functions", &project.functions, &["code"], &[])?;
+        if let Some(build_file) = &project.build_file {
+            check("project.build_file.content", &build_file.content, &with_package(&["source_file"]), &[])?;
+        }
+
+        if let Some(program) = &self.check {
+            for file in &program.files {
+                check("check.files.name", &file.name, PACKAGE, &[])?;
+                check("check.files.content", &file.content, PACKAGE, &[])?;
+            }
+            for argument in program.commands.iter().flatten() {
+                check("check.commands", argument, PACKAGE, &[])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn builtins() -> Vec<LanguageSpec> {
+        BUILTIN_LANGUAGES
+            .iter()
+            .map(|text| LanguageSpec::parse(text).expect("The built-in language files are valid"))
+            .collect()
+    }
+}
+
+// Every `*.toml` file of `directory` in name order, each describing one language
+fn load_languages(directory: &Path) -> Result<Vec<LanguageSpec>, String> {
+    let entries = fs::read_dir(directory).map_err(|e| format!("Failed to read {}: {}", directory.display(), e))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "toml"))
+        .collect();
+    paths.sort();
+
+    let mut languages = Vec::new();
+    for path in paths {
+        let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let language = LanguageSpec::parse(&content).map_err(|e| format!("Invalid language file {}: {}", path.display(), e))?;
+        languages.push(language);
+    }
+    Ok(languages)
+}
+
+// A loaded language replaces the built-in one of the same name, any other is added
+fn merge_languages(languages: &mut Vec<LanguageSpec>, loaded: Vec<LanguageSpec>) {
+    for language in loaded {
+        match languages.iter_mut().find(|known| known.name.eq_ignore_ascii_case(&language.name)) {
+            Some(known) => *known = language,
+            None => languages.push(language),
+        }
+    }
+}
+
+// Writes the built-in languages as `<project directory>.toml`, as a starting point for new ones
+fn export_languages(directory: &Path, overwrite: Overwrite) -> usize {
+    let mut failures = 0;
+    for (text, language) in BUILTIN_LANGUAGES.iter().zip(LanguageSpec::builtins()) {
+        let path = directory.join(format!("{}.toml", language.project.directory));
+        match write_file(&path, text, overwrite) {
+            Ok(outcome) => println!("{} {}", outcome, path.display()),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                failures += 1;
+            }
+        }
+    }
+    failures
+}
+
+struct TemplateConverter {
+    spec: LanguageSpec,
+}
+
+impl LanguageDetails for TemplateConverter {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn file_extension(&self) -> &str {
+        &self.spec.extension
+    }
+}
+
+impl LanguageConverter for TemplateConverter {
+    fn convert(&self, function: &IrFunction, module: &Module, types: &TypeMap) -> Result<String, String> {
+        let target = self.target(module, types);
+        let values = TemplateValues::default()
+            .text("name", target.identifier(&function.name))
+            .text("return_type", types.map(&function.return_type, false)?)
+            .text("body", emit_body(function, &target, &self.spec.syntax.body_indent, None)?)
+            .list("parameters", parameter_values(&function.parameters, &target, None)?);
+        Ok(render(&self.spec.function, &values))
+    }
+
+    fn details(&self) -> &dyn LanguageDetails {
+        self
+    }
+
+    fn type_names(&self) -> &HashMap<String, String> {
+        &self.spec.types
+    }
+
+    fn syntax(&self) -> &Syntax {
+        &self.spec.syntax
+    }
+
+    fn type_syntax(&self) -> &TypeSyntax {
+        &self.spec.declarations
+    }
+
+    fn project(&self) -> &Project {
+        &self.spec.project
+    }
+
+    fn check(&self) -> Option<&CheckProgram> {
+        self.spec.check.as_ref()
+    }
+}
+
+// Language files of the built-in targets, in the order they are printed
+const BUILTIN_LANGUAGES: &[&str] = &[
+    JAVA_LANGUAGE,
+    CPP_LANGUAGE,
+    KOTLIN_LANGUAGE,
+    PYTHON_LANGUAGE,
+    RUST_LANGUAGE,
+    GO_LANGUAGE,
+    RUBY_LANGUAGE,
+    SWIFT_LANGUAGE,
+    CSHARP_LANGUAGE,
+];
+
+const JAVA_LANGUAGE: &str = r#"name = "Java"
+extension = "java"
+function = '''
+  public static {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+
+[types]
+"i8" = "byte"
+"i16" = "short"
+"i32" = "int"
+"i64" = "long"
+"isize" = "long"
+"u8" = "short"
+"u16" = "int"
+"u32" = "long"
+"u64" = "long"
+"usize" = "long"
+"f32" = "float"
+"f64" = "double"
+"bool" = "boolean"
+"char" = "char"
+"String" = "String"
+"&str" = "String"
+"()" = "void"
+"boxed.i8" = "Byte"
+"boxed.i16" = "Short"
+"boxed.i32" = "Integer"
+"boxed.i64" = "Long"
+"boxed.isize" = "Long"
+"boxed.u8" = "Short"
+"boxed.u16" = "Integer"
+"boxed.u32" = "Long"
+"boxed.u64" = "Long"
+"boxed.usize" = "Long"
+"boxed.f32" = "Float"
+"boxed.f64" = "Double"
+"boxed.bool" = "Boolean"
+"boxed.char" = "Character"
+"boxed.()" = "Void"
+"Vec" = "List<{0}>"
+"Option" = "Optional<{0}>"
+"HashMap" = "Map<{0}, {1}>"
+
+[syntax]
+blocks = "braces"
+body_indent = "    "
+indent = "  "
+statement_end = ";"
+declarations = [
+    "final var {name} = {value}", "var {name} = {value}", "final {type} {name} = {value}",
+    "{type} {name} = {value}",
+]
+if_header = "if ({condition})"
+else_if_header = "else if ({condition})"
+while_header = "while ({condition})"
+for_header = "for ({type} {variable} = {start}; {variable} < {end}; {variable}++)"
+inclusive_for_header = "for ({type} {variable} = {start}; {variable} <= {end}; {variable}++)"
+cast = "({type}) {value}"
+wrapping_cast = "({type}) Math.floorMod({value}, {modulus}L)"
+and = "&&"
+or = "||"
+not = "!"
+booleans = ["false", "true"]
+continue_keyword = "continue"
+self_value = "this"
+self_field = "this.{field}"
+static_call = "{type}.{name}({arguments})"
+reserved = [
+    "abstract", "boolean", "byte", "case", "catch", "char", "class", "default", "double",
+    "extends", "final", "finally", "float", "goto", "implements", "import", "instanceof",
+    "int", "interface", "long", "native", "new", "null", "package", "private",
+    "protected", "public", "short", "static", "super", "switch", "synchronized", "this",
+    "throw", "throws", "transient", "try", "void", "volatile",
+]
+escape = "{name}_"
+
+[declarations]
+record = '''
+public final class {name} {
+{#fields}  public final {type} {name};
+{/fields}
+  public {name}({#fields ", "}{type} {name}{/fields}) {
+{#fields}    this.{name} = {name};
+{/fields}  }
+{members}}'''
+mutable_record = '''
+public final class {name} {
+{#fields}  public {type} {name};
+{/fields}
+  public {name}({#fields ", "}{type} {name}{/fields}) {
+{#fields}    this.{name} = {name};
+{/fields}  }
+{members}}'''
+plain_enum = '''
+public enum {name} {
+{variants};
+{members}}'''
+plain_variant = "  {variant}"
+plain_variant_separator = ",\n"
+union = '''
+public sealed interface {name} {
+{variants}{members}}'''
+variant = "  record {variant}({#fields \", \"}{type} {name}{/fields}) implements {enum} {}\n"
+unit_variant = "  record {variant}() implements {enum} {}\n"
+members = "\n{methods}\n"
+method = '''
+  public {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+mutating_method = '''
+  public {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+union_method = '''
+  default {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+static_method = '''
+  public static {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+static_block = "{methods}"
+method_body_indent = "    "
+static_body_indent = "    "
+construct = 'new {type}({#fields ", "}{value}{/fields})'
+plain_variant_value = "{enum}.{variant}"
+variant_value = 'new {enum}.{variant}({#fields ", "}{value}{/fields})'
+unit_variant_value = "new {enum}.{variant}()"
+
+[project]
+directory = "java"
+source_file = "src/main/java/{package_path}/Main.{extension}"
+source = '''
+package {package};
+
+import java.util.*;
+
+{code}
+'''
+type_file = "src/main/java/{package_path}/{type}.{extension}"
+functions = '''
+public final class Main {
+{code}
+}'''
+separator = "\n\n"
+
+[project.build_file]
+name = "pom.xml"
+content = '''
+<?xml version="1.0" encoding="UTF-8"?>
+<project xmlns="http://maven.apache.org/POM/4.0.0"
+         xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+         xsi:schemaLocation="http://maven.apache.org/POM/4.0.0 https://maven.apache.org/xsd/maven-4.0.0.xsd">
+  <modelVersion>4.0.0</modelVersion>
+  <groupId>{package}</groupId>
+  <artifactId>{package_name}</artifactId>
+  <version>0.1.0</version>
+  <properties>
+    <maven.compiler.release>17</maven.compiler.release>
+    <project.build.sourceEncoding>UTF-8</project.build.sourceEncoding>
+  </properties>
+</project>
+'''
+
+[check]
+commands = [
+    ["javac", "-d", "classes", "-sourcepath", "src/main/java", "src/main/java/{package_path}/Check.java"],
+    ["java", "-cp", "classes", "{package}.Check"],
+]
+
+[[check.files]]
+name = "src/main/java/{package_path}/Check.java"
+content = '''
+package {package};
+
+public final class Check {
+  public static void main(String[] args) {
+    System.out.println(Main.check());
+  }
+}
+'''
+"#;
+
+const CPP_LANGUAGE: &str = r#"name = "C++"
+extension = "cpp"
+function = '''
+{return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+}'''
+
+[types]
+"i8" = "std::int8_t"
+"i16" = "std::int16_t"
+"i32" = "std::int32_t"
+"i64" = "std::int64_t"
+"isize" = "std::ptrdiff_t"
+"u8" = "std::uint8_t"
+"u16" = "std::uint16_t"
+"u32" = "std::uint32_t"
+"u64" = "std::uint64_t"
+"usize" = "std::size_t"
+"f32" = "float"
+"f64" = "double"
+"bool" = "bool"
+"char" = "char32_t"
+"String" = "std::string"
+"&str" = "std::string_view"
+"()" = "void"
+"Vec" = "std::vector<{0}>"
+"Option" = "std::optional<{0}>"
+"HashMap" = "std::unordered_map<{0}, {1}>"
+"tuple" = "std::tuple<{elements}>"
+
+[syntax]
+blocks = "braces"
+body_indent = "  "
+indent = "  "
+statement_end = ";"
+declarations = [
+    "const auto {name} = {value}", "auto {name} = {value}",
+    "const {type} {name} = {value}", "{type} {name} = {value}",
+]
+if_header = "if ({condition})"
+else_if_header = "else if ({condition})"
+while_header = "while ({condition})"
+for_header = "for ({type} {variable} = {start}; {variable} < {end}; ++{variable})"
+inclusive_for_header = "for ({type} {variable} = {start}; {variable} <= {end}; ++{variable})"
+cast = "static_cast<{type}>({value})"
+and = "&&"
+or = "||"
+not = "!"
+booleans = ["false", "true"]
+continue_keyword = "continue"
+self_value = "(*this)"
+self_field = "this->{field}"
+static_call = "{type}::{name}({arguments})"
+reserved = [
+    "and", "auto", "bool", "case", "catch", "char", "class", "default", "delete",
+    "double", "explicit", "float", "friend", "goto", "inline", "int", "long", "namespace",
+    "new", "not", "nullptr", "operator", "or", "private", "protected", "public",
+    "register", "short", "signed", "sizeof", "switch", "template", "this", "throw",
+    "typedef", "typename", "union", "unsigned", "using", "virtual", "void", "volatile",
+    "xor",
+]
+escape = "{name}_"
+
+[declarations]
+record = '''
+struct {name} {
+{#fields}  {type} {name};
+{/fields}{members}};'''
+mutable_record = '''
+struct {name} {
+{#fields}  {type} {name};
+{/fields}{members}};'''
+plain_enum = '''
+enum class {name} {
+{variants}
+};'''
+plain_variant = "  {variant}"
+plain_variant_separator = ",\n"
+union = '''
+struct {name} {
+{variants}
+  std::variant<{variant_names}> value;
+{members}};'''
+variant = '''
+  struct {variant} {
+{#fields}    {type} {name};
+{/fields}  };
+'''
+unit_variant = "  struct {variant} {};\n"
+members = "\n{methods}\n"
+method = '''
+  {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) const {
+{body}
+  }'''
+mutating_method = '''
+  {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+union_method = '''
+  {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) const {
+{body}
+  }'''
+static_method = '''
+  static {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+static_block = "{methods}"
+method_body_indent = "    "
+static_body_indent = "    "
+construct = '{type}{{#fields ", "}{value}{/fields}}'
+plain_variant_value = "{enum}::{variant}"
+variant_value = '{enum}{{enum}::{variant}{{#fields ", "}{value}{/fields}}}'
+unit_variant_value = "{enum}{{enum}::{variant}{}}"
+
+[project]
+directory = "cpp"
+source_file = "{package_name}.{extension}"
+source = '''
+#include <cstddef>
+#include <cstdint>
+#include <optional>
+#include <string>
+#include <string_view>
+#include <tuple>
+#include <unordered_map>
+#include <variant>
+#include <vector>
+
+namespace {package_scope} {
+
+{code}
+
+}  // namespace {package_scope}
+'''
+functions = "{code}"
+separator = "\n\n"
+
+[project.build_file]
+name = "CMakeLists.txt"
+content = '''
+cmake_minimum_required(VERSION 3.16)
+project({package_name} LANGUAGES CXX)
+
+set(CMAKE_CXX_STANDARD 17)
+set(CMAKE_CXX_STANDARD_REQUIRED ON)
+
+add_library({package_name} {source_file})
+'''
+
+[check]
+commands = [["g++", "-std=c++17", "-o", "check", "check.cpp"], ["./check"]]
+
+[[check.files]]
+name = "check.cpp"
+content = '''
+#include <iostream>
+
+#include "{package_name}.cpp"
+
+int main() {
+  std::cout << {package_scope}::check() << std::endl;
+}
+'''
+"#;
+
+const KOTLIN_LANGUAGE: &str = r#"name = "Kotlin"
+extension = "kt"
+function = '''
+fun {name}({#parameters ", "}{name}: {type}{/parameters}): {return_type} {
+{body}
+}'''
+
+[types]
+"i8" = "Byte"
+"i16" = "Short"
+"i32" = "Int"
+"i64" = "Long"
+"isize" = "Long"
+"u8" = "Short"
+"u16" = "Int"
+"u32" = "Long"
+"u64" = "Long"
+"usize" = "Long"
+"f32" = "Float"
+"f64" = "Double"
+"bool" = "Boolean"
+"char" = "Char"
+"String" = "String"
+"&str" = "String"
+"()" = "Unit"
+"Vec" = "List<{0}>"
+"Option" = "{0}?"
+"HashMap" = "Map<{0}, {1}>"
+"tuple2" = "Pair<{0}, {1}>"
+"tuple3" = "Triple<{0}, {1}, {2}>"
+
+[syntax]
+blocks = "braces"
+body_indent = "  "
+indent = "  "
+statement_end = ""
+declarations = [
+    "val {name} = {value}", "var {name} = {value}", "val {name}: {type} = {value}",
+    "var {name}: {type} = {value}",
+]
+if_header = "if ({condition})"
+else_if_header = "else if ({condition})"
+while_header = "while ({condition})"
+for_header = "for ({variable} in {start} until {end})"
+inclusive_for_header = "for ({variable} in {start}..{end})"
+cast = "{value}.to{type}()"
+wrapping_cast = "{value}.mod({modulus}L).to{type}()"
+and = "&&"
+or = "||"
+not = "!"
+booleans = ["false", "true"]
+integer_suffixes = { "i64" = "L", "isize" = "L", "u32" = "L", "u64" = "L", "usize" = "L" }
+continue_keyword = "continue"
+string_escapes = [["$", "\\$"]]
+self_value = "this"
+self_field = "this.{field}"
+static_call = "{type}.{name}({arguments})"
+reserved = [
+    "class", "fun", "interface", "is", "null", "object", "package", "this", "throw",
+    "typealias", "typeof", "val", "var", "when",
+]
+escape = "{name}_"
+
+[declarations]
+record = 'data class {name}({#fields ", "}val {name}: {type}{/fields}) {{members}}'
+mutable_record = 'data class {name}({#fields ", "}var {name}: {type}{/fields}) {{members}}'
+plain_enum = '''
+enum class {name} {
+{variants};
+{members}}'''
+plain_variant = "  {variant}"
+plain_variant_separator = ",\n"
+union = '''
+sealed class {name} {
+{variants}{members}}'''
+variant = "  data class {variant}({#fields \", \"}val {name}: {type}{/fields}) : {enum}()\n"
+unit_variant = "  data object {variant} : {enum}()\n"
+members = "\n{methods}\n"
+method = '''
+  fun {name}({#parameters ", "}{name}: {type}{/parameters}): {return_type} {
+{body}
+  }'''
+mutating_method = '''
+  fun {name}({#parameters ", "}{name}: {type}{/parameters}): {return_type} {
+{body}
+  }'''
+union_method = '''
+  fun {name}({#parameters ", "}{name}: {type}{/parameters}): {return_type} {
+{body}
+  }'''
+static_method = '''
+    fun {name}({#parameters ", "}{name}: {type}{/parameters}): {return_type} {
+{body}
+    }'''
+static_block = '''
+  companion object {
+{methods}
+  }'''
+method_body_indent = "    "
+static_body_indent = "      "
+construct = '{type}({#fields ", "}{value}{/fields})'
+plain_variant_value = "{enum}.{variant}"
+variant_value = '{enum}.{variant}({#fields ", "}{value}{/fields})'
+unit_variant_value = "{enum}.{variant}"
+
+[project]
+directory = "kotlin"
+source_file = "Main.{extension}"
+source = '''
+package {package}
+
+{code}
+'''
+functions = "{code}"
+separator = "\n\n"
+
+[check]
+commands = [
+    ["kotlinc", "Main.kt", "Check.kt", "-include-runtime", "-d", "check.jar"],
+    ["java", "-jar", "check.jar"],
+]
+
+[[check.files]]
+name = "Check.kt"
+content = '''
+package {package}
+
+fun main() {
+    println(check())
+}
+'''
+"#;
+
+const PYTHON_LANGUAGE: &str = r#"name = "Python"
+extension = "py"
+function = '''
+def {name}({#parameters ", "}{name}: {type}{/parameters}) -> {return_type}:
+{body}'''
+
+[types]
+"i8" = "int"
+"i16" = "int"
+"i32" = "int"
+"i64" = "int"
+"i128" = "int"
+"isize" = "int"
+"u8" = "int"
+"u16" = "int"
+"u32" = "int"
+"u64" = "int"
+"u128" = "int"
+"usize" = "int"
+"f32" = "float"
+"f64" = "float"
+"bool" = "bool"
+"char" = "str"
+"String" = "str"
+"&str" = "str"
+"()" = "None"
+"Vec" = "list[{0}]"
+"Option" = "{0} | None"
+"HashMap" = "dict[{0}, {1}]"
+"tuple" = "tuple[{elements}]"
+
+[syntax]
+blocks = "indentation"
+body_indent = "  "
+indent = "  "
+statement_end = ""
+declarations = [
+    "{name} = {value}", "{name} = {value}", "{name}: {type} = {value}",
+    "{name}: {type} = {value}",
+]
+if_header = "if {condition}"
+else_if_header = "elif {condition}"
+while_header = "while {condition}"
+for_header = "for {variable} in range({start}, {end})"
+cast = "{type}({value})"
+wrapping_cast = "({value} % {modulus})"
+and = "and"
+or = "or"
+not = "not "
+loose_not = true
+booleans = ["False", "True"]
+truncating_division = "_div({left}, {right})"
+truncating_remainder = "_rem({left}, {right})"
+continue_keyword = "continue"
+self_value = "self"
+self_field = "self.{field}"
+static_call = "{type}.{name}({arguments})"
+reserved = [
+    "False", "None", "True", "and", "assert", "class", "def", "del", "elif", "except",
+    "finally", "from", "global", "import", "is", "lambda", "nonlocal", "not", "or",
+    "pass", "raise", "try", "with", "yield",
+]
+escape = "{name}_"
+
+[declarations]
+record = '''
+@dataclass
+class {name}:
+{#fields}  {name}: {type}
+{/fields}{^fields}{^members}  pass
+{/members}{/fields}{members}'''
+mutable_record = '''
+@dataclass
+class {name}:
+{#fields}  {name}: {type}
+{/fields}{^fields}{^members}  pass
+{/members}{/fields}{members}'''
+plain_enum = '''
+class {name}(Enum):
+{variants}
+{members}'''
+plain_variant = "  {variant} = auto()"
+plain_variant_separator = "\n"
+union = '''
+class {name}:
+{^members}  pass
+{/members}{members}{variants}'''
+variant = '''
+
+
+@dataclass
+class {enum}{variant}({enum}):
+{#fields}  {name}: {type}
+{/fields}'''
+unit_variant = '''
+
+
+class {enum}{variant}({enum}):
+  pass
+'''
+members = "\n{methods}\n"
+method = '''
+  def {name}({receiver}{?parameters}, {/parameters}{#parameters ", "}{name}: {type}{/parameters}) -> {return_type}:
+{body}'''
+mutating_method = '''
+  def {name}({receiver}{?parameters}, {/parameters}{#parameters ", "}{name}: {type}{/parameters}) -> {return_type}:
+{body}'''
+union_method = '''
+  def {name}({receiver}{?parameters}, {/parameters}{#parameters ", "}{name}: {type}{/parameters}) -> {return_type}:
+{body}'''
+static_method = '''
+  @staticmethod
+  def {name}({#parameters ", "}{name}: {type}{/parameters}) -> {return_type}:
+{body}'''
+static_block = "{methods}"
+method_body_indent = "    "
+static_body_indent = "    "
+receiver = ["self", "self", "self"]
+construct = '{type}({#fields ", "}{value}{/fields})'
+plain_variant_value = "{enum}.{variant}"
+variant_value = '{enum}{variant}({#fields ", "}{value}{/fields})'
+unit_variant_value = "{enum}{variant}()"
+
+[project]
+directory = "python"
+source_file = "{package_name}.{extension}"
+source = '''
+from __future__ import annotations
+
+from dataclasses import dataclass
+from enum import Enum, auto
+
+
+# Integer division and remainder toward zero, as in Rust
+def _div(left: int, right: int) -> int:
+  quotient = abs(left) // abs(right)
+  return quotient if (left < 0) == (right < 0) else -quotient
+
+
+def _rem(left: int, right: int) -> int:
+  return left - right * _div(left, right)
+
+
+{code}
+'''
+functions = "{code}"
+separator = "\n\n\n"
+
+[check]
+commands = [["python3", "check.py"]]
+
+[[check.files]]
+name = "check.py"
+content = '''
+from {package_name} import check
+
+print(check())
+'''
+"#;
+
+const RUST_LANGUAGE: &str = r#"name = "Rust"
+extension = "rs"
+function = '''
+fn {name}({#parameters ", "}{name}: {type}{/parameters}) -> {return_type} {
+{body}
+}'''
+
+[types]
+"i8" = "i8"
+"i16" = "i16"
+"i32" = "i32"
+"i64" = "i64"
+"i128" = "i128"
+"isize" = "isize"
+"u8" = "u8"
+"u16" = "u16"
+"u32" = "u32"
+"u64" = "u64"
+"u128" = "u128"
+"usize" = "usize"
+"f32" = "f32"
+"f64" = "f64"
+"bool" = "bool"
+"char" = "char"
+"String" = "String"
+"&str" = "&str"
+"()" = "()"
+"Vec" = "Vec<{0}>"
+"Option" = "Option<{0}>"
+"HashMap" = "HashMap<{0}, {1}>"
+"tuple" = "({elements})"
+
+[syntax]
+blocks = "braces"
+body_indent = "  "
+indent = "  "
+statement_end = ";"
+declarations = [
+    "let {name} = {value}", "let mut {name} = {value}", "let {name}: {type} = {value}",
+    "let mut {name}: {type} = {value}",
+]
+if_header = "if {condition}"
+else_if_header = "else if {condition}"
+while_header = "while {condition}"
+for_header = "for {variable} in {start}..{end}"
+inclusive_for_header = "for {variable} in {start}..={end}"
+cast = "{value} as {type}"
+and = "&&"
+or = "||"
+not = "!"
+booleans = ["false", "true"]
+continue_keyword = "continue"
+self_value = "self"
+self_field = "self.{field}"
+static_call = "{type}::{name}({arguments})"
+reserved = [
+    "abstract", "async", "await", "become", "box", "const", "crate", "do", "dyn", "enum",
+    "extern", "final", "fn", "impl", "let", "loop", "macro", "match", "mod", "move",
+    "mut", "override", "priv", "pub", "ref", "static", "struct", "trait", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "yield",
+]
+escape = "r#{name}"
+
+[declarations]
+record = '''
+#[derive(Debug, Clone, PartialEq)]
+struct {name} {
+{#fields}  {name}: {type},
+{/fields}}
+{members}'''
+mutable_record = '''
+#[derive(Debug, Clone, PartialEq)]
+struct {name} {
+{#fields}  {name}: {type},
+{/fields}}
+{members}'''
+plain_variant = "  {variant}"
+plain_variant_separator = ",\n"
+union = '''
+#[derive(Debug, Clone, PartialEq)]
+enum {name} {
+{variants}}
+{members}'''
+variant = "  {variant} { {#fields \", \"}{name}: {type}{/fields} },\n"
+unit_variant = "  {variant},\n"
+members = '''
+
+impl {name} {
+{methods}
+}
+'''
+method = '''
+  fn {name}({receiver}{?parameters}, {/parameters}{#parameters ", "}{name}: {type}{/parameters}) -> {return_type} {
+{body}
+  }'''
+mutating_method = '''
+  fn {name}({receiver}{?parameters}, {/parameters}{#parameters ", "}{name}: {type}{/parameters}) -> {return_type} {
+{body}
+  }'''
+union_method = '''
+  fn {name}({receiver}{?parameters}, {/parameters}{#parameters ", "}{name}: {type}{/parameters}) -> {return_type} {
+{body}
+  }'''
+static_method = '''
+  fn {name}({#parameters ", "}{name}: {type}{/parameters}) -> {return_type} {
+{body}
+  }'''
+static_block = "{methods}"
+method_body_indent = "    "
+static_body_indent = "    "
+receiver = ["&self", "&mut self", "self"]
+construct = '{type} { {#fields ", "}{name}: {value}{/fields} }'
+plain_variant_value = "{enum}::{variant}"
+variant_value = '{enum}::{variant} { {#fields ", "}{name}: {value}{/fields} }'
+unit_variant_value = "{enum}::{variant}"
+
+[project]
+directory = "rust"
+source_file = "src/lib.{extension}"
+source = "{code}\n"
+functions = "{code}"
+separator = "\n\n"
+
+[project.build_file]
+name = "Cargo.toml"
+content = '''
+[package]
+name = "{package_name}"
+version = "0.1.0"
+edition = "2021"
+'''
+
+[check]
+commands = [["rustc", "--edition", "2021", "-o", "check", "check.rs"], ["./check"]]
+
+[[check.files]]
+name = "check.rs"
+content = '''
+include!("src/lib.rs");
+
+fn main() {
+    println!("{}", check());
+}
+'''
+"#;
+
+const GO_LANGUAGE: &str = r#"name = "Go"
+extension = "go"
+function = '''
+func {name}({#parameters ", "}{name} {type}{/parameters}){?return_type} {return_type}{/return_type} {
+{body}
+}'''
+
+[types]
+"i8" = "int8"
+"i16" = "int16"
+"i32" = "int32"
+"i64" = "int64"
+"isize" = "int"
+"u8" = "uint8"
+"u16" = "uint16"
+"u32" = "uint32"
+"u64" = "uint64"
+"usize" = "uint"
+"f32" = "float32"
+"f64" = "float64"
+"bool" = "bool"
+"char" = "rune"
+"String" = "string"
+"&str" = "string"
+"()" = ""
+"Vec" = "[]{0}"
+"Option" = "*{0}"
+"HashMap" = "map[{0}]{1}"
+
+[syntax]
+blocks = "braces"
+body_indent = "\t"
+indent = "\t"
+statement_end = ""
+declarations = [
+    "{name} := {value}", "{name} := {value}", "var {name} {type} = {value}",
+    "var {name} {type} = {value}",
+]
+if_header = "if {condition}"
+else_if_header = "else if {condition}"
+while_header = "for {condition}"
+for_header = "for {variable} := {type}({start}); {variable} < {end}; {variable}++"
+inclusive_for_header = "for {variable} := {type}({start}); {variable} <= {end}; {variable}++"
+cast = "{type}({value})"
+and = "&&"
+or = "||"
+not = "!"
+booleans = ["false", "true"]
+continue_keyword = "continue"
+self_value = "self"
+self_field = "self.{field}"
+static_call = "{name}{type}({arguments})"
+reserved = [
+    "case", "chan", "const", "default", "defer", "fallthrough", "func", "go", "goto",
+    "import", "interface", "map", "package", "range", "select", "switch", "var",
+]
+escape = "{name}_"
+
+[declarations]
+record = '''
+type {name} struct {
+{#fields}	{name} {type}
+{/fields}}
+{members}'''
+mutable_record = '''
+type {name} struct {
+{#fields}	{name} {type}
+{/fields}}
+{members}'''
+plain_enum = '''
+type {name} int
+
+const (
+{variants}
+)
+{members}'''
+plain_variant = "\t{enum}{variant} {enum} = {index}"
+plain_variant_separator = "\n"
+union = '''
+type {name} interface {
+	is{name}()
+}
+{variants}{members}'''
+variant = '''
+
+type {enum}{variant} struct {
+{#fields}	{name} {type}
+{/fields}}
+
+func ({enum}{variant}) is{enum}() {}
+'''
+unit_variant = '''
+
+type {enum}{variant} struct{}
+
+func ({enum}{variant}) is{enum}() {}
+'''
+members = "\n{methods}\n"
+method = '''
+func (self {type}) {name}({#parameters ", "}{name} {type}{/parameters}){?return_type} {return_type}{/return_type} {
+{body}
+}'''
+mutating_method = '''
+func (self *{type}) {name}({#parameters ", "}{name} {type}{/parameters}){?return_type} {return_type}{/return_type} {
+{body}
+}'''
+static_method = '''
+func {name}{type}({#parameters ", "}{name} {type}{/parameters}){?return_type} {return_type}{/return_type} {
+{body}
+}'''
+static_block = "{methods}"
+method_body_indent = "\t"
+static_body_indent = "\t"
+construct = '{type}{{#fields ", "}{name}: {value}{/fields}}'
+plain_variant_value = "{enum}{variant}"
+variant_value = '{enum}{variant}{{#fields ", "}{name}: {value}{/fields}}'
+unit_variant_value = "{enum}{variant}{}"
+
+[project]
+directory = "go"
+source_file = "{package_name}.{extension}"
+source = '''
+package {package_name}
+
+{code}
+'''
+functions = "{code}"
+separator = "\n\n"
+
+[project.build_file]
+name = "go.mod"
+content = '''
+module {package_path}
+
+go 1.21
+'''
+
+# Only package main runs
+[check]
+package = "main"
+commands = [["go", "run", "{package_name}.go", "check.go"]]
+
+[[check.files]]
+name = "check.go"
+content = '''
+package main
+
+import "fmt"
+
+func main() {
+	fmt.Println(check())
+}
+'''
+"#;
+
+const RUBY_LANGUAGE: &str = r##"name = "Ruby"
+extension = "rb"
+function = '''
+def {name}({#parameters ", "}{name}{/parameters})
+{body}
+end'''
+
+[types]
+"i8" = "Integer"
+"i16" = "Integer"
+"i32" = "Integer"
+"i64" = "Integer"
+"i128" = "Integer"
+"isize" = "Integer"
+"u8" = "Integer"
+"u16" = "Integer"
+"u32" = "Integer"
+"u64" = "Integer"
+"u128" = "Integer"
+"usize" = "Integer"
+"f32" = "Float"
+"f64" = "Float"
+"bool" = "Boolean"
+"char" = "String"
+"String" = "String"
+"&str" = "String"
+"()" = "void"
+"Vec" = "Array<{0}>"
+"Option" = "{0}, nil"
+"HashMap" = "Hash{{0} => {1}}"
+"tuple" = "Array({elements})"
+
+[syntax]
+blocks = "end"
+body_indent = "  "
+indent = "  "
+statement_end = ""
+declarations = ["{name} = {value}", "{name} = {value}", "{name} = {value}", "{name} = {value}"]
+if_header = "if {condition}"
+else_if_header = "elsif {condition}"
+while_header = "while {condition}"
+for_header = "({start}...{end}).each do |{variable}|"
+inclusive_for_header = "({start}..{end}).each do |{variable}|"
+cast = "{type}({value})"
+wrapping_cast = "({value} % {modulus})"
+and = "&&"
+or = "||"
+not = "!"
+booleans = ["false", "true"]
+truncating_division = "_div({left}, {right})"
+truncating_remainder = "_rem({left}, {right})"
+continue_keyword = "next"
+string_escapes = [["#{", "\\#{"]]
+self_value = "self"
+self_field = "self.{field}"
+static_call = "{type}.{name}({arguments})"
+reserved = [
+    "alias", "and", "begin", "case", "class", "def", "defined", "do", "elsif", "end",
+    "ensure", "module", "new", "next", "nil", "not", "or", "redo", "rescue", "retry",
+    "undef", "unless", "until", "when", "yield",
+]
+escape = "{name}_"
+
+[declarations]
+record = '''
+{name} = Struct.new({#fields ", "}:{name}{/fields}) do
+{members}end'''
+mutable_record = '''
+{name} = Struct.new({#fields ", "}:{name}{/fields}) do
+{members}end'''
+plain_enum = '''
+module {name}
+{variants}
+end'''
+plain_variant = "  {variant} = :{variant}"
+plain_variant_separator = "\n"
+union = '''
+class {name}
+{members}end
+{variants}'''
+variant = '''
+
+class {enum}::{variant} < {enum}
+  attr_reader {#fields ", "}:{name}{/fields}
+
+  def initialize({#fields ", "}{name}{/fields})
+{#fields}    @{name} = {name}
+{/fields}  end
+end
+'''
+unit_variant = '''
+
+class {enum}::{variant} < {enum}
+end
+'''
+members = "{methods}\n"
+method = '''
+  def {name}({#parameters ", "}{name}{/parameters})
+{body}
+  end'''
+mutating_method = '''
+  def {name}({#parameters ", "}{name}{/parameters})
+{body}
+  end'''
+union_method = '''
+  def {name}({#parameters ", "}{name}{/parameters})
+{body}
+  end'''
+static_method = '''
+  def self.{name}({#parameters ", "}{name}{/parameters})
+{body}
+  end'''
+static_block = "{methods}"
+method_body_indent = "    "
+static_body_indent = "    "
+construct = '{type}.new({#fields ", "}{value}{/fields})'
+plain_variant_value = "{enum}::{variant}"
+variant_value = '{enum}::{variant}.new({#fields ", "}{value}{/fields})'
+unit_variant_value = "{enum}::{variant}.new"
+
+[project]
+directory = "ruby"
+source_file = "{package_name}.{extension}"
+source = '''
+# Integer division and remainder toward zero, as in Rust
+def _div(left, right)
+  left.quo(right).truncate
+end
+
+def _rem(left, right)
+  left.remainder(right)
+end
+
+{code}
+'''
+functions = "{code}"
+separator = "\n\n"
+
+[check]
+commands = [["ruby", "check.rb"]]
+
+[[check.files]]
+name = "check.rb"
+content = '''
+require_relative "{package_name}"
+
+puts check
+'''
+"##;
+
+const SWIFT_LANGUAGE: &str = r#"name = "Swift"
+extension = "swift"
+function = '''
+func {name}({#parameters ", "}_ {name}: {type}{/parameters}) -> {return_type} {
+{body}
+}'''
+
+[types]
+"i8" = "Int8"
+"i16" = "Int16"
+"i32" = "Int32"
+"i64" = "Int64"
+"isize" = "Int"
+"u8" = "UInt8"
+"u16" = "UInt16"
+"u32" = "UInt32"
+"u64" = "UInt64"
+"usize" = "Int"
+"f32" = "Float"
+"f64" = "Double"
+"bool" = "Bool"
+"char" = "Character"
+"String" = "String"
+"&str" = "String"
+"()" = "Void"
+"Vec" = "[{0}]"
+"Option" = "{0}?"
+"HashMap" = "[{0}: {1}]"
+"tuple" = "({elements})"
+
+[syntax]
+blocks = "braces"
+body_indent = "  "
+indent = "  "
+statement_end = ""
+declarations = [
+    "let {name} = {value}", "var {name} = {value}", "let {name}: {type} = {value}",
+    "var {name}: {type} = {value}",
+]
+if_header = "if {condition}"
+else_if_header = "else if {condition}"
+while_header = "while {condition}"
+for_header = "for {variable} in {start}..<{end}"
+inclusive_for_header = "for {variable} in {start}...{end}"
+cast = "{type}({value})"
+wrapping_cast = "{type}(truncatingIfNeeded: {value})"
+and = "&&"
+or = "||"
+not = "!"
+booleans = ["false", "true"]
+continue_keyword = "continue"
+self_value = "self"
+self_field = "self.{field}"
+static_call = "{type}.{name}({arguments})"
+reserved = [
+    "associatedtype", "case", "catch", "class", "default", "defer", "deinit", "extension",
+    "fallthrough", "fileprivate", "func", "guard", "init", "inout", "internal", "is",
+    "nil", "operator", "private", "protocol", "public", "repeat", "rethrows", "subscript",
+    "switch", "throw", "throws", "typealias", "var",
+]
+escape = "{name}_"
+
+[declarations]
+record = '''
+struct {name} {
+{#fields}  let {name}: {type}
+{/fields}{members}}'''
+mutable_record = '''
+struct {name} {
+{#fields}  var {name}: {type}
+{/fields}{members}}'''
+plain_enum = '''
+enum {name} {
+{variants}
+{members}}'''
+plain_variant = "  case {variant}"
+plain_variant_separator = "\n"
+union = '''
+indirect enum {name} {
+{variants}{members}}'''
+variant = "  case {variant}({#fields \", \"}{name}: {type}{/fields})\n"
+unit_variant = "  case {variant}\n"
+members = "\n{methods}\n"
+method = '''
+  func {name}({#parameters ", "}_ {name}: {type}{/parameters}) -> {return_type} {
+{body}
+  }'''
+mutating_method = '''
+  mutating func {name}({#parameters ", "}_ {name}: {type}{/parameters}) -> {return_type} {
+{body}
+  }'''
+union_method = '''
+  func {name}({#parameters ", "}_ {name}: {type}{/parameters}) -> {return_type} {
+{body}
+  }'''
+static_method = '''
+  static func {name}({#parameters ", "}_ {name}: {type}{/parameters}) -> {return_type} {
+{body}
+  }'''
+static_block = "{methods}"
+method_body_indent = "    "
+static_body_indent = "    "
+construct = '{type}({#fields ", "}{name}: {value}{/fields})'
+plain_variant_value = "{enum}.{variant}"
+variant_value = '{enum}.{variant}({#fields ", "}{name}: {value}{/fields})'
+unit_variant_value = "{enum}.{variant}"
+
+[project]
+directory = "swift"
+source_file = "{package_name}.{extension}"
+source = "{code}\n"
+functions = "{code}"
+separator = "\n\n"
+
+[check]
+commands = [["swiftc", "-o", "check", "{package_name}.swift", "main.swift"], ["./check"]]
+
+[[check.files]]
+name = "main.swift"
+content = '''
+print(check())
+'''
+"#;
+
+const CSHARP_LANGUAGE: &str = r#"name = "C#"
+extension = "cs"
+function = '''
+  public static {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+
+[types]
+"i8" = "sbyte"
+"i16" = "short"
+"i32" = "int"
+"i64" = "long"
+"i128" = "Int128"
+"isize" = "nint"
+"u8" = "byte"
+"u16" = "ushort"
+"u32" = "uint"
+"u64" = "ulong"
+"u128" = "UInt128"
+"usize" = "nuint"
+"f32" = "float"
+"f64" = "double"
+"bool" = "bool"
+"char" = "char"
+"String" = "string"
+"&str" = "string"
+"()" = "void"
+"Vec" = "List<{0}>"
+"Option" = "{0}?"
+"HashMap" = "Dictionary<{0}, {1}>"
+"tuple" = "({elements})"
+
+[syntax]
+blocks = "braces"
+body_indent = "    "
+indent = "  "
+statement_end = ";"
+declarations = [
+    "var {name} = {value}", "var {name} = {value}", "{type} {name} = {value}",
+    "{type} {name} = {value}",
+]
+if_header = "if ({condition})"
+else_if_header = "else if ({condition})"
+while_header = "while ({condition})"
+for_header = "for ({type} {variable} = {start}; {variable} < {end}; {variable}++)"
+inclusive_for_header = "for ({type} {variable} = {start}; {variable} <= {end}; {variable}++)"
+cast = "({type}) {value}"
+and = "&&"
+or = "||"
+not = "!"
+booleans = ["false", "true"]
+continue_keyword = "continue"
+self_value = "this"
+self_field = "this.{field}"
+static_call = "{type}.{name}({arguments})"
+reserved = [
+    "abstract", "base", "bool", "byte", "case", "catch", "char", "checked", "class",
+    "decimal", "default", "delegate", "double", "event", "explicit", "finally", "fixed",
+    "float", "foreach", "goto", "implicit", "int", "interface", "internal", "is", "lock",
+    "long", "namespace", "new", "null", "object", "operator", "out", "override", "params",
+    "private", "protected", "public", "readonly", "sbyte", "sealed", "short", "sizeof",
+    "stackalloc", "static", "string", "switch", "this", "throw", "try", "uint", "ulong",
+    "unchecked", "ushort", "using", "virtual", "void", "volatile",
+]
+escape = "{name}_"
+
+[declarations]
+record = 'public record {name}({#fields ", "}{type} {name}{/fields}) {{members}}'
+mutable_record = '''
+public class {name} {
+{#fields}  public {type} {name};
+{/fields}
+  public {name}({#fields ", "}{type} {name}{/fields}) {
+{#fields}    this.{name} = {name};
+{/fields}  }
+{members}}'''
+plain_enum = '''
+public enum {name} {
+{variants}
+}'''
+plain_variant = "  {variant}"
+plain_variant_separator = ",\n"
+union = '''
+public abstract record {name} {
+{variants}{members}}'''
+variant = "  public sealed record {variant}({#fields \", \"}{type} {name}{/fields}) : {enum};\n"
+unit_variant = "  public sealed record {variant}() : {enum};\n"
+members = "\n{methods}\n"
+method = '''
+  public {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+mutating_method = '''
+  public {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+union_method = '''
+  public {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+static_method = '''
+  public static {return_type} {name}({#parameters ", "}{type} {name}{/parameters}) {
+{body}
+  }'''
+static_block = "{methods}"
+method_body_indent = "    "
+static_body_indent = "    "
+construct = 'new {type}({#fields ", "}{value}{/fields})'
+plain_variant_value = "{enum}.{variant}"
+variant_value = 'new {enum}.{variant}({#fields ", "}{value}{/fields})'
+unit_variant_value = "new {enum}.{variant}()"
+
+[project]
+directory = "csharp"
+source_file = "Program.{extension}"
+source = '''
+using System;
+using System.Collections.Generic;
+
+namespace {namespace};
+
+{code}
+'''
+functions = '''
+public static class Program {
+{code}
+}'''
+separator = "\n\n"
+
+[check]
+commands = [["dotnet", "build", "--nologo", "--verbosity", "quiet", "--output", "bin"], ["dotnet", "bin/Check.dll"]]
+
+[[check.files]]
+name = "Check.csproj"
+content = '''
+<Project Sdk="Microsoft.NET.Sdk">
+  <PropertyGroup>
+    <OutputType>Exe</OutputType>
+    <TargetFramework>net8.0</TargetFramework>
+  </PropertyGroup>
+</Project>
+'''
+
+[[check.files]]
+name = "Check.cs"
+content = '''
+System.Console.WriteLine({namespace}.Program.check());
+'''
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BlockStyle {
+    // `header {` ... `}`
+    Braces,
+    // `header:` and indented lines, `pass` when empty
+    Indentation,
+    // `header` ... `end`
+    End,
+}
+
+// Precedence of literals, names and calls, and of prefix operators, next to BinaryOp::precedence
+const PRIMARY_PRECEDENCE: u8 = 90;
+const UNARY_PRECEDENCE: u8 = 80;
+// Python's `not`, which binds looser than comparisons
+const LOOSE_NOT_PRECEDENCE: u8 = 25;
+
+// How a target language spells statements and operators. Templates use the placeholders
+// `{name}`, `{type}`, `{value}`, `{condition}`, `{variable}`, `{start}` and `{end}`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Syntax {
+    blocks: BlockStyle,
+    // Prefix of top-level body lines, and of each further nesting level
+    body_indent: String,
+    indent: String,
+    statement_end: String,
+    // Immutable and mutable declarations, then the same with an explicit type
+    declarations: [String; 4],
+    if_header: String,
+    else_if_header: String,
+    while_header: String,
+    for_header: String,
+    // Without one, `a..=b` is emitted with `for_header` up to `b + 1`
+    inclusive_for_header: Option<String>,
+    cast: String,
+    // Casts of integers to u8, u16 and u32 from `{value}`, `{type}` and `{modulus}`, 2 to the
+    // power of their bits, where the target's types for them are wider or its casts do not
+    // wrap around as Rust's do
+    #[serde(default)]
+    wrapping_cast: Option<String>,
+    and: String,
+    or: String,
+    not: String,
+    // `not` binds looser than comparisons, as in Python
+    #[serde(default)]
+    loose_not: bool,
+    booleans: [String; 2],
+    // Calls from `{left}` and `{right}` that divide two integers and take their remainder
+    // toward zero as Rust does, where the operators floor as in Python and Ruby
+    #[serde(default)]
+    truncating_division: Option<String>,
+    #[serde(default)]
+    truncating_remainder: Option<String>,
+    // Suffixes of integer literals next to values of these Rust types, such as Kotlin's `1L`,
+    // where the literal would otherwise be an `Int`
+    #[serde(default)]
+    integer_suffixes: HashMap<String, String>,
+    continue_keyword: String,
+    // Replacements that keep string literals from interpolating, e.g. Kotlin's `$`
+    #[serde(default)]
+    string_escapes: Vec<(String, String)>,
+    // `self` inside methods, and `{field}` of it
+    self_value: String,
+    self_field: String,
+    // `{name}` of `{type}` called with `{arguments}`
+    static_call: String,
+    // Names that are keywords in the target, and how such a `{name}` is escaped
+    #[serde(default)]
+    reserved: Vec<String>,
+    escape: String,
+}
+
+// How a target language declares structs, enums and their methods, and builds their values.
+// Declarations use `{name}`, `{members}` and the `fields` list with `{name}` and `{type}` per
+// field; enums also use `{variants}` and `{variant_names}`, and each variant `{enum}`,
+// `{variant}`, `{index}` and its `fields`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TypeSyntax {
+    record: String,
+    // Used for structs with `&mut self` methods
+    mutable_record: String,
+    // Enums without data; without `{members}` it only takes enums without methods
+    plain_enum: Option<String>,
+    plain_variant: String,
+    plain_variant_separator: String,
+    // Enums with data, as a tagged union or a sealed hierarchy
+    union: String,
+    variant: String,
+    unit_variant: String,
+    // The `{methods}` of type `{name}`, separated by blank lines
+    members: String,
+    // Methods from `{type}`, `{name}`, `{receiver}`, the `parameters` list, `{return_type}`
+    // and `{body}`
+    method: String,
+    mutating_method: String,
+    // None where the union form cannot have methods
+    union_method: Option<String>,
+    static_method: String,
+    // Wraps the associated functions, such as Kotlin's companion object
+    static_block: String,
+    method_body_indent: String,
+    static_body_indent: String,
+    // Explicit `self` parameter for shared, mutable and owned receivers
+    receiver: Option<[String; 3]>,
+    // Values from `{type}` or `{enum}` and `{variant}`, and the `fields` list with `{name}`
+    // and `{value}` in field order
+    construct: String,
+    plain_variant_value: String,
+    variant_value: String,
+    unit_variant_value: String,
+}
+
+// Everything needed to print one module in one target language
+struct Target<'a> {
+    language: &'a str,
+    syntax: &'a Syntax,
+    type_syntax: &'a TypeSyntax,
+    types: &'a TypeMap,
+    module: &'a Module,
+}
+
+impl<'a> Target<'a> {
+    // Rust names that are keywords in the target, such as `new` in Java, are escaped
+    fn identifier(&self, name: &str) -> String {
+        if self.syntax.reserved.iter().any(|reserved| reserved == name) {
+            fill(&self.syntax.escape, &[("name", name)])
+        } else {
+            name.to_owned()
+        }
+    }
+
+    // Declarations and values of an enum must agree on its form
+    fn is_plain_enum(&self, item: &RustEnum) -> bool {
+        match &self.type_syntax.plain_enum {
+            Some(template) => item.is_plain() && (item.methods.is_empty() || template.contains("{members}")),
+            None => false,
+        }
+    }
+
+    // `Self` in the methods of `owner` stands for the owner
+    fn map_type(&self, ty: &RustType, owner: Option<&str>) -> Result<String, String> {
+        match owner {
+            Some(owner) => self.types.map(&resolve_self(ty, owner), false),
+            None => self.types.map(ty, false),
+        }
+    }
+}
+
+fn resolve_self(ty: &RustType, owner: &str) -> RustType {
+    RustType {
+        name: if ty.name == "Self" { owner.to_owned() } else { ty.name.clone() },
+        arguments: ty.arguments.iter().map(|argument| resolve_self(argument, owner)).collect(),
+    }
+}
+
+// The `fields` list of a struct or an enum variant
+fn field_values(target: &Target, fields: &[(String, RustType)], owner: &str) -> Result<Vec<TemplateValues<'static>>, String> {
+    fields
+        .iter()
+        .map(|(name, ty)| {
+            Ok(TemplateValues::default()
+                .text("name", target.identifier(name))
+                .text("type", target.map_type(ty, Some(owner))?))
+        })
+        .collect()
+}
+
+// The `parameters` list of a function or method, `owner` being the type of a method
+fn parameter_values(
+    parameters: &[(String, RustType)],
+    target: &Target,
+    owner: Option<&str>,
+) -> Result<Vec<TemplateValues<'static>>, String> {
+    parameters
+        .iter()
+        .map(|(name, ty)| {
+            Ok(TemplateValues::default()
+                .text("name", target.identifier(name))
+                .text("type", target.map_type(ty, owner)?))
+        })
+        .collect()
+}
+
+fn declare_struct(item: &RustStruct, target: &Target) -> Result<String, String> {
+    let type_syntax = target.type_syntax;
+    let template = if item.is_mutable() { &type_syntax.mutable_record } else { &type_syntax.record };
+    let values = TemplateValues::default()
+        .text("name", item.name.clone())
+        .text("members", declare_methods(&item.name, &item.methods, false, target)?)
+        .list("fields", field_values(target, &item.fields, &item.name)?);
+    Ok(render(template, &values))
+}
+
+fn declare_enum(item: &RustEnum, target: &Target) -> Result<String, String> {
+    let type_syntax = target.type_syntax;
+    let plain = target.is_plain_enum(item);
+    let members = declare_methods(&item.name, &item.methods, !plain, target)?;
+    let variants = if plain {
+        item.variants
+            .iter()
+            .enumerate()
+            .map(|(index, variant)| {
+                fill(
+                    &type_syntax.plain_variant,
+                    &[("enum", &item.name), ("variant", &variant.name), ("index", &index.to_string())],
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(&type_syntax.plain_variant_separator)
+    } else {
+        let mut variants = String::new();
+        for variant in &item.variants {
+            let template = if variant.fields.is_empty() { &type_syntax.unit_variant } else { &type_syntax.variant };
+            let values = TemplateValues::default()
+                .text("enum", item.name.clone())
+                .text("variant", variant.name.clone())
+                .list("fields", field_values(target, &variant.fields, &item.name)?);
+            variants.push_str(&render(template, &values));
+        }
+        variants
+    };
+    let variant_names = item.variants.iter().map(|variant| variant.name.as_str()).collect::<Vec<&str>>().join(", ");
+
+    let template = match &type_syntax.plain_enum {
+        Some(template) if plain => template,
+        _ => &type_syntax.union,
+    };
+    Ok(fill(
+        template,
+        &[("name", &item.name), ("variants", &variants), ("variant_names", &variant_names), ("members", &members)],
+    ))
+}
+
+// The methods of `owner` followed by its associated functions, or nothing without any
+fn declare_methods(owner: &str, methods: &[IrFunction], in_union: bool, target: &Target) -> Result<String, String> {
+    let type_syntax = target.type_syntax;
+    let mut instance_methods = Vec::new();
+    let mut static_methods = Vec::new();
+    for method in methods {
+        let (template, body_indent) = match method.receiver {
+            Some(_) if in_union => {
+                let template = type_syntax.union_method.as_ref().ok_or_else(|| {
+                    format!("{} has no methods on enums with data such as {}::{}", target.language, owner, method.name)
+                })?;
+                (template, &type_syntax.method_body_indent)
+            }
+            Some(Receiver::Mutable) => (&type_syntax.mutating_method, &type_syntax.method_body_indent),
+            Some(_) => (&type_syntax.method, &type_syntax.method_body_indent),
+            None => (&type_syntax.static_method, &type_syntax.static_body_indent),
+        };
+        let receiver = match (method.receiver, &type_syntax.receiver) {
+            (Some(receiver), Some(receivers)) => receivers[receiver.index()].clone(),
+            _ => String::new(),
+        };
+
+        let values = TemplateValues::default()
+            .text("type", owner)
+            .text("name", target.identifier(&method.name))
+            .text("receiver", receiver)
+            .text("return_type", target.map_type(&method.return_type, Some(owner))?)
+            .text("body", emit_body(method, target, body_indent, Some(owner))?)
+            .list("parameters", parameter_values(&method.parameters, target, Some(owner))?);
+        let text = render(template, &values);
+        if method.receiver.is_some() {
+            instance_methods.push(text);
+        } else {
+            static_methods.push(text);
+        }
+    }
+
+    if !static_methods.is_empty() {
+        instance_methods.push(fill(&type_syntax.static_block, &[("methods", &static_methods.join("\n\n"))]));
+    }
+    if instance_methods.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(fill(&type_syntax.members, &[("name", owner), ("methods", &instance_methods.join("\n\n"))]))
+}
+
+// Values for `render`: text placeholders, and lists whose items have values of their own
+#[derive(Debug, Clone, Default)]
+struct TemplateValues<'a> {
+    text: Vec<(&'a str, String)>,
+    lists: Vec<(&'a str, Vec<TemplateValues<'a>>)>,
+}
+
+impl<'a> TemplateValues<'a> {
+    fn text(mut self, key: &'a str, value: impl Into<String>) -> Self {
+        self.text.push((key, value.into()));
+        self
+    }
+
+    fn list(mut self, key: &'a str, items: Vec<TemplateValues<'a>>) -> Self {
+        self.lists.push((key, items));
+        self
+    }
+}
+
+enum TemplateValue<'v, 'a> {
+    Text(&'v str),
+    List(&'v [TemplateValues<'a>]),
+}
+
+// A tag between braces: `{key}`, or `{#key "separator"}`, `{?key}`, `{^key}` and `{/key}`
+enum Tag<'t> {
+    Placeholder(&'t str),
+    Section { kind: char, key: &'t str, separator: &'t str },
+}
+
+// Templates of all language files use the same syntax. `{key}` is replaced by its value in one
+// pass, and braces around anything but a key stay as they are, so literal braces need no
+// escaping; language files are checked for unknown keys when loaded, see check_template.
+// `{#list ", "}...{/list}` repeats its body for each item of a list, separated by the optional
+// quoted text; inside, the keys of the item hide the outer ones. `{?key}...{/key}` keeps its
+// body when a value or list is not empty, and `{^key}...{/key}` when it is.
+fn render(template: &str, values: &TemplateValues) -> String {
+    let mut rendered = String::new();
+    render_into(template, &[values], &mut rendered);
+    rendered
+}
+
+fn render_into(template: &str, scopes: &[&TemplateValues], rendered: &mut String) {
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        rendered.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        match parse_tag(after) {
+            Some((Tag::Placeholder(key), length)) => {
+                if let Some(TemplateValue::Text(value)) = lookup_value(scopes, key) {
+                    rendered.push_str(value);
+                    rest = &after[length..];
+                    continue;
+                }
+            }
+            Some((Tag::Section { kind, key, separator }, length)) if kind != '/' => {
+                if let Some((body, after_section)) = section_body(&after[length..], key) {
+                    let value = lookup_value(scopes, key);
+                    let empty = match value {
+                        Some(TemplateValue::Text(text)) => text.is_empty(),
+                        Some(TemplateValue::List(items)) => items.is_empty(),
+                        None => true,
+                    };
+                    match (kind, value) {
+                        ('#', Some(TemplateValue::List(items))) => {
+                            for (index, item) in items.iter().enumerate() {
+                                if index > 0 {
+                                    rendered.push_str(separator);
+                                }
+                                let mut inner = scopes.to_vec();
+                                inner.push(item);
+                                render_into(body, &inner, rendered);
+                            }
+                        }
+                        ('#' | '?', _) if !empty => render_into(body, scopes, rendered),
+                        ('^', _) if empty => render_into(body, scopes, rendered),
+                        _ => {}
+                    }
+                    rest = after_section;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        rendered.push('{');
+        rest = after;
+    }
+    rendered.push_str(rest);
+}
+
+// The tag at the start of `text`, which follows a `{`, and its length including the `}`
+fn parse_tag(text: &str) -> Option<(Tag<'_>, usize)> {
+    let kind = text.chars().next().filter(|c| matches!(c, '#' | '?' | '^' | '/'));
+    let start = usize::from(kind.is_some());
+    let key_length = text[start..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(text.len() - start);
+    if key_length == 0 {
+        return None;
+    }
+    let key = &text[start..start + key_length];
+    let mut rest = &text[start + key_length..];
+    let mut separator = "";
+    if kind == Some('#') {
+        if let Some(quoted) = rest.strip_prefix(" \"") {
+            let close = quoted.find('"')?;
+            separator = &quoted[..close];
+            rest = &quoted[close + 1..];
+        }
+    }
+    if !rest.starts_with('}') {
+        return None;
+    }
+    let tag = match kind {
+        Some(kind) => Tag::Section { kind, key, separator },
+        None => Tag::Placeholder(key),
+    };
+    Some((tag, text.len() - rest.len() + 1))
+}
+
+// The body of a section up to its `{/key}` and the text after that, sections of the same key
+// nested inside included
+fn section_body<'t>(text: &'t str, key: &str) -> Option<(&'t str, &'t str)> {
+    let mut depth = 0;
+    let mut position = 0;
+    while let Some(offset) = text[position..].find('{') {
+        let open = position + offset;
+        position = open + 1;
+        if let Some((Tag::Section { kind, key: inner, .. }, length)) = parse_tag(&text[position..]) {
+            if inner != key {
+                continue;
+            }
+            if kind != '/' {
+                depth += 1;
+            } else if depth == 0 {
+                return Some((&text[..open], &text[position + length..]));
+            } else {
+                depth -= 1;
+            }
+        }
+    }
+    None
+}
+
+// Checks that `template` closes its sections and uses no placeholders besides `keys`, and
+// lists only in sections, where `{#list}` adds the keys of its items
+fn check_template(template: &str, keys: &[&str], lists: &[(&str, &[&str])]) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let after = &rest[open + 1..];
+        rest = after;
+        let Some((tag, length)) = parse_tag(after) else {
+            continue;
+        };
+        rest = &after[length..];
+        let (kind, key) = match tag {
+            Tag::Placeholder(key) if keys.contains(&key) => continue,
+            Tag::Placeholder(key) if lists.iter().any(|(list, _)| *list == key) => {
+                return Err(format!("list `{{{}}}` can only be used in sections such as `{{#{}}}`", key, key))
+            }
+            Tag::Placeholder(key) => return Err(format!("unknown placeholder `{{{}}}`", key)),
+            Tag::Section { kind: '/', key, .. } => return Err(format!("`{{/{}}}` closes no section", key)),
+            Tag::Section { kind, key, .. } => (kind, key),
+        };
+        let item_keys = lists.iter().find(|(list, _)| *list == key).map(|(_, item_keys)| *item_keys);
+        if item_keys.is_none() && !keys.contains(&key) {
+            return Err(format!("unknown placeholder `{{{}{}}}`", kind, key));
+        }
+        let (body, after_section) = section_body(rest, key).ok_or_else(|| format!("section `{{{}{}}}` is not closed", kind, key))?;
+        match item_keys {
+            Some(item_keys) if kind == '#' => {
+                let inner: Vec<&str> = keys.iter().chain(item_keys).copied().collect();
+                check_template(body, &inner, lists)?;
+            }
+            _ => check_template(body, keys, lists)?,
+        }
+        rest = after_section;
+    }
+    Ok(())
+}
+
+// The innermost value named `key`
+fn lookup_value<'v, 'a>(scopes: &[&'v TemplateValues<'a>], key: &str) -> Option<TemplateValue<'v, 'a>> {
+    scopes.iter().rev().find_map(|values| {
+        let text = values.text.iter().find(|(name, _)| *name == key).map(|(_, value)| TemplateValue::Text(value));
+        text.or_else(|| values.lists.iter().find(|(name, _)| *name == key).map(|(_, items)| TemplateValue::List(items)))
+    })
+}
+
+// Substitutes `{key}` placeholders, see `render`
+fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    let values = TemplateValues {
+        text: values.iter().map(|(key, value)| (*key, value.to_string())).collect(),
+        lists: Vec::new(),
+    };
+    render(template, &values)
+}
+
+// `owner` is the struct or enum whose method is printed
+fn emit_body(function: &IrFunction, target: &Target, body_indent: &str, owner: Option<&str>) -> Result<String, String> {
+    let mut emitter = Emitter {
+        syntax: target.syntax,
+        target,
+        body_indent,
+        owner,
+        return_type: &function.return_type,
+        scopes: vec![HashMap::new()],
+        used_names: HashSet::new(),
+        lines: Vec::new(),
+    };
+    for (name, ty) in &function.parameters {
+        emitter.declare(name, Some(ty.clone()));
+    }
+    emitter.block(&function.body, 0)?;
+    Ok(emitter.lines.join("\n"))
+}
+
+// Prints the statements of one function body for a target language
+struct Emitter<'a> {
+    syntax: &'a Syntax,
+    target: &'a Target<'a>,
+    // Prefix of top-level body lines, deeper for method bodies
+    body_indent: &'a str,
+    owner: Option<&'a str>,
+    return_type: &'a RustType,
+    // Rust name to target name and type where known, innermost block last. Java, C# and Go
+    // reject a second declaration of a name, so shadowing `let`s get a fresh name instead
+    scopes: Vec<HashMap<String, (String, Option<RustType>)>>,
+    used_names: HashSet<String>,
+    lines: Vec<String>,
+}
+
+impl<'a> Emitter<'a> {
+    fn declare(&mut self, name: &str, ty: Option<RustType>) -> String {
+        let base = self.target.identifier(name);
+        let mut target = base.clone();
+        let mut suffix = 2;
+        while self.used_names.contains(&target) {
+            target = format!("{}{}", base, suffix);
+            suffix += 1;
+        }
+        self.used_names.insert(target.clone());
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), (target.clone(), ty));
+        }
+        target
+    }
+
+    fn lookup(&self, name: &str) -> Option<&(String, Option<RustType>)> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    // Names that were never declared, such as constants and statics the import skipped, have
+    // nothing to refer to in the translation
+    fn target_name(&self, name: &str) -> Result<String, String> {
+        match self.lookup(name) {
+            Some((target, _)) => Ok(target.clone()),
+            None => Err(format!("`{}` is not a variable of the function", name)),
+        }
+    }
+
+    fn line(&mut self, depth: usize, text: String) {
+        let indent = self.syntax.indent.repeat(depth);
+        self.lines.push(format!("{}{}{}", self.body_indent, indent, text));
+    }
+
+    fn open(&mut self, depth: usize, header: String) {
+        let text = match self.syntax.blocks {
+            BlockStyle::Braces => format!("{} {{", header),
+            BlockStyle::Indentation => format!("{}:", header),
+            BlockStyle::End => header,
+        };
+        self.line(depth, text);
+    }
+
+    // `else` and `else if` lines between two blocks
+    fn reopen(&mut self, depth: usize, header: String) {
+        let text = match self.syntax.blocks {
+            BlockStyle::Braces => format!("}} {} {{", header),
+            BlockStyle::Indentation => format!("{}:", header),
+            BlockStyle::End => header,
+        };
+        self.line(depth, text);
+    }
+
+    fn close(&mut self, depth: usize) {
+        match self.syntax.blocks {
+            BlockStyle::Braces => self.line(depth, "}".to_owned()),
+            BlockStyle::End => self.line(depth, "end".to_owned()),
+            BlockStyle::Indentation => {}
+        }
+    }
+
+    fn block(&mut self, statements: &[Stmt], depth: usize) -> Result<(), String> {
+        self.scopes.push(HashMap::new());
+        let result = self.statements(statements, depth);
+        self.scopes.pop();
+        result
+    }
+
+    fn statements(&mut self, statements: &[Stmt], depth: usize) -> Result<(), String> {
+        if statements.is_empty() && self.syntax.blocks == BlockStyle::Indentation {
+            self.line(depth, "pass".to_owned());
+        }
+        for (index, statement) in statements.iter().enumerate() {
+            self.statement(statement, &statements[index + 1..], depth)?;
+        }
+        Ok(())
+    }
+
+    // `following` are the statements after this one in its block
+    fn statement(&mut self, statement: &Stmt, following: &[Stmt], depth: usize) -> Result<(), String> {
+        let end = &self.syntax.statement_end;
+        match statement {
+            Stmt::Let {
+                name,
+                mutable,
+                ty,
+                value,
+            } => {
+                let value_text = self.expression(value)?;
+                // Other targets would give an integer literal their default integer type
+                let ty = match ty {
+                    None if is_integer_literal(value) => self.use_type(name, following),
+                    ty => ty.clone(),
+                };
+                let type_name = match &ty {
+                    Some(ty) => self.target.map_type(ty, self.owner)?,
+                    None => String::new(),
+                };
+                let known_type = ty.clone().or_else(|| self.expression_type(value));
+                let target = self.declare(name, known_type);
+                let template = &self.syntax.declarations[usize::from(ty.is_some()) * 2 + usize::from(*mutable)];
+                let declaration = fill(template, &[("name", &target), ("type", &type_name), ("value", &value_text)]);
+                self.line(depth, format!("{}{}", declaration, end));
+            }
+            Stmt::Assign {
+                target,
+                op: Some(op),
+                value,
+            } if self.truncating(*op, target, value).is_some() => {
+                let target_text = self.expression(target)?;
+                let value_text = self.expression(value)?;
+                let template = self.truncating(*op, target, value).unwrap_or_default();
+                let value_text = fill(template, &[("left", &target_text), ("right", &value_text)]);
+                self.line(depth, format!("{} = {}{}", target_text, value_text, end));
+            }
+            Stmt::Assign { target, op, value } => {
+                let value_text = self.expression(value)?;
+                let operator = match op {
+                    Some(op) => format!("{}=", self.operator(*op)),
+                    None => "=".to_owned(),
+                };
+                let target = self.expression(target)?;
+                self.line(depth, format!("{} {} {}{}", target, operator, value_text, end));
+            }
+            Stmt::Expr(expr) => {
+                let text = self.expression(expr)?;
+                self.line(depth, format!("{}{}", text, end));
+            }
+            Stmt::If {
+                condition,
+                then_body,
+                else_body,
+            } => {
+                let condition = self.expression(condition)?;
+                self.open(depth, fill(&self.syntax.if_header, &[("condition", &condition)]));
+                self.block(then_body, depth + 1)?;
+                let mut else_body = else_body;
+                while !else_body.is_empty() {
+                    match else_body.as_slice() {
+                        [Stmt::If {
+                            condition,
+                            then_body,
+                            else_body: next,
+                        }] => {
+                            let condition = self.expression(condition)?;
+                            self.reopen(depth, fill(&self.syntax.else_if_header, &[("condition", &condition)]));
+                            self.block(then_body, depth + 1)?;
+                            else_body = next;
+                        }
+                        statements => {
+                            self.reopen(depth, "else".to_owned());
+                            self.block(statements, depth + 1)?;
+                            break;
+                        }
+                    }
+                }
+                self.close(depth);
+            }
+            Stmt::While { condition, body } => {
+                let condition = self.expression(condition)?;
+                self.open(depth, fill(&self.syntax.while_header, &[("condition", &condition)]));
+                self.block(body, depth + 1)?;
+                self.close(depth);
+            }
+            Stmt::ForRange {
+                variable,
+                start,
+                end: range_end,
+                inclusive,
+                body,
+            } => {
+                let start_text = self.expression(start)?;
+                let (header, end_text) = match (inclusive, &self.syntax.inclusive_for_header) {
+                    (true, Some(header)) => (header, self.expression(range_end)?),
+                    (true, None) => {
+                        let after_end = Expr::Binary(BinaryOp::Add, Box::new(range_end.clone()), Box::new(Expr::Int("1".to_owned())));
+                        (&self.syntax.for_header, self.expression(&after_end)?)
+                    }
+                    (false, _) => (&self.syntax.for_header, self.expression(range_end)?),
+                };
+                // The variable has the type of the bound, as a literal start would give it the
+                // default integer type in most targets
+                let variable_type = self.known_type(start).or_else(|| self.known_type(range_end)).unwrap_or_else(|| RustType::simple("i32"));
+                let type_name = self.target.map_type(&variable_type, self.owner)?;
+
+                self.scopes.push(HashMap::new());
+                let target = self.declare(variable, Some(variable_type));
+                self.open(
+                    depth,
+                    fill(header, &[("variable", &target), ("type", &type_name), ("start", &start_text), ("end", &end_text)]),
+                );
+                let result = self.statements(body, depth + 1);
+                self.scopes.pop();
+                result?;
+                self.close(depth);
+            }
+            Stmt::Break => self.line(depth, format!("break{}", end)),
+            Stmt::Continue => self.line(depth, format!("{}{}", self.syntax.continue_keyword, end)),
+            Stmt::Return(Some(value)) => {
+                let value = self.expression(value)?;
+                self.line(depth, format!("return {}{}", value, end));
+            }
+            Stmt::Return(None) => self.line(depth, format!("return{}", end)),
+        }
+        Ok(())
+    }
+
+    fn expression(&self, expr: &Expr) -> Result<String, String> {
+        Ok(self.expression_with_precedence(expr)?.0)
+    }
+
+    // The text of an expression and the precedence of its outermost operator
+    fn expression_with_precedence(&self, expr: &Expr) -> Result<(String, u8), String> {
+        Ok(match expr {
+            Expr::Int(digits) | Expr::Float(digits) => (digits.clone(), PRIMARY_PRECEDENCE),
+            Expr::Bool(value) => (self.syntax.booleans[usize::from(*value)].to_owned(), PRIMARY_PRECEDENCE),
+            Expr::Str(text) => (self.string_literal(text), PRIMARY_PRECEDENCE),
+            Expr::Variable(name) => (self.target_name(name)?, PRIMARY_PRECEDENCE),
+            // Tuple structs are built like calls in Rust
+            Expr::Call(name, arguments) if self.target.module.find_struct(name).is_some() => {
+                (self.construct(name, None, &positional(arguments))?, PRIMARY_PRECEDENCE)
+            }
+            Expr::Call(name, arguments) => {
+                if !self.target.module.functions.iter().any(|function| &function.name == name) {
+                    return Err(format!("`{}()` is not a function of the module", name));
+                }
+                let arguments = self.arguments(arguments)?;
+                (format!("{}({})", self.target.identifier(name), arguments), PRIMARY_PRECEDENCE)
+            }
+            Expr::SelfValue => (self.syntax.self_value.to_owned(), PRIMARY_PRECEDENCE),
+            Expr::Field(object, field) => {
+                let field = self.target.identifier(field);
+                let text = match **object {
+                    Expr::SelfValue => fill(&self.syntax.self_field, &[("field", &field)]),
+                    _ => format!("{}.{}", self.operand(object, PRIMARY_PRECEDENCE)?, field),
+                };
+                (text, PRIMARY_PRECEDENCE)
+            }
+            Expr::MethodCall(object, name, arguments) => {
+                // Methods of the standard library are spelled differently in every target
+                let method_type = self.expression_type(object).and_then(|ty| self.method_type(&ty.name, name));
+                if method_type.is_none() {
+                    return Err(format!("`.{}()` is not a method of a struct or enum of the module", name));
+                }
+                let object = self.operand(object, PRIMARY_PRECEDENCE)?;
+                let arguments = self.arguments(arguments)?;
+                (format!("{}.{}({})", object, self.target.identifier(name), arguments), PRIMARY_PRECEDENCE)
+            }
+            Expr::StaticCall(type_name, name, arguments) => {
+                let type_name = self.resolve(type_name);
+                let text = if self.is_variant(&type_name, name) {
+                    self.construct(&type_name, Some(name), &positional(arguments))?
+                } else if self.method_type(&type_name, name).is_none() {
+                    return Err(format!("`{}::{}` is not an associated function of a struct or enum of the module", type_name, name));
+                } else {
+                    let arguments = self.arguments(arguments)?;
+                    fill(
+                        &self.syntax.static_call,
+                        &[("type", &type_name), ("name", &self.target.identifier(name)), ("arguments", &arguments)],
+                    )
+                };
+                (text, PRIMARY_PRECEDENCE)
+            }
+            Expr::Associated(type_name, name) => {
+                let type_name = self.resolve(type_name);
+                if !self.is_variant(&type_name, name) {
+                    return Err(format!("`{}::{}` is not a unit variant of an enum of the module", type_name, name));
+                }
+                (self.construct(&type_name, Some(name), &[])?, PRIMARY_PRECEDENCE)
+            }
+            Expr::Construct {
+                type_name,
+                variant,
+                fields,
+            } => (self.construct(&self.resolve(type_name), variant.as_deref(), fields)?, PRIMARY_PRECEDENCE),
+            Expr::Cast(value, ty) => {
+                let wrapping = match (&self.syntax.wrapping_cast, wrapping_modulus(&ty.name)) {
+                    (Some(template), Some(modulus)) => match self.expression_type(value) {
+                        Some(from) if from.name == "f32" || from.name == "f64" => {
+                            return Err(format!("casting a float to {} saturates in Rust but not in {}", ty.name, self.target.language));
+                        }
+                        // Smaller unsigned values fit as they are
+                        Some(from) if unsigned_bits(&from.name).is_some_and(|bits| Some(bits) <= unsigned_bits(&ty.name)) => None,
+                        _ => Some((template, modulus)),
+                    },
+                    _ => None,
+                };
+                let value = self.operand(value, PRIMARY_PRECEDENCE)?;
+                let ty = self.target.map_type(ty, self.owner)?;
+                let text = match wrapping {
+                    Some((template, modulus)) => fill(template, &[("value", &value), ("type", &ty), ("modulus", modulus)]),
+                    None => fill(&self.syntax.cast, &[("value", &value), ("type", &ty)]),
+                };
+                (text, UNARY_PRECEDENCE)
+            }
+            Expr::Unary(UnaryOp::Neg, operand) => {
+                let operand = self.operand(operand, UNARY_PRECEDENCE)?;
+                // `- -x` must not turn into a decrement
+                let operand = if operand.starts_with('-') { format!("({})", operand) } else { operand };
+                (format!("-{}", operand), UNARY_PRECEDENCE)
+            }
+            Expr::Unary(UnaryOp::Not, operand) => {
+                let precedence = if self.syntax.loose_not { LOOSE_NOT_PRECEDENCE } else { UNARY_PRECEDENCE };
+                let operand = self.operand(operand, precedence)?;
+                (format!("{}{}", self.syntax.not, operand), precedence)
+            }
+            Expr::Binary(op, left, right) => {
+                if let Some(template) = self.truncating(*op, left, right) {
+                    let values = [("left", &self.expression(left)?), ("right", &self.expression(right)?)];
+                    return Ok((fill(template, &values.map(|(key, value)| (key, value.as_str()))), PRIMARY_PRECEDENCE));
+                }
+                let precedence = op.precedence();
+                let (left_text, left_precedence) = self.expression_with_precedence(left)?;
+                let left_text = self.suffixed(left_text, left, right);
+                let left_text = if left_precedence < precedence || (left_precedence == precedence && op.is_comparison()) {
+                    format!("({})", left_text)
+                } else {
+                    left_text
+                };
+                // Operators are left-associative, so an equal right operand needs parentheses
+                let (right_text, right_precedence) = self.expression_with_precedence(right)?;
+                let right_text = self.suffixed(right_text, right, left);
+                let right_text = if right_precedence <= precedence { format!("({})", right_text) } else { right_text };
+                (format!("{} {} {}", left_text, self.operator(*op), right_text), precedence)
+            }
+        })
+    }
+
+    fn arguments(&self, arguments: &[Expr]) -> Result<String, String> {
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.expression(argument))
+            .collect::<Result<Vec<String>, String>>()?;
+        Ok(arguments.join(", "))
+    }
+
+    fn is_variant(&self, type_name: &str, name: &str) -> bool {
+        let item = self.target.module.find_enum(type_name);
+        item.is_some_and(|item| item.variants.iter().any(|variant| variant.name == name))
+    }
+
+    fn resolve(&self, type_name: &str) -> String {
+        match self.owner {
+            Some(owner) if type_name == "Self" => owner.to_owned(),
+            _ => type_name.to_owned(),
+        }
+    }
+
+    // A struct or enum variant value, with the fields in declaration order since most
+    // targets pass them positionally
+    fn construct(&self, type_name: &str, variant: Option<&str>, values: &[(String, Expr)]) -> Result<String, String> {
+        let module = self.target.module;
+        let type_syntax = self.target.type_syntax;
+        let (fields, template) = match variant {
+            None => {
+                let item = module.find_struct(type_name).ok_or_else(|| format!("unknown struct {}", type_name))?;
+                (&item.fields, &type_syntax.construct)
+            }
+            Some(variant) => {
+                let item = module.find_enum(type_name).ok_or_else(|| format!("unknown enum {}", type_name))?;
+                let definition = item
+                    .variants
+                    .iter()
+                    .find(|definition| definition.name == variant)
+                    .ok_or_else(|| format!("enum {} has no variant {}", type_name, variant))?;
+                let template = if self.target.is_plain_enum(item) {
+                    &type_syntax.plain_variant_value
+                } else if definition.fields.is_empty() {
+                    &type_syntax.unit_variant_value
+                } else {
+                    &type_syntax.variant_value
+                };
+                (&definition.fields, template)
+            }
+        };
+        if values.len() != fields.len() {
+            return Err(format!("{} has {} fields but {} are given", type_name, fields.len(), values.len()));
+        }
+
+        let mut arguments = Vec::new();
+        for (name, _) in fields {
+            let (_, value) = values
+                .iter()
+                .find(|(given, _)| given == name)
+                .ok_or_else(|| format!("field {} of {} is missing", name, type_name))?;
+            arguments.push(
+                TemplateValues::default()
+                    .text("name", self.target.identifier(name))
+                    .text("value", self.expression(value)?),
+            );
+        }
+        let values = TemplateValues::default()
+            .text("type", type_name)
+            .text("enum", type_name)
+            .text("variant", variant.unwrap_or_default())
+            .list("fields", arguments);
+        Ok(render(template, &values))
+    }
+
+    // An operand that is parenthesized when it binds looser than `precedence`
+    fn operand(&self, expr: &Expr, precedence: u8) -> Result<String, String> {
+        let (text, operand_precedence) = self.expression_with_precedence(expr)?;
+        Ok(if operand_precedence < precedence { format!("({})", text) } else { text })
+    }
+
+    fn operator(&self, op: BinaryOp) -> &'a str {
+        match op {
+            BinaryOp::And => &self.syntax.and,
+            BinaryOp::Or => &self.syntax.or,
+            op => op.symbol(),
+        }
+    }
+
+    // The call that replaces `/` or `%` of two integers, see Syntax::truncating_division
+    fn truncating(&self, op: BinaryOp, left: &Expr, right: &Expr) -> Option<&'a str> {
+        let template = match op {
+            BinaryOp::Div => &self.syntax.truncating_division,
+            BinaryOp::Rem => &self.syntax.truncating_remainder,
+            _ => return None,
+        };
+        template.as_deref().filter(|_| !self.is_float(left) && !self.is_float(right))
+    }
+
+    // An integer literal `text` with the suffix the type of the other operand needs
+    fn suffixed(&self, text: String, literal: &Expr, other: &Expr) -> String {
+        let suffix = match literal {
+            Expr::Int(_) | Expr::Unary(UnaryOp::Neg, _) if is_integer_literal(literal) => {
+                self.expression_type(other).and_then(|ty| self.syntax.integer_suffixes.get(&ty.name))
+            }
+            _ => None,
+        };
+        match suffix {
+            Some(suffix) => format!("{}{}", text, suffix),
+            None => text,
+        }
+    }
+
+    // The integer type that the statements after `let name = <integer literal>` give the
+    // variable: the type it is returned, assigned or passed as, or compared with
+    fn use_type(&self, name: &str, statements: &[Stmt]) -> Option<RustType> {
+        for statement in statements {
+            let found = match statement {
+                Stmt::Let { name: other, ty, value, .. } => {
+                    let found = ty.clone().filter(|_| follows(value, name)).or_else(|| self.operand_type(name, value));
+                    // A shadowing `let` ends the variable
+                    if other == name {
+                        return found;
+                    }
+                    found
+                }
+                Stmt::Assign { target, value, .. } => {
+                    let found = if follows(value, name) {
+                        self.known_type(target)
+                    } else if follows(target, name) {
+                        self.known_type(value)
+                    } else {
+                        None
+                    };
+                    found.or_else(|| self.operand_type(name, target)).or_else(|| self.operand_type(name, value))
+                }
+                Stmt::Expr(expr) => self.operand_type(name, expr),
+                Stmt::If {
+                    condition,
+                    then_body,
+                    else_body,
+                } => self
+                    .operand_type(name, condition)
+                    .or_else(|| self.use_type(name, then_body))
+                    .or_else(|| self.use_type(name, else_body)),
+                Stmt::While { condition, body } => self.operand_type(name, condition).or_else(|| self.use_type(name, body)),
+                Stmt::ForRange {
+                    variable,
+                    start,
+                    end,
+                    body,
+                    ..
+                } => {
+                    let bounds = self.operand_type(name, start).or_else(|| self.operand_type(name, end));
+                    bounds.or_else(|| if variable == name { None } else { self.use_type(name, body) })
+                }
+                Stmt::Return(Some(value)) if follows(value, name) => Some(self.return_type.clone()),
+                Stmt::Return(Some(value)) => self.operand_type(name, value),
+                Stmt::Return(None) | Stmt::Break | Stmt::Continue => None,
+            };
+            if found.is_some() {
+                return found.filter(RustType::is_integer);
+            }
+        }
+        None
+    }
+
+    // The type given to `name` by an operator or call within `expr`
+    fn operand_type(&self, name: &str, expr: &Expr) -> Option<RustType> {
+        match expr {
+            Expr::Binary(op, left, right) if !matches!(op, BinaryOp::And | BinaryOp::Or) => {
+                let found = if follows(left, name) {
+                    self.known_type(right)
+                } else if follows(right, name) {
+                    self.known_type(left)
+                } else {
+                    None
+                };
+                found.or_else(|| self.operand_type(name, left)).or_else(|| self.operand_type(name, right))
+            }
+            Expr::Binary(_, left, right) => self.operand_type(name, left).or_else(|| self.operand_type(name, right)),
+            Expr::Call(function, arguments) => {
+                let parameters = self.target.module.functions.iter().find(|item| &item.name == function).map(|item| &item.parameters);
+                let found = parameters.and_then(|parameters| {
+                    let mut pairs = arguments.iter().zip(parameters);
+                    pairs.find(|(argument, _)| follows(argument, name)).map(|(_, (_, ty))| ty.clone())
+                });
+                found.or_else(|| arguments.iter().find_map(|argument| self.operand_type(name, argument)))
+            }
+            Expr::Unary(_, operand) | Expr::Cast(operand, _) | Expr::Field(operand, _) => self.operand_type(name, operand),
+            Expr::MethodCall(object, _, arguments) => self
+                .operand_type(name, object)
+                .or_else(|| arguments.iter().find_map(|argument| self.operand_type(name, argument))),
+            Expr::StaticCall(_, _, arguments) => arguments.iter().find_map(|argument| self.operand_type(name, argument)),
+            Expr::Construct { fields, .. } => fields.iter().find_map(|(_, value)| self.operand_type(name, value)),
+            Expr::Int(_) | Expr::Float(_) | Expr::Bool(_) | Expr::Str(_) | Expr::Variable(_) | Expr::SelfValue | Expr::Associated(..) => None,
+        }
+    }
+
+    // The type of an expression unless it only has the default type of its literals
+    fn known_type(&self, expr: &Expr) -> Option<RustType> {
+        if is_integer_literal(expr) {
+            None
+        } else {
+            self.expression_type(expr)
+        }
+    }
+
+    fn is_float(&self, expr: &Expr) -> bool {
+        matches!(self.expression_type(expr), Some(ty) if ty.name == "f32" || ty.name == "f64")
+    }
+
+    // Just enough type inference to tell integer from floating-point division
+    fn expression_type(&self, expr: &Expr) -> Option<RustType> {
+        match expr {
+            Expr::Int(_) => Some(RustType::simple("i32")),
+            Expr::Float(_) => Some(RustType::simple("f64")),
+            Expr::Bool(_) | Expr::Unary(UnaryOp::Not, _) => Some(RustType::simple("bool")),
+            Expr::Str(_) => Some(RustType::simple("&str")),
+            Expr::Variable(name) => self.lookup(name).and_then(|(_, ty)| ty.clone()),
+            Expr::Unary(UnaryOp::Neg, operand) => self.expression_type(operand),
+            Expr::Binary(op, _, _) if op.is_comparison() || matches!(op, BinaryOp::And | BinaryOp::Or) => {
+                Some(RustType::simple("bool"))
+            }
+            Expr::Binary(_, left, right) if is_integer_literal(left) => self.expression_type(right).or_else(|| self.expression_type(left)),
+            Expr::Binary(_, left, right) => self.expression_type(left).or_else(|| self.expression_type(right)),
+            Expr::Cast(_, ty) => Some(ty.clone()),
+            Expr::Call(name, _) if self.target.module.find_struct(name).is_some() => Some(RustType::simple(name)),
+            Expr::Call(name, _) => self
+                .target
+                .module
+                .functions
+                .iter()
+                .find(|function| &function.name == name)
+                .map(|function| function.return_type.clone()),
+            Expr::SelfValue => self.owner.map(RustType::simple),
+            Expr::Field(object, field) => {
+                let object_type = self.expression_type(object)?;
+                let item = self.target.module.find_struct(&object_type.name)?;
+                let (_, ty) = item.fields.iter().find(|(name, _)| name == field)?;
+                Some(resolve_self(ty, &item.name))
+            }
+            Expr::MethodCall(object, name, _) => {
+                let object_type = self.expression_type(object)?;
+                self.method_type(&object_type.name, name)
+            }
+            Expr::StaticCall(type_name, name, _) => {
+                let type_name = self.resolve(type_name);
+                if self.is_variant(&type_name, name) {
+                    Some(RustType::simple(&type_name))
+                } else {
+                    self.method_type(&type_name, name)
+                }
+            }
+            Expr::Associated(type_name, _) | Expr::Construct { type_name, .. } => Some(RustType::simple(&self.resolve(type_name))),
+        }
+    }
+
+    // Return type of a method or associated function of a type of the module
+    fn method_type(&self, type_name: &str, name: &str) -> Option<RustType> {
+        let module = self.target.module;
+        let methods = match module.find_struct(type_name) {
+            Some(item) => &item.methods,
+            None => &module.find_enum(type_name)?.methods,
+        };
+        let method = methods.iter().find(|method| method.name == name)?;
+        Some(resolve_self(&method.return_type, type_name))
+    }
+
+    fn string_literal(&self, text: &str) -> String {
+        let mut escaped = text
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+            .replace('\t', "\\t");
+        for (from, to) in &self.syntax.string_escapes {
+            escaped = escaped.replace(from.as_str(), to);
+        }
+        format!("\"{}\"", escaped)
+    }
+}
+
+// An integer literal or arithmetic on integer literals, whose type comes from its context
+fn is_integer_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Int(_) => true,
+        Expr::Unary(UnaryOp::Neg, operand) => is_integer_literal(operand),
+        Expr::Binary(op, left, right) if op.is_arithmetic() => {
+            is_integer_literal(left) && is_integer_literal(right)
+        }
+        _ => false,
+    }
+}
+
+// Whether the type of `expr` is that of the variable `name`, as in `name` or `name * 2 + 1`
+fn follows(expr: &Expr, name: &str) -> bool {
+    match expr {
+        Expr::Variable(variable) => variable == name,
+        Expr::Unary(UnaryOp::Neg, operand) => follows(operand, name),
+        Expr::Binary(op, left, right) if op.is_arithmetic() => {
+            (follows(left, name) && (follows(right, name) || is_integer_literal(right)))
+                || (is_integer_literal(left) && follows(right, name))
+        }
+        _ => false,
+    }
+}
+
+// Positional fields of tuple structs and tuple variants are named like in import_fields
+fn positional(arguments: &[Expr]) -> Vec<(String, Expr)> {
+    arguments
+        .iter()
+        .enumerate()
+        .map(|(index, argument)| (format!("value{}", index), argument.clone()))
+        .collect()
+}
+
+// Package of the translations compared with goldens
+const GOLDEN_PACKAGE: &str = "golden";
+
+// Appended to a case to print its result in Rust, which the translations must reproduce
+const REFERENCE_MAIN: &str = "\nfn main() {\n    println!(\"{}\", check());\n}\n";
+
+#[derive(Debug, Default)]
+struct GoldenSummary {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+enum RunOutcome {
+    // What the last command printed
+    Output(String),
+    // The program that is not installed
+    Missing(String),
+    Failed(String),
+}
+
+// Checks of `--golden DIR`. Every `*.rs` file of DIR is a case whose translation into each
+// language must equal `<case>/<project directory>.golden` next to it; `update` writes the
+// goldens instead. A case with `fn check() -> i64` is also compiled with rustc, and each
+// translation is run with the `check` program of its language, where the toolchain is
+// installed, and must print the same result.
+fn run_goldens(converter: &CodeConverter, directory: &Path, update: bool) -> Result<GoldenSummary, String> {
+    let entries = fs::read_dir(directory).map_err(|e| format!("Failed to read {}: {}", directory.display(), e))?;
+    let mut cases: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "rs"))
+        .collect();
+    cases.sort();
+    if cases.is_empty() {
+        return Err(format!("{} has no .rs cases", directory.display()));
+    }
+
+    let scratch = env::temp_dir().join(format!("translator-goldens-{}", process::id()));
+    let mut summary = GoldenSummary::default();
+    for case in &cases {
+        let name = case.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let source = fs::read_to_string(case).map_err(|e| format!("Failed to read {}: {}", case.display(), e))?;
+        let imported = match import_rust_source(&source) {
+            Ok(imported) if imported.unsupported.is_empty() => imported,
+            Ok(imported) => {
+                for unsupported in &imported.unsupported {
+                    println!("{} FAILED: {}:{}", name, case.display(), unsupported);
+                }
+                summary.failed += 1;
+                continue;
+            }
+            Err(err) => {
+                println!("{} FAILED: {}", name, err);
+                summary.failed += 1;
+                continue;
+            }
+        };
+        let module = &imported.module;
+        let checked = module.functions.iter().any(|function| function.name == "check" && function.parameters.is_empty());
+        let case_scratch = scratch.join(&name);
+        let mut reference = None;
+
+        for language in &converter.language_converters {
+            let language = language.as_ref();
+            let label = format!("{} {}", name, language.details().name());
+            let mut errors = Vec::new();
+            let files = converter.project_files(language, module, GOLDEN_PACKAGE, true, &mut |item, err| {
+                errors.push(format!("{}: {}", item, err))
+            });
+            if !errors.is_empty() {
+                println!("{}: FAILED to translate {}", label, errors.join("; "));
+                summary.failed += 1;
+                continue;
+            }
+
+            let golden = directory.join(&name).join(format!("{}.golden", language.project().directory));
+            let translation = golden_text(&files);
+            if update {
+                match write_file(&golden, &translation, Overwrite::Always) {
+                    Ok(outcome) => println!("{}: {} {}", label, outcome, golden.display()),
+                    Err(err) => {
+                        println!("{}: FAILED {}", label, err);
+                        summary.failed += 1;
+                    }
+                }
+            } else {
+                match fs::read_to_string(&golden) {
+                    Ok(expected) if expected == translation => {
+                        println!("{}: golden ok", label);
+                        summary.passed += 1;
+                    }
+                    Ok(expected) => {
+                        println!("{}: golden FAILED, {}", label, first_difference(&expected, &translation));
+                        summary.failed += 1;
+                    }
+                    Err(_) => {
+                        println!("{}: golden FAILED, {} is missing; run with --update", label, golden.display());
+                        summary.failed += 1;
+                    }
+                }
+            }
+
+            if !checked {
+                continue;
+            }
+            let Some(check) = language.check() else {
+                println!("{}: run skipped, the language has no check program", label);
+                summary.skipped += 1;
+                continue;
+            };
+            let expected = reference.get_or_insert_with(|| run_reference(&source, &case_scratch.join("reference")));
+            let expected = match expected {
+                RunOutcome::Output(output) => output.clone(),
+                RunOutcome::Missing(program) => {
+                    println!("{}: run skipped, {} is not installed for the Rust result", label, program);
+                    summary.skipped += 1;
+                    continue;
+                }
+                RunOutcome::Failed(err) => {
+                    println!("{}: run FAILED, the Rust case does not run: {}", label, err);
+                    summary.failed += 1;
+                    continue;
+                }
+            };
+
+            let root = case_scratch.join(&language.project().directory);
+            match run_check(converter, language, module, check, &root) {
+                RunOutcome::Output(output) if output.trim_end() == expected.trim_end() => {
+                    println!("{}: run ok, printed {}", label, output.trim_end());
+                    summary.passed += 1;
+                }
+                RunOutcome::Output(output) => {
+                    println!("{}: run FAILED, printed {} instead of {}", label, output.trim_end(), expected.trim_end());
+                    summary.failed += 1;
+                }
+                RunOutcome::Missing(program) => {
+                    println!("{}: run skipped, {} is not installed", label, program);
+                    summary.skipped += 1;
+                }
+                RunOutcome::Failed(err) => {
+                    println!("{}: run FAILED, {}", label, err);
+                    summary.failed += 1;
+                }
+            }
+        }
+    }
+    // The scratch directory only holds build products
+    let _ = fs::remove_dir_all(&scratch);
+    Ok(summary)
+}
+
+// All files of a project, each after a `==> path <==` line
+fn golden_text(files: &[(String, String)]) -> String {
+    files
+        .iter()
+        .map(|(path, content)| format!("==> {} <==\n{}", path, content))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for number in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(expected), Some(actual)) if expected == actual => continue,
+            (Some(expected), Some(actual)) => return format!("line {} is `{}` instead of `{}`", number, actual, expected),
+            (Some(expected), None) => return format!("line {} `{}` is missing", number, expected),
+            (None, Some(actual)) => return format!("line {} `{}` is extra", number, actual),
+            (None, None) => break,
+        }
+    }
+    "the files differ in line endings".to_owned()
+}
+
+fn run_reference(source: &str, directory: &Path) -> RunOutcome {
+    let program = format!("{}{}", source, REFERENCE_MAIN);
+    if let Err(err) = write_file(&directory.join("main.rs"), &program, Overwrite::Always) {
+        return RunOutcome::Failed(err);
+    }
+    let commands = [
+        vec!["rustc".to_owned(), "-o".to_owned(), "main".to_owned(), "main.rs".to_owned()],
+        vec!["./main".to_owned()],
+    ];
+    run_commands(&commands, directory)
+}
+
+// Writes the translation with the check program below `root` and runs it
+fn run_check(
+    converter: &CodeConverter,
+    language: &dyn LanguageConverter,
+    module: &Module,
+    check: &CheckProgram,
+    root: &Path,
+) -> RunOutcome {
+    let package = check.package.as_deref().unwrap_or(GOLDEN_PACKAGE);
+    let values = package_values(package, language.details().file_extension());
+    let mut files = converter.project_files(language, module, package, true, &mut |_, _| {});
+    for file in &check.files {
+        files.push((render(&file.name, &values), render(&file.content, &values)));
+    }
+    for (path, content) in &files {
+        if let Err(err) = write_file(&root.join(path), content, Overwrite::Always) {
+            return RunOutcome::Failed(err);
+        }
+    }
+    let commands: Vec<Vec<String>> = check
+        .commands
+        .iter()
+        .map(|command| command.iter().map(|argument| render(argument, &values)).collect())
+        .collect();
+    run_commands(&commands, root)
+}
+
+// Runs `commands` one after another in `directory` and returns what the last one printed
+fn run_commands(commands: &[Vec<String>], directory: &Path) -> RunOutcome {
+    let mut output = String::new();
+    for command in commands {
+        let Some((program, arguments)) = command.split_first() else {
+            continue;
+        };
+        // `./name` is a program built in `directory`
+        let path = match program.strip_prefix("./") {
+            Some(built) => directory.join(built),
+            None => PathBuf::from(program),
+        };
+        match process::Command::new(&path).args(arguments).current_dir(directory).output() {
+            Ok(result) if result.status.success() => output = String::from_utf8_lossy(&result.stdout).into_owned(),
+            Ok(result) => {
+                let stderr = String::from_utf8_lossy(&result.stderr);
+                let message: Vec<&str> = stderr.lines().take(5).collect();
+                return RunOutcome::Failed(format!("`{}` failed: {}", command.join(" "), message.join(" | ")));
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return RunOutcome::Missing(program.clone()),
+            Err(err) => return RunOutcome::Failed(format!("Failed to run {}: {}", program, err)),
+        }
+    }
+    RunOutcome::Output(output)
+}
+
+// Translated when no file is given
+const DEMO_SOURCE: &str = r#"
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+fn multiply(a: i32, b: i32) -> i32 {
+    a * b
+}
+
+fn sum_of_even(n: i32) -> i32 {
+    let mut total = 0;
+    for i in 1..=n {
+        if i % 2 == 0 {
+            total += i;
+        }
+    }
+    total
+}
+
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+impl Point {
+    fn new(x: f64, y: f64) -> Self {
+        Point { x, y }
+    }
+
+    fn length_squared(&self) -> f64 {
+        self.x * self.x + self.y * self.y
+    }
+
+    fn translate(&mut self, dx: f64, dy: f64) {
+        self.x += dx;
+        self.y += dy;
+    }
+}
+
+enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+enum Shape {
+    Circle(f64),
+    Rectangle { width: f64, height: f64 },
+    Empty,
+}
+
+fn unit_square() -> Shape {
+    Shape::Rectangle { width: 1.0, height: 1.0 }
+}
+"#;
+
+// `translator [file.rs|file.py] [--lang name]... [--types types.toml] [--languages dir] [--out dir
+// [--package name] [--build-files] [--overwrite never|always|backup]]`; without `--out` the
+// translations are printed. `translator --export-languages dir` writes the built-in language
+// files, which `--languages` reads back after editing along with any new ones.
+// `translator --golden dir [--update] [--lang name]... [--languages dir]` checks the
+// translations of the cases in `dir`, see run_goldens.
+fn main() {
+    let mut source = None;
+    let mut type_overrides = HashMap::new();
+    let mut directory = None;
+    let mut package = "translated".to_owned();
+    let mut build_files = false;
+    let mut overwrite = Overwrite::Never;
+    let mut languages = Vec::new();
+    let mut language_directory = None;
+    let mut export_directory = None;
+    let mut golden_directory = None;
+    let mut update_goldens = false;
+
+    let fail = |message: String| -> ! {
+        eprintln!("Error: {}", message);
+        process::exit(1);
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| args.next().unwrap_or_else(|| fail(format!("{} needs {}", arg, what)));
+        match arg.as_str() {
+            "--types" => type_overrides = load_type_overrides(Path::new(&value("a file"))).unwrap_or_else(|err| fail(err)),
+            "--out" => directory = Some(PathBuf::from(value("a directory"))),
+            "--package" => {
+                package = value("a name");
+                if !is_valid_package(&package) {
+                    fail(format!("invalid package name `{}`, expected identifiers separated by dots", package));
+                }
+            }
+            "--overwrite" => {
+                overwrite = match value("a policy").as_str() {
+                    "never" => Overwrite::Never,
+                    "always" => Overwrite::Always,
+                    "backup" => Overwrite::Backup,
+                    other => fail(format!("unknown overwrite policy `{}`, expected never, always or backup", other)),
+                }
+            }
+            "--lang" => languages.push(value("a language")),
+            "--languages" => language_directory = Some(PathBuf::from(value("a directory"))),
+            "--export-languages" => export_directory = Some(PathBuf::from(value("a directory"))),
+            "--golden" => golden_directory = Some(PathBuf::from(value("a directory"))),
+            "--update" => update_goldens = true,
+            "--build-files" => build_files = true,
+            _ => source = Some(arg),
+        }
+    }
+
+    if let Some(directory) = export_directory {
+        if export_languages(&directory, overwrite) > 0 {
+            process::exit(1);
+        }
+        return;
+    }
+
+    let mut specs = LanguageSpec::builtins();
+    if let Some(directory) = language_directory {
+        merge_languages(&mut specs, load_languages(&directory).unwrap_or_else(|err| fail(err)));
+    }
+    let mut code_converter = CodeConverter::new(type_overrides, specs);
+    if !languages.is_empty() {
+        code_converter.select_languages(&languages).unwrap_or_else(|err| fail(err));
+    }
+    if let Some(directory) = golden_directory {
+        let summary = run_goldens(&code_converter, &directory, update_goldens).unwrap_or_else(|err| fail(err));
+        println!("{} passed, {} failed, {} skipped", summary.passed, summary.failed, summary.skipped);
+        if summary.failed > 0 {
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Translate the items of a Rust or Python file, or the built-in examples without an argument
+    let (origin, imported) = match source {
+        Some(path) => {
+            let imported = if path.ends_with(".py") {
+                import_python_file(Path::new(&path))
+            } else {
+                import_rust_file(Path::new(&path))
+            };
+            let imported = imported.unwrap_or_else(|err| fail(err));
+            (path, imported)
+        }
+        None => ("examples".to_owned(), import_rust_source(DEMO_SOURCE).expect("The examples are valid Rust")),
+    };
+    // An item that cannot be translated is skipped as a whole
+    for unsupported in &imported.unsupported {
+        eprintln!("{}:{}", origin, unsupported);
+    }
+
+    match directory {
+        Some(directory) => {
+            let options = OutputOptions {
+                directory,
+                package,
+                build_files,
+                overwrite,
+            };
+            let failures = code_converter.write_projects(&imported.module, &options);
+            if failures > 0 {
+                process::exit(1);
+            }
+        }
+        None => code_converter.convert_to_languages(&imported.module),
+    }
 }
@@ -0,0 +1,54 @@
+// Integer arithmetic, loops over ranges, casts, including ones that wrap around, and
+// division of negative numbers
+
+fn add(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    let mut x = a;
+    let mut y = b;
+    while y != 0 {
+        let rest = x % y;
+        x = y;
+        y = rest;
+    }
+    x
+}
+
+fn factorial(n: i64) -> i64 {
+    let mut product = 1;
+    for i in 1..=n {
+        product *= i;
+    }
+    product
+}
+
+fn sum_of_squares(n: i64) -> i64 {
+    let mut total = 0;
+    for i in 0..n {
+        total += i * i;
+    }
+    total
+}
+
+fn average(values_sum: i64, count: i64) -> f64 {
+    values_sum as f64 / count as f64
+}
+
+fn truncated(mut a: i64, b: i64) -> i64 {
+    a -= 1;
+    let quotient = a / b;
+    a %= b;
+    quotient * 100 + a
+}
+
+fn low_byte(value: i64) -> i64 {
+    value as u8 as i64
+}
+
+fn check() -> i64 {
+    let halves = (average(7, 2) * 2.0) as i64;
+    add(2, 3) + gcd(84, 36) * 10 + factorial(10) + sum_of_squares(10) * 1000 + halves + 17 / 5
+        + truncated(-6, 2) * 10000000 + low_byte(-212) * 1000000000
+}
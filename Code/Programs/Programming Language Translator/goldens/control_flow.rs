@@ -0,0 +1,71 @@
+// Branches, early returns, `break`, `continue` and boolean operators
+
+fn collatz_steps(start: i64) -> i64 {
+    let mut n = start;
+    let mut steps = 0;
+    while n != 1 {
+        if n % 2 == 0 {
+            n = n / 2;
+        } else {
+            n = 3 * n + 1;
+        }
+        steps += 1;
+    }
+    steps
+}
+
+fn is_prime(n: i64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    let mut divisor = 2;
+    while divisor * divisor <= n {
+        if n % divisor == 0 {
+            return false;
+        }
+        divisor += 1;
+    }
+    true
+}
+
+fn count_primes(limit: i64) -> i64 {
+    let mut count = 0;
+    for n in 0..limit {
+        if !is_prime(n) {
+            continue;
+        }
+        count += 1;
+    }
+    count
+}
+
+fn first_multiple(factor: i64, above: i64) -> i64 {
+    let mut candidate = above + 1;
+    while true {
+        if candidate % factor == 0 {
+            break;
+        }
+        candidate += 1;
+    }
+    candidate
+}
+
+fn classify(n: i64) -> i64 {
+    if n < 0 {
+        -1
+    } else if n == 0 {
+        0
+    } else if n < 10 && !(n == 5 || n == 7) {
+        1
+    } else {
+        2
+    }
+}
+
+fn check() -> i64 {
+    let mut code = collatz_steps(27) * 100000 + count_primes(100) * 1000 + first_multiple(7, 50);
+    if is_prime(97) && !is_prime(91) {
+        code += classify(-4) + classify(0) * 10 + classify(3) * 100 + classify(5) * 1000;
+    }
+    code
+}
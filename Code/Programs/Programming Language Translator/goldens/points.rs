@@ -0,0 +1,65 @@
+// A struct with associated functions and methods, and enums that are only constructed
+
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+impl Point {
+    fn new(x: i64, y: i64) -> Self {
+        Point { x, y }
+    }
+
+    fn manhattan(&self) -> i64 {
+        let mut total = 0;
+        if self.x < 0 {
+            total -= self.x;
+        } else {
+            total += self.x;
+        }
+        if self.y < 0 {
+            total -= self.y;
+        } else {
+            total += self.y;
+        }
+        total
+    }
+
+    fn translate(&mut self, dx: i64, dy: i64) {
+        self.x += dx;
+        self.y += dy;
+    }
+}
+
+enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+enum Shape {
+    Circle(f64),
+    Rectangle { width: f64, height: f64 },
+    Empty,
+}
+
+fn heading() -> Direction {
+    Direction::West
+}
+
+fn unit_square() -> Shape {
+    Shape::Rectangle { width: 1.0, height: 1.0 }
+}
+
+fn walk(steps: i64) -> i64 {
+    let mut point = Point::new(3, -4);
+    for step in 0..steps {
+        point.translate(-1, 2 + step % 2);
+    }
+    point.manhattan() * 100 + point.x
+}
+
+fn check() -> i64 {
+    walk(5) + Point::new(-2, 7).manhattan()
+}